@@ -0,0 +1,276 @@
+use std::{net::SocketAddr, path::{Path, PathBuf}, sync::Mutex};
+
+use serde::{Deserialize, Deserializer, Serialize};
+
+use crate::{file_manager::UploadActivation, input_source::InputConfig, media_probe::MediaConfig, scheduler::{CronSchedule, ScheduleConfig}, sync::SyncConfig, url_fetcher::FetchConfig, video_player::{LogoOverlay, VideoAdjustment, VideoPlayerConfig}, webserver::WebServer};
+
+pub const CONFIG_FILENAME: &str = "config.json";
+
+#[derive(Debug, thiserror::Error)]
+pub enum ConfigError {
+    #[error("TokioIoError reason = '{0}'")]
+    TokioIoError(#[from] tokio::io::Error),
+
+    #[error("SerdeJsonError reason = '{0}'")]
+    SerdeJsonError(#[from] serde_json::Error),
+}
+
+/// Device configuration, missing fields take default values.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Config {
+    /// Image shown over video since startup.
+    #[serde(default)]
+    pub logo: Option<LogoOverlay>,
+
+    /// Brightness, contrast, saturation, gamma and hue.
+    #[serde(default)]
+    pub adjustment: VideoAdjustment,
+
+    #[serde(default)]
+    pub splash: SplashConfig,
+
+    #[serde(default)]
+    pub player: VideoPlayerConfig,
+
+    #[serde(default)]
+    pub media: MediaConfig,
+
+    #[serde(default)]
+    pub upload: UploadConfig,
+
+    /// Media downloaded by URL.
+    #[serde(default)]
+    pub fetch: FetchConfig,
+
+    /// Playback in sync with other devices.
+    #[serde(default)]
+    pub sync: SyncConfig,
+
+    /// Time-of-day playlists and display power.
+    #[serde(default)]
+    pub schedule: ScheduleConfig,
+
+    /// Keypads, named pipe and socket triggering playback actions.
+    #[serde(default)]
+    pub input: InputConfig,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct UploadConfig {
+    /// Used when upload request does not set activation.
+    #[serde(default)]
+    pub activation: UploadActivation,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SplashConfig {
+    /// Show device name, IP address and web URL when nothing is played.
+    #[serde(default = "SplashConfig::default_enabled")]
+    pub enabled: bool,
+
+    /// Hostname is used if missing.
+    #[serde(default)]
+    pub device_name: Option<String>,
+}
+
+impl SplashConfig {
+    fn default_enabled() -> bool {
+        true
+    }
+}
+
+impl Default for SplashConfig {
+    fn default() -> Self {
+        Self {
+            enabled: Self::default_enabled(),
+            device_name: None,
+        }
+    }
+}
+
+/// Sections editable over HTTP, missing ones are kept, 'input' is set in config file only.
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ConfigPatch {
+    /// Null hides logo.
+    #[serde(default, deserialize_with = "ConfigPatch::deserialize_present")]
+    pub logo: Option<Option<LogoOverlay>>,
+
+    #[serde(default)]
+    pub adjustment: Option<VideoAdjustment>,
+
+    #[serde(default)]
+    pub splash: Option<SplashConfig>,
+
+    #[serde(default)]
+    pub player: Option<VideoPlayerConfig>,
+
+    #[serde(default)]
+    pub media: Option<MediaConfig>,
+
+    #[serde(default)]
+    pub upload: Option<UploadConfig>,
+
+    #[serde(default)]
+    pub fetch: Option<FetchConfig>,
+
+    #[serde(default)]
+    pub sync: Option<SyncConfig>,
+
+    #[serde(default)]
+    pub schedule: Option<ScheduleConfig>,
+}
+
+impl ConfigPatch {
+    fn deserialize_present<'de, D, T>(deserializer: D) -> Result<Option<T>, D::Error>
+    where
+        D: Deserializer<'de>,
+        T: Deserialize<'de>,
+    {
+        T::deserialize(deserializer).map(Some)
+    }
+
+    /// Rejects limits out of range and settings kept in config file only.
+    pub fn validate(&self, config: &Config) -> Result<(), String> {
+        if let Some(fetch) = &self.fetch {
+            let max_size_bytes = WebServer::MAX_VIDEO_FILESIZE_BYTES as u64;
+            if fetch.max_size_bytes == 0 || fetch.max_size_bytes > max_size_bytes {
+                return Err(format!("fetch.max_size_bytes must be between 1 and {max_size_bytes}"));
+            }
+            if fetch.read_timeout_secs == 0 {
+                return Err("fetch.read_timeout_secs must be positive".to_string());
+            }
+        }
+
+        if let Some(sync) = &self.sync
+            && sync.address.parse::<SocketAddr>().is_err()
+        {
+            return Err(format!("sync.address '{}' must be IP address and port", sync.address));
+        }
+
+        if let Some(schedule) = &self.schedule {
+            // Display power runs commands, backend is set in config file only
+            if schedule.display != config.schedule.display {
+                return Err("schedule.display cannot be changed".to_string());
+            }
+            for rule in &schedule.rules {
+                CronSchedule::parse(&rule.cron).map_err(|e| e.to_string())?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Returns changed sections which take effect after restart, logo and adjustment are applied immediately.
+    pub fn apply(self, config: &mut Config) -> Vec<&'static str> {
+        if let Some(logo) = self.logo {
+            config.logo = logo;
+        }
+        if let Some(adjustment) = self.adjustment {
+            config.adjustment = adjustment.clamped();
+        }
+
+        let mut restart_required = vec![];
+        Self::replace("splash", &mut config.splash, self.splash, &mut restart_required);
+        Self::replace("player", &mut config.player, self.player, &mut restart_required);
+        Self::replace("media", &mut config.media, self.media, &mut restart_required);
+        Self::replace("upload", &mut config.upload, self.upload, &mut restart_required);
+        Self::replace("fetch", &mut config.fetch, self.fetch, &mut restart_required);
+        Self::replace("sync", &mut config.sync, self.sync, &mut restart_required);
+        Self::replace("schedule", &mut config.schedule, self.schedule, &mut restart_required);
+        restart_required
+    }
+
+    fn replace<T: Serialize>(name: &'static str, section: &mut T, value: Option<T>, restart_required: &mut Vec<&'static str>) {
+        let Some(value) = value else {
+            return;
+        };
+        if serde_json::to_value(&*section).ok() != serde_json::to_value(&value).ok() {
+            restart_required.push(name);
+        }
+        *section = value;
+    }
+}
+
+impl Config {
+    pub async fn load(path: &Path) -> Result<Self, ConfigError> {
+        let content = tokio::fs::read(path).await?;
+        Ok(serde_json::from_slice(&content)?)
+    }
+
+    pub async fn save(&self, path: &Path) -> Result<(), ConfigError> {
+        let content = serde_json::to_vec_pretty(self)?;
+        tokio::fs::write(path, content).await?;
+        Ok(())
+    }
+}
+
+/// Shared configuration, changes are persisted.
+pub struct ConfigStore {
+    path: PathBuf,
+    config: Mutex<Config>,
+}
+
+impl ConfigStore {
+    /// Missing or malformed file results in default config.
+    pub async fn load(path: PathBuf) -> Self {
+        let config = match Config::load(&path).await {
+            Ok(config) => {
+                tracing::info!("Loaded config from {path:?}");
+                config
+            },
+            Err(ConfigError::TokioIoError(e)) if e.kind() == std::io::ErrorKind::NotFound => {
+                tracing::info!("Config {path:?} not found, using default");
+                Config::default()
+            },
+            Err(e) => {
+                tracing::warn!("Could not load config {path:?}, using default, reason = {e}");
+                Config::default()
+            },
+        };
+
+        Self { path, config: Mutex::new(config) }
+    }
+
+    pub fn get(&self) -> Config {
+        self.config.lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+            .clone()
+    }
+
+    /// Modifies config and saves it, returns updated config.
+    pub async fn update<F: FnOnce(&mut Config)>(&self, modify: F) -> Result<Config, ConfigError> {
+        let config = {
+            let mut config = self.config.lock()
+                .unwrap_or_else(|poisoned| poisoned.into_inner());
+            modify(&mut config);
+            config.clone()
+        };
+
+        config.save(&self.path).await
+            .inspect_err(|e| tracing::warn!("Could not save config {:?}, reason = {e}", self.path))?;
+        Ok(config)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_config_patch() {
+        assert!(serde_json::from_str::<ConfigPatch>(r#"{"input": {"socket": "/etc/passwd"}}"#).is_err());
+
+        let config = Config::default();
+        let patch: ConfigPatch = serde_json::from_str(r#"{"fetch": {"max_size_bytes": 1073741824}}"#).unwrap();
+        assert!(patch.validate(&config).is_err());
+        let patch: ConfigPatch = serde_json::from_str(r#"{"schedule": {"display": {"backend": "xset"}}}"#).unwrap();
+        assert!(patch.validate(&config).is_err());
+
+        let mut config: Config = serde_json::from_str(r#"{"logo": {"file": "/tmp/logo.png"}}"#).unwrap();
+        let patch: ConfigPatch = serde_json::from_str(r#"{"logo": null, "splash": {"enabled": true}, "upload": {"activation": {"mode": "after_loop"}}}"#).unwrap();
+        patch.validate(&config).unwrap();
+        assert_eq!(patch.apply(&mut config), ["upload"]);
+        assert!(config.logo.is_none());
+        assert_eq!(config.upload.activation, UploadActivation::AfterLoop);
+    }
+}
@@ -0,0 +1,258 @@
+use std::time::Duration;
+
+use serde::Serialize;
+use tokio::process::Command;
+use tokio_util::sync::CancellationToken;
+
+use crate::{wifi_manager::{try_getting_ip, WifiProgress}, WiFiCredentialsProcedure};
+
+/// Network state observed by monitor.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(tag = "state", rename_all = "snake_case")]
+pub enum ConnectivityState {
+    Unknown,
+    LinkDown,
+    GatewayUnreachable {
+        ip_address: String,
+        gateway: Option<String>,
+    },
+    Online {
+        ip_address: String,
+        gateway: String,
+    },
+    Reconnecting {
+        attempt: u32,
+    },
+}
+
+impl std::fmt::Display for ConnectivityState {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ConnectivityState::Unknown => write!(f, "unknown"),
+            ConnectivityState::LinkDown => write!(f, "link down"),
+            ConnectivityState::GatewayUnreachable { ip_address, gateway } => write!(f, "gateway {gateway:?} unreachable, ip={ip_address}"),
+            ConnectivityState::Online { ip_address, gateway } => write!(f, "online, ip={ip_address}, gateway={gateway}"),
+            ConnectivityState::Reconnecting { attempt } => write!(f, "reconnecting, attempt {attempt}"),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct ConnectivityMonitorConfig {
+    pub check_interval: Duration,
+    pub ping_timeout: Duration,
+    /// Consecutive failed checks before reconnecting.
+    pub reconnect_after_failures: u32,
+    pub reconnect_timeout: Duration,
+}
+
+impl Default for ConnectivityMonitorConfig {
+    fn default() -> Self {
+        Self {
+            check_interval: Duration::from_secs(10),
+            ping_timeout: Duration::from_secs(2),
+            reconnect_after_failures: 3,
+            reconnect_timeout: Duration::from_secs(45),
+        }
+    }
+}
+
+pub struct ConnectivityMonitor {
+    config: ConnectivityMonitorConfig,
+    wifi_manager: Option<WiFiCredentialsProcedure>,
+}
+
+pub struct ConnectivityMonitorHandler {
+    task_handle: tokio::task::JoinHandle<()>,
+    cancel_token: CancellationToken,
+    state_rx: tokio::sync::watch::Receiver<ConnectivityState>,
+}
+
+impl ConnectivityMonitorHandler {
+    pub fn subscribe(&self) -> tokio::sync::watch::Receiver<ConnectivityState> {
+        self.state_rx.clone()
+    }
+
+    pub fn state(&self) -> ConnectivityState {
+        self.state_rx.borrow().clone()
+    }
+
+    pub async fn shutdown(self) -> Result<(), tokio::task::JoinError> {
+        self.cancel_token.cancel();
+        self.task_handle.await
+    }
+}
+
+impl ConnectivityMonitor {
+    /// WiFi manager is used to avoid reconnecting while credentials are being applied.
+    pub fn new(config: ConnectivityMonitorConfig, wifi_manager: Option<WiFiCredentialsProcedure>) -> Self {
+        Self { config, wifi_manager }
+    }
+
+    pub fn start(self) -> ConnectivityMonitorHandler {
+        let (state_tx, state_rx) = tokio::sync::watch::channel(ConnectivityState::Unknown);
+        let cancel_token = CancellationToken::new();
+        let cancel_token_shared = cancel_token.clone();
+
+        let task_handle = tokio::spawn(async move {
+            tracing::info!("Starting connectivity monitor");
+            let mut failed_checks = 0;
+            let mut reconnect_attempt = 0;
+
+            loop {
+                let state = self.check().await;
+                if matches!(state, ConnectivityState::Online { .. }) {
+                    failed_checks = 0;
+                    reconnect_attempt = 0;
+                } else {
+                    failed_checks += 1;
+                }
+                Self::publish(&state_tx, state);
+
+                if failed_checks >= self.config.reconnect_after_failures && !self.is_wifi_procedure_running() {
+                    failed_checks = 0;
+                    reconnect_attempt += 1;
+                    Self::publish(&state_tx, ConnectivityState::Reconnecting { attempt: reconnect_attempt });
+
+                    tokio::select! {
+                        _ = cancel_token_shared.cancelled() => break,
+                        result = tokio::time::timeout(self.config.reconnect_timeout, reconnect()) => {
+                            match result {
+                                Ok(Ok(())) => tracing::info!("Connectivity monitor reconnected to saved network."),
+                                Ok(Err(e)) => tracing::warn!("Connectivity monitor could not reconnect, reason = {e}"),
+                                Err(_) => tracing::warn!("Connectivity monitor reconnect timed out."),
+                            }
+                        },
+                    }
+                }
+
+                tokio::select! {
+                    _ = cancel_token_shared.cancelled() => break,
+                    _ = tokio::time::sleep(self.config.check_interval) => {},
+                }
+            }
+            tracing::info!("Connectivity monitor shutting down.");
+        });
+
+        ConnectivityMonitorHandler { task_handle, cancel_token, state_rx }
+    }
+
+    fn publish(state_tx: &tokio::sync::watch::Sender<ConnectivityState>, state: ConnectivityState) {
+        state_tx.send_if_modified(|current_state| {
+            if *current_state == state {
+                return false;
+            }
+            tracing::info!("Connectivity changed: {current_state} -> {state}");
+            *current_state = state;
+            true
+        });
+    }
+
+    fn is_wifi_procedure_running(&self) -> bool {
+        self.wifi_manager.as_ref().is_some_and(|wifi_manager| matches!(
+            wifi_manager.progress(),
+            WifiProgress::Scanning
+            | WifiProgress::Associating { .. }
+            | WifiProgress::Configuring { .. }
+            | WifiProgress::Dhcp { .. }
+        ))
+    }
+
+    async fn check(&self) -> ConnectivityState {
+        let Some(ip_address) = try_getting_ip().await else {
+            return ConnectivityState::LinkDown;
+        };
+
+        let Some(gateway) = default_gateway().await else {
+            return ConnectivityState::GatewayUnreachable { ip_address, gateway: None };
+        };
+
+        if is_reachable(&gateway, self.config.ping_timeout).await {
+            ConnectivityState::Online { ip_address, gateway }
+        } else {
+            ConnectivityState::GatewayUnreachable { ip_address, gateway: Some(gateway) }
+        }
+    }
+}
+
+async fn default_gateway() -> Option<String> {
+    let output = Command::new("ip")
+        .args(["-4", "route", "show", "default"])
+        .output()
+        .await
+        .ok()?;
+
+    if !output.status.success() {
+        return None;
+    }
+
+    parse_default_gateway(&String::from_utf8_lossy(&output.stdout))
+}
+
+async fn is_reachable(address: &str, timeout_duration: Duration) -> bool {
+    let timeout_secs = timeout_duration.as_secs().max(1).to_string();
+    Command::new("ping")
+        .args(["-c", "1", "-W", &timeout_secs, address])
+        .kill_on_drop(true)
+        .output()
+        .await
+        .is_ok_and(|output| output.status.success())
+}
+
+/// Lets NetworkManager pick best saved network for WiFi device.
+async fn reconnect() -> Result<(), std::io::Error> {
+    let output = Command::new("nmcli")
+        .args(["-t", "-f", "DEVICE,TYPE", "dev"])
+        .output()
+        .await?;
+
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let device = parse_wifi_device(&stdout)
+        .ok_or_else(|| std::io::Error::new(std::io::ErrorKind::NotFound, "No WiFi device"))?;
+
+    let output = Command::new("nmcli")
+        .args(["dev", "connect", &device])
+        .kill_on_drop(true)
+        .output()
+        .await?;
+
+    if output.status.success() {
+        Ok(())
+    } else {
+        let stderr_str = String::from_utf8_lossy(&output.stderr);
+        Err(std::io::Error::other(format!("nmcli failed with status {}: {}", output.status, stderr_str.trim())))
+    }
+}
+
+fn parse_default_gateway(ip_route_output: &str) -> Option<String> {
+    ip_route_output.lines()
+        .filter_map(|line| {
+            let mut words = line.split_whitespace();
+            words.find(|&word| word == "via")?;
+            words.next()
+        })
+        .map(str::to_string)
+        .next()
+}
+
+fn parse_wifi_device(nmcli_dev_output: &str) -> Option<String> {
+    nmcli_dev_output.lines()
+        .filter_map(|line| line.split_once(':'))
+        .find(|(_, device_type)| *device_type == "wifi")
+        .map(|(device, _)| device.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_nmcli_and_route_output() {
+        let route = "default via 192.168.1.1 dev wlan0 proto dhcp src 192.168.1.23 metric 600\n";
+        assert_eq!(parse_default_gateway(route), Some("192.168.1.1".to_string()));
+        assert_eq!(parse_default_gateway(""), None);
+
+        let devices = "eth0:ethernet\nwlan0:wifi\np2p-dev-wlan0:wifi-p2p\nlo:loopback\n";
+        assert_eq!(parse_wifi_device(devices), Some("wlan0".to_string()));
+    }
+}
@@ -0,0 +1,487 @@
+"use strict";
+
+// Served from binary, no external resources so it works without internet.
+
+const MAX_EVENTS = 50;
+const UPLOAD_CHUNK_BYTES = 4 * 1024 * 1024;
+const UPLOAD_RETRIES = 20;
+const UPLOAD_RETRY_DELAY_MS = 3000;
+
+let playlistItems = [];
+let currentStatus = null;
+
+function byId(id) {
+    return document.getElementById(id);
+}
+
+function formatTime(ms) {
+    if (ms === null || ms === undefined) {
+        return "--:--";
+    }
+    const seconds = Math.floor(ms / 1000);
+    return `${Math.floor(seconds / 60)}:${String(seconds % 60).padStart(2, "0")}`;
+}
+
+function formatSize(bytes) {
+    if (bytes >= 1024 * 1024) {
+        return `${(bytes / 1024 / 1024).toFixed(1)} MB`;
+    }
+    return `${(bytes / 1024).toFixed(0)} kB`;
+}
+
+function fileName(path) {
+    return path ? path.split("/").pop() : "";
+}
+
+function describeState(value) {
+    const details = Object.entries(value)
+        .filter(([key]) => key !== "state")
+        .map(([key, detail]) => `${key}=${detail}`)
+        .join(", ");
+    return details ? `${value.state} (${details})` : value.state;
+}
+
+async function request(url, options = {}) {
+    const response = await fetch(url, options);
+    if (!response.ok) {
+        throw new Error(`${response.status} ${await response.text()}`);
+    }
+    return response;
+}
+
+async function postJson(url, value, method = "POST") {
+    return request(url, { method, headers: { "Content-Type": "application/json" }, body: JSON.stringify(value) });
+}
+
+function addEvent(text) {
+    const events = byId("events");
+    const item = document.createElement("li");
+    item.textContent = `${new Date().toLocaleTimeString()} ${text}`;
+    events.prepend(item);
+    while (events.children.length > MAX_EVENTS) {
+        events.lastChild.remove();
+    }
+}
+
+// Now playing
+
+function renderStatus(status) {
+    currentStatus = status;
+    byId("player-state").textContent = status.state;
+    byId("player-path").textContent = fileName(status.path);
+    byId("player-error").textContent = status.error || "";
+    byId("player-queued").textContent = status.queued
+        ? `After this loop: ${status.queued.items.map((item) => fileName(item.path)).join(", ")}`
+        : "";
+    byId("player-time").textContent = `${formatTime(status.time_ms)} / ${formatTime(status.length_ms)}`;
+    const progress = byId("player-progress");
+    progress.value = status.length_ms ? (status.time_ms || 0) / status.length_ms : 0;
+}
+
+async function refreshStatus() {
+    try {
+        renderStatus(await (await request("/api/player")).json());
+    } catch (e) {
+        byId("player-state").textContent = `unavailable: ${e.message}`;
+    }
+}
+
+for (const button of document.querySelectorAll("[data-player]")) {
+    button.addEventListener("click", async () => {
+        try {
+            await request(`/api/player/${button.dataset.player}`, { method: "POST" });
+        } catch (e) {
+            addEvent(`Player ${button.dataset.player} failed: ${e.message}`);
+        }
+    });
+}
+
+// Library
+
+async function refreshLibrary() {
+    const files = byId("library-files");
+    files.replaceChildren();
+    let entries;
+    try {
+        entries = await (await request("/api/library")).json();
+    } catch (e) {
+        addEvent(`Library unavailable: ${e.message}`);
+        return;
+    }
+
+    for (const entry of entries) {
+        const row = files.insertRow();
+        row.insertCell().textContent = entry.name;
+        row.insertCell().textContent = entry.format || "unsupported";
+        row.insertCell().textContent = formatSize(entry.size_bytes);
+        const actions = row.insertCell();
+        if (entry.format) {
+            actions.append(button("Play", () => playItems([{ file: entry.name }])));
+            actions.append(button("Add", () => {
+                playlistItems.push({ file: entry.name });
+                renderPlaylist();
+            }));
+        }
+        actions.append(button("Delete", async () => {
+            if (!confirm(`Delete ${entry.name}?`)) {
+                return;
+            }
+            try {
+                await request(`/api/library/${encodeURIComponent(entry.name)}`, { method: "DELETE" });
+            } catch (e) {
+                addEvent(`Delete failed: ${e.message}`);
+            }
+        }));
+    }
+}
+
+function button(text, onClick) {
+    const element = document.createElement("button");
+    element.textContent = text;
+    element.addEventListener("click", onClick);
+    return element;
+}
+
+// Empty activation uses default from settings
+function uploadActivation() {
+    const activation = {};
+    if (byId("upload-activation").value) {
+        activation.activation = byId("upload-activation").value;
+    }
+    if (activation.activation === "at") {
+        activation.at = byId("upload-at").value;
+    }
+    return activation;
+}
+
+byId("upload-activation").addEventListener("change", () => {
+    byId("upload-at").hidden = byId("upload-activation").value !== "at";
+});
+
+function uploadMultipart(form, onProgress) {
+    return new Promise((resolve, reject) => {
+        const xhr = new XMLHttpRequest();
+        xhr.open("POST", `/upload?${new URLSearchParams(uploadActivation())}`);
+        xhr.upload.addEventListener("progress", (progressEvent) => {
+            if (progressEvent.lengthComputable) {
+                onProgress(progressEvent.loaded / progressEvent.total);
+            }
+        });
+        xhr.addEventListener("load", () => (xhr.status < 300 ? resolve(xhr.responseText) : reject(new Error(xhr.responseText))));
+        xhr.addEventListener("error", () => reject(new Error("Upload failed")));
+        xhr.send(new FormData(form));
+    });
+}
+
+// Single file is sent in chunks, upload URL is remembered so it continues after reload or network loss
+async function uploadResumable(file, onProgress) {
+    const key = `upload:${file.name}:${file.size}:${file.lastModified}`;
+    const tusHeaders = { "Tus-Resumable": "1.0.0" };
+    let location = localStorage.getItem(key);
+    let offset = null;
+    if (location) {
+        const response = await fetch(location, { method: "HEAD", headers: tusHeaders });
+        offset = response.ok ? parseInt(response.headers.get("Upload-Offset"), 10) : null;
+    }
+    if (offset === null) {
+        const metadata = Object.entries({ filename: file.name, ...uploadActivation() })
+            .map(([key, value]) => `${key} ${btoa(unescape(encodeURIComponent(value)))}`)
+            .join(",");
+        const response = await request("/api/uploads", {
+            method: "POST",
+            headers: { ...tusHeaders, "Upload-Length": String(file.size), "Upload-Metadata": metadata },
+        });
+        location = response.headers.get("Location");
+        localStorage.setItem(key, location);
+        offset = 0;
+    }
+
+    let retries = 0;
+    while (offset < file.size) {
+        onProgress(offset / file.size);
+        try {
+            const response = await request(location, {
+                method: "PATCH",
+                headers: { ...tusHeaders, "Content-Type": "application/offset+octet-stream", "Upload-Offset": String(offset) },
+                body: file.slice(offset, offset + UPLOAD_CHUNK_BYTES),
+            });
+            offset = parseInt(response.headers.get("Upload-Offset"), 10);
+            retries = 0;
+        } catch (e) {
+            if (++retries > UPLOAD_RETRIES) {
+                throw e;
+            }
+            await new Promise((resolve) => setTimeout(resolve, UPLOAD_RETRY_DELAY_MS));
+            const response = await request(location, { method: "HEAD", headers: tusHeaders });
+            offset = parseInt(response.headers.get("Upload-Offset"), 10);
+        }
+    }
+    localStorage.removeItem(key);
+    return "Uploaded";
+}
+
+byId("upload-form").addEventListener("submit", async (event) => {
+    event.preventDefault();
+    const form = event.target;
+    const files = form.querySelector("input[type=file]").files;
+    const progress = byId("upload-progress");
+    const message = byId("upload-message");
+    const onProgress = (value) => {
+        progress.value = value;
+    };
+    progress.value = 0;
+    progress.hidden = false;
+    message.textContent = "";
+    try {
+        message.textContent = files.length === 1 ? await uploadResumable(files[0], onProgress) : await uploadMultipart(form, onProgress);
+    } catch (e) {
+        message.textContent = e.message;
+    }
+    progress.hidden = true;
+});
+
+// Activation of upload form is used for downloads too
+byId("fetch-form").addEventListener("submit", async (event) => {
+    event.preventDefault();
+    const form = event.target;
+    const message = byId("fetch-message");
+    const value = { url: form.url.value, ...uploadActivation() };
+    if (form.sha256.value) {
+        value.sha256 = form.sha256.value.trim();
+    }
+    try {
+        const response = await postJson("/api/media/fetch", value);
+        message.textContent = `Downloading ${(await response.json()).filename}`;
+        form.reset();
+    } catch (e) {
+        message.textContent = e.message;
+    }
+});
+
+// Playlist
+
+function renderPlaylist() {
+    const rows = byId("playlist-items");
+    rows.replaceChildren();
+    playlistItems.forEach((item, index) => {
+        const row = rows.insertRow();
+        row.insertCell().textContent = item.url || item.file;
+        const duration = document.createElement("input");
+        duration.type = "number";
+        duration.min = "0";
+        duration.step = "0.5";
+        duration.value = item.duration_secs || "";
+        duration.addEventListener("change", () => {
+            item.duration_secs = duration.value ? parseFloat(duration.value) : null;
+        });
+        row.insertCell().append(duration);
+        const actions = row.insertCell();
+        actions.append(button("Up", () => moveItem(index, -1)));
+        actions.append(button("Down", () => moveItem(index, 1)));
+        actions.append(button("Remove", () => {
+            playlistItems.splice(index, 1);
+            renderPlaylist();
+        }));
+    });
+}
+
+function moveItem(index, offset) {
+    const target = index + offset;
+    if (target < 0 || target >= playlistItems.length) {
+        return;
+    }
+    [playlistItems[index], playlistItems[target]] = [playlistItems[target], playlistItems[index]];
+    renderPlaylist();
+}
+
+async function playItems(items) {
+    try {
+        await postJson("/api/playlist", { items });
+    } catch (e) {
+        addEvent(`Playlist failed: ${e.message}`);
+    }
+}
+
+byId("playlist-load").addEventListener("click", async () => {
+    await refreshStatus();
+    playlistItems = (currentStatus ? currentStatus.playlist.items : []).map((item) => ({
+        ...(item.kind === "stream" ? { url: item.path } : { file: fileName(item.path) }),
+        duration_secs: item.duration ? item.duration.secs + item.duration.nanos / 1e9 : null,
+    }));
+    renderPlaylist();
+});
+
+byId("playlist-stream").addEventListener("submit", (event) => {
+    event.preventDefault();
+    const input = event.target.url;
+    playlistItems.push({ url: input.value.trim() });
+    input.value = "";
+    renderPlaylist();
+});
+
+byId("playlist-clear").addEventListener("click", () => {
+    playlistItems = [];
+    renderPlaylist();
+});
+
+byId("playlist-play").addEventListener("click", () => playItems(playlistItems));
+
+// Settings
+
+const adjustmentForm = byId("adjustment");
+
+function adjustmentValue() {
+    const values = {};
+    for (const input of adjustmentForm.querySelectorAll("input")) {
+        values[input.name] = parseFloat(input.value);
+    }
+    return values;
+}
+
+function renderAdjustment(adjustment) {
+    for (const input of adjustmentForm.querySelectorAll("input")) {
+        input.value = adjustment[input.name];
+    }
+}
+
+adjustmentForm.addEventListener("input", () => postJson("/api/adjustment/preview", adjustmentValue()).catch(() => {}));
+adjustmentForm.addEventListener("submit", (event) => {
+    event.preventDefault();
+    postJson("/api/adjustment", adjustmentValue()).catch((e) => addEvent(`Adjustment failed: ${e.message}`));
+});
+byId("adjustment-reset").addEventListener("click", async () => {
+    await request("/api/adjustment", { method: "DELETE" }).catch(() => {});
+    renderAdjustment(await (await request("/api/adjustment")).json());
+});
+
+// Sections accepted by '/api/config', others are set in config file only
+const CONFIG_SECTIONS = ["logo", "adjustment", "splash", "player", "media", "upload", "fetch", "sync", "schedule"];
+
+async function refreshConfig() {
+    try {
+        const config = await (await request("/api/config")).json();
+        const editable = Object.fromEntries(CONFIG_SECTIONS.map((section) => [section, config[section]]));
+        byId("config").value = JSON.stringify(editable, null, 2);
+        renderAdjustment(config.adjustment);
+    } catch (e) {
+        byId("config-message").textContent = e.message;
+    }
+}
+
+byId("config-reload").addEventListener("click", refreshConfig);
+byId("config-save").addEventListener("click", async () => {
+    const message = byId("config-message");
+    let config;
+    try {
+        config = JSON.parse(byId("config").value);
+    } catch (e) {
+        message.textContent = `Invalid JSON: ${e.message}`;
+        return;
+    }
+    try {
+        const result = await (await postJson("/api/config", config)).json();
+        await refreshConfig();
+        message.textContent = result.restart_required.length > 0
+            ? `Saved, restart needed for: ${result.restart_required.join(", ")}`
+            : "Saved";
+    } catch (e) {
+        message.textContent = e.message;
+    }
+});
+
+// Logs
+
+async function refreshLogs() {
+    try {
+        const lines = await (await request("/api/logs")).json();
+        byId("log-lines").textContent = lines
+            .map((line) => `${line.timestamp} ${line.level} ${line.target}: ${line.message}`)
+            .join("\n");
+    } catch (e) {
+        byId("log-lines").textContent = e.message;
+    }
+}
+
+byId("logs-refresh").addEventListener("click", refreshLogs);
+
+// Live events
+
+// Last scheduled action, initial state is not logged
+let lastSchedule;
+
+function connectEvents() {
+    const events = new EventSource("/api/events");
+    events.addEventListener("open", () => {
+        byId("live").textContent = "connected";
+    });
+    events.addEventListener("error", () => {
+        // Browser reconnects by itself
+        byId("live").textContent = "disconnected";
+    });
+    events.addEventListener("network", (event) => {
+        byId("network").textContent = describeState(JSON.parse(event.data).data);
+    });
+    events.addEventListener("wifi", (event) => {
+        byId("wifi").textContent = describeState(JSON.parse(event.data).data);
+    });
+    events.addEventListener("schedule", (event) => {
+        const schedule = JSON.parse(event.data).data;
+        const next = schedule.next;
+        byId("schedule").textContent = next ? `${next.action} at ${next.at}` : "none";
+        const last = schedule.last ? `${schedule.last.action} (${schedule.last.rule}) at ${schedule.last.at}` : null;
+        if (lastSchedule !== undefined && last && last !== lastSchedule) {
+            addEvent(`Schedule ${last}`);
+        }
+        lastSchedule = last;
+    });
+    events.addEventListener("sync", (event) => {
+        const sync = JSON.parse(event.data).data;
+        const drift = sync.drift_ms === null ? "" : `, drift ${sync.drift_ms} ms`;
+        byId("sync").textContent = `${sync.role}${drift}`;
+    });
+    events.addEventListener("activation", (event) => {
+        const pending = JSON.parse(event.data).data;
+        byId("activation").textContent = pending === null
+            ? "none"
+            : `${new Date(pending.at).toLocaleString()} (${pending.files.join(", ")})`;
+    });
+    events.addEventListener("player", (event) => {
+        const playerEvent = JSON.parse(event.data).data;
+        if (playerEvent.event === "position" && currentStatus) {
+            renderStatus({ ...currentStatus, path: playerEvent.path, time_ms: playerEvent.time_ms, length_ms: playerEvent.length_ms });
+            return;
+        }
+        addEvent(`Player ${playerEvent.event} ${fileName(playerEvent.path)}`);
+        refreshStatus();
+    });
+    events.addEventListener("upload", (event) => {
+        const upload = JSON.parse(event.data).data;
+        if (upload.state !== "receiving") {
+            addEvent(`Upload ${describeState(upload)}`);
+        }
+    });
+    events.addEventListener("fetch", (event) => {
+        const fetchProgress = JSON.parse(event.data).data;
+        if (fetchProgress.state === "downloading") {
+            const total = fetchProgress.total_bytes ? ` of ${formatSize(fetchProgress.total_bytes)}` : "";
+            byId("fetch-message").textContent = `${fetchProgress.filename}: ${formatSize(fetchProgress.received_bytes)}${total}`;
+            return;
+        }
+        byId("fetch-message").textContent = `${fetchProgress.filename}: ${fetchProgress.state}`;
+        addEvent(`Fetch ${describeState(fetchProgress)}`);
+    });
+    events.addEventListener("files", (event) => {
+        const action = JSON.parse(event.data).data;
+        addEvent(`Files from ${action.source}: ${action.succeeded ? "ok" : "failed"} ${action.messages.join("; ")}`);
+        refreshLibrary();
+    });
+    events.addEventListener("drive", (event) => {
+        addEvent(`FLASH drive ${JSON.parse(event.data).data.state}`);
+    });
+}
+
+refreshStatus();
+refreshLibrary();
+refreshConfig();
+refreshLogs();
+connectEvents();
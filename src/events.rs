@@ -0,0 +1,182 @@
+use serde::Serialize;
+
+use crate::{connectivity_monitor::ConnectivityState, file_manager::PendingActivation, scheduler::ScheduleStatus, sync::SyncStatus, video_player::PlayerEvent, wifi_manager::WifiProgress};
+
+const EVENTS_CAP: usize = 128;
+
+/// Upload received by web server.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(tag = "state", rename_all = "snake_case")]
+pub enum UploadProgress {
+    Receiving {
+        filename: String,
+        received_bytes: u64,
+        /// Request size, includes form overhead.
+        total_bytes: Option<u64>,
+    },
+    Received {
+        filename: String,
+        size_bytes: u64,
+    },
+    Rejected {
+        filename: String,
+        reason: String,
+    },
+}
+
+/// Media downloaded by URL.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(tag = "state", rename_all = "snake_case")]
+pub enum FetchProgress {
+    Downloading {
+        url: String,
+        filename: String,
+        received_bytes: u64,
+        /// Missing if server does not send Content-Length.
+        total_bytes: Option<u64>,
+    },
+    Downloaded {
+        url: String,
+        filename: String,
+        size_bytes: u64,
+    },
+    Failed {
+        url: String,
+        filename: String,
+        reason: String,
+    },
+}
+
+/// Result of processing files from one source.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct FilesAction {
+    /// 'flash_drive' or 'web_server'.
+    pub source: String,
+    pub succeeded: bool,
+    pub messages: Vec<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(tag = "state", rename_all = "snake_case")]
+pub enum DriveEvent {
+    Inserted,
+    Ejected,
+}
+
+/// Anything observable from outside, e.g. by web dashboard.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(tag = "type", content = "data", rename_all = "snake_case")]
+pub enum SystemEvent {
+    Player(PlayerEvent),
+    Upload(UploadProgress),
+    Fetch(FetchProgress),
+    Files(FilesAction),
+    Drive(DriveEvent),
+    Wifi(WifiProgress),
+    Network(ConnectivityState),
+    Schedule(ScheduleStatus),
+    Sync(SyncStatus),
+    /// Upload waiting for its activation time, null when none.
+    Activation(Option<PendingActivation>),
+}
+
+impl SystemEvent {
+    pub fn name(&self) -> &'static str {
+        match self {
+            SystemEvent::Player(_) => "player",
+            SystemEvent::Upload(_) => "upload",
+            SystemEvent::Fetch(_) => "fetch",
+            SystemEvent::Files(_) => "files",
+            SystemEvent::Drive(_) => "drive",
+            SystemEvent::Wifi(_) => "wifi",
+            SystemEvent::Network(_) => "network",
+            SystemEvent::Schedule(_) => "schedule",
+            SystemEvent::Sync(_) => "sync",
+            SystemEvent::Activation(_) => "activation",
+        }
+    }
+}
+
+/// Collects events of all modules into one broadcast channel.
+pub struct EventBus {
+    events_tx: tokio::sync::broadcast::Sender<SystemEvent>,
+}
+
+impl Default for EventBus {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl EventBus {
+    pub fn new() -> Self {
+        let (events_tx, _) = tokio::sync::broadcast::channel(EVENTS_CAP);
+        Self { events_tx }
+    }
+
+    pub fn publish(&self, event: SystemEvent) {
+        // No receivers is not an error
+        let _ = self.events_tx.send(event);
+    }
+
+    /// Slow receivers lose oldest events.
+    pub fn subscribe(&self) -> tokio::sync::broadcast::Receiver<SystemEvent> {
+        self.events_tx.subscribe()
+    }
+
+    /// Republishes events of other channel until it is closed.
+    pub fn forward<T: Clone + Send + 'static>(
+        &self,
+        mut source_rx: tokio::sync::broadcast::Receiver<T>,
+        to_event: fn(T) -> SystemEvent,
+    ) -> tokio::task::JoinHandle<()> {
+        let events_tx = self.events_tx.clone();
+        tokio::spawn(async move {
+            loop {
+                match source_rx.recv().await {
+                    Ok(value) => {
+                        let _ = events_tx.send(to_event(value));
+                    },
+                    Err(tokio::sync::broadcast::error::RecvError::Lagged(skipped)) => {
+                        tracing::debug!("Event bus skipped {skipped} events.");
+                    },
+                    Err(tokio::sync::broadcast::error::RecvError::Closed) => break,
+                }
+            }
+        })
+    }
+
+    /// Republishes each state change until sender is dropped.
+    pub fn forward_watch<T: Clone + Send + Sync + 'static>(
+        &self,
+        mut source_rx: tokio::sync::watch::Receiver<T>,
+        to_event: fn(T) -> SystemEvent,
+    ) -> tokio::task::JoinHandle<()> {
+        let events_tx = self.events_tx.clone();
+        tokio::spawn(async move {
+            while source_rx.changed().await.is_ok() {
+                let value = source_rx.borrow_and_update().clone();
+                let _ = events_tx.send(to_event(value));
+            }
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_event_bus_forward() {
+        let event_bus = EventBus::new();
+        let mut events_rx = event_bus.subscribe();
+
+        let (drive_tx, drive_rx) = tokio::sync::broadcast::channel(4);
+        let _forward_task = event_bus.forward(drive_rx, SystemEvent::Drive);
+        drive_tx.send(DriveEvent::Inserted).unwrap();
+
+        let event = events_rx.recv().await.unwrap();
+        assert_eq!(event.name(), "drive");
+        assert_eq!(serde_json::to_value(&event).unwrap(), serde_json::json!({"type": "drive", "data": {"state": "inserted"}}));
+    }
+}
@@ -1,36 +1,84 @@
-use std::{path::{Path, PathBuf}, sync::Arc, time::Duration};
+use std::{collections::HashMap, path::{Component, Path, PathBuf}, sync::Arc, time::Duration};
 
-use tokio::io::AsyncWriteExt;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio_util::sync::CancellationToken;
 
-use crate::{wifi_manager::WifiManagerError, FileSubscriber, FilesManagerSink, FilesSourceType, WiFiCredentialsProcedure};
+use crate::{config::ConfigStore, events::{EventBus, FilesAction, SystemEvent}, media_probe::{MediaConfig, MediaFormat, MediaProbeError}, playlist::{Playlist, PlaylistCfg, PlaylistItem}, video_player::DisplayGeometry, wifi_manager::WifiManagerError, FileSubscriber, FilesManagerSink, FilesSourceType, UploadedContent, UploadedFile, WiFiCredentialsProcedure};
 
 #[cfg(target_os = "linux")]
 const TMP_ROOT_PATH: &str = "/tmp";
 
 const TMP_DIR_NAME: &str = "headlesspiplayer";
 
+/// Persistent state directory inside user home, survives reboots unlike temporary directory.
+const STATE_DIR_NAME: &str = ".headlesspiplayer";
+
 #[cfg(target_os = "linux")]
 const MEDIA_ROOT_PATH: &str = "/media";
 
-const SUPPORTED_VIDEO_FILES: &[&str] = &["avi", "mp4"];
-
 const WIFI_CFG_FILENAME: &str = "wifi_config.json";
 
+const WIFI_CFG_APPLIED_FILENAME: &str = "wifi_config.applied.json";
+
+/// Keys replaced in applied credentials file.
+const WIFI_CFG_SECRET_KEYS: &[&str] = &["psswd"];
+
+const WIFI_CFG_REDACTED: &str = "<redacted>";
+
+/// Hash of last successfully applied credentials file, stored in state directory.
+const WIFI_CFG_HASH_FILENAME: &str = "wifi_config.sha256";
+
 const LOG_FILENAME: &str = "log.txt";
 
+/// Optional order and image durations of FLASH drive media.
+const PLAYLIST_FILENAME: &str = "playlist.json";
+
 type ResponseMsg = Vec<String>;
 
-fn is_supported_video_file(path: &Path) -> bool {
-    path.extension()
-        .and_then(|ext| ext.to_str())
-        .map(|ext| SUPPORTED_VIDEO_FILES.iter().any(|&supported| supported.eq_ignore_ascii_case(ext)))
-        .unwrap_or(false)
+/// FLASH drive playlist entry, files are copied to library, streams are played as given.
+enum PlaylistSource {
+    File(PathBuf, MediaFormat, Option<Duration>, Option<DisplayGeometry>),
+    Stream(PlaylistItem),
 }
 
 fn is_supported_wifi_credentials_file(path: &Path) -> bool {
     path.ends_with(WIFI_CFG_FILENAME)
 }
 
+/// What happens with credentials file on FLASH drive after successful connection.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+enum AfterApply {
+    #[default]
+    Keep,
+    Redact,
+    Delete,
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct WiFiCredentialsFilePolicy {
+    #[serde(default)]
+    after_apply: AfterApply,
+}
+
+fn redact_wifi_credentials(value: &mut serde_json::Value) {
+    match value {
+        serde_json::Value::Object(map) => {
+            for (key, value) in map.iter_mut() {
+                if WIFI_CFG_SECRET_KEYS.contains(&key.as_str()) {
+                    *value = serde_json::Value::String(WIFI_CFG_REDACTED.to_string());
+                } else {
+                    redact_wifi_credentials(value);
+                }
+            }
+        },
+        serde_json::Value::Array(values) => values.iter_mut().for_each(redact_wifi_credentials),
+        _ => {},
+    }
+}
+
 #[derive(Debug, thiserror::Error)]
 pub enum FilesManagerError {
     #[error("TokioIoError reason = '{0}'")]
@@ -41,13 +89,171 @@ pub enum FilesManagerError {
 
     #[error("UserMediaNotFound")]
     UserMediaNotFound,
+
+    #[error("PlaylistError reason = '{0}'")]
+    PlaylistError(#[from] serde_json::Error),
+
+    #[error("UnsupportedMedia reason = '{0}'")]
+    UnsupportedMedia(#[from] MediaProbeError),
+
+    #[error("StagingVerificationFailed reason = '{0}'")]
+    StagingVerificationFailed(String),
+
+    #[error("InvalidFileName name = '{0}'")]
+    InvalidFileName(String),
+}
+
+/// When uploaded files are played, they are added to media library in any case.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "mode", rename_all = "snake_case")]
+pub enum UploadActivation {
+    /// Current playback is replaced immediately.
+    #[default]
+    PlayNow,
+    /// Files are only stored, e.g. to prepare next clip during show.
+    StoreOnly,
+    /// Files replace current playlist when its loop ends.
+    AfterLoop,
+    /// Files are played at given time.
+    At {
+        time: chrono::DateTime<chrono::Local>,
+    },
+}
+
+impl UploadActivation {
+    /// Parses mode name, 'time' is required by 'at' mode: RFC 3339, local 'YYYY-MM-DDTHH:MM[:SS]' or next 'HH:MM[:SS]'.
+    pub fn parse(mode: &str, time: Option<&str>) -> Result<Self, String> {
+        match mode {
+            "play_now" => Ok(Self::PlayNow),
+            "store_only" => Ok(Self::StoreOnly),
+            "after_loop" => Ok(Self::AfterLoop),
+            "at" => {
+                let time = time.ok_or("Missing activation time")?;
+                let time = Self::parse_time(time, chrono::Local::now()).ok_or_else(|| format!("Invalid activation time '{time}'"))?;
+                Ok(Self::At { time })
+            },
+            _ => Err(format!("Unknown activation '{mode}'")),
+        }
+    }
+
+    fn parse_time(time: &str, now: chrono::DateTime<chrono::Local>) -> Option<chrono::DateTime<chrono::Local>> {
+        use chrono::TimeZone;
+
+        if let Ok(time) = chrono::DateTime::parse_from_rfc3339(time) {
+            return Some(time.with_timezone(&chrono::Local));
+        }
+        for format in ["%Y-%m-%dT%H:%M:%S", "%Y-%m-%dT%H:%M"] {
+            if let Ok(time) = chrono::NaiveDateTime::parse_from_str(time, format) {
+                return chrono::Local.from_local_datetime(&time).earliest();
+            }
+        }
+
+        // Time of day, today or tomorrow if it has passed
+        let time_of_day = ["%H:%M:%S", "%H:%M"].iter()
+            .find_map(|format| chrono::NaiveTime::parse_from_str(time, format).ok())?;
+        let today = chrono::Local.from_local_datetime(&now.date_naive().and_time(time_of_day)).earliest()?;
+        if today > now {
+            Some(today)
+        } else {
+            chrono::Local.from_local_datetime(&(now.date_naive() + chrono::Days::new(1)).and_time(time_of_day)).earliest()
+        }
+    }
+}
+
+/// Playlist waiting for its activation time.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct PendingActivation {
+    pub at: chrono::DateTime<chrono::Local>,
+    pub files: Vec<String>,
+}
+
+/// Single scheduled activation, newer activation or deletion of its file cancels it.
+struct PendingActivationSlot {
+    parent_token: CancellationToken,
+    token: CancellationToken,
+    status_tx: Arc<tokio::sync::watch::Sender<Option<PendingActivation>>>,
+}
+
+impl PendingActivationSlot {
+    fn new(parent_token: CancellationToken, status_tx: Arc<tokio::sync::watch::Sender<Option<PendingActivation>>>) -> Self {
+        Self { token: parent_token.child_token(), parent_token, status_tx }
+    }
+
+    fn contains(&self, filename: &str) -> bool {
+        self.status_tx.borrow().as_ref().is_some_and(|pending| pending.files.iter().any(|file| file == filename))
+    }
+
+    /// Returns whether a playlist was pending.
+    fn cancel(&mut self, reason: &str) -> bool {
+        self.token.cancel();
+        self.token = self.parent_token.child_token();
+        let cancelled = self.status_tx.send_replace(None);
+        if let Some(pending) = &cancelled {
+            tracing::info!("Scheduled playlist at {} cancelled, {reason}.", pending.at);
+        }
+        cancelled.is_some()
+    }
+
+    fn schedule<S: FileSubscriber + 'static>(&mut self, subscriber: Arc<S>, playlist: Playlist, time: chrono::DateTime<chrono::Local>) {
+        self.cancel("replaced by newer one");
+        let pending = PendingActivation {
+            at: time,
+            files: playlist.items.iter()
+                .filter_map(|item| item.path.file_name())
+                .map(|name| name.to_string_lossy().into_owned())
+                .collect(),
+        };
+        self.status_tx.send_replace(Some(pending.clone()));
+
+        let cancel_token = self.token.clone();
+        let status_tx = self.status_tx.clone();
+        // Time in past plays immediately
+        let delay = (time - chrono::Local::now()).to_std().unwrap_or_default();
+        tokio::spawn(async move {
+            tokio::select! {
+                _ = cancel_token.cancelled() => {},
+                _ = tokio::time::sleep(delay) => {
+                    status_tx.send_if_modified(|status| {
+                        let is_own = status.as_ref() == Some(&pending);
+                        if is_own {
+                            *status = None;
+                        }
+                        is_own
+                    });
+                    tracing::info!("Playing scheduled playlist of {} items.", playlist.items.len());
+                    if let Err(e) = subscriber.on_new_playlist_available(&playlist).await {
+                        tracing::warn!("'on_new_playlist_available' failed reason {e}");
+                    }
+                },
+            }
+        });
+    }
+}
+
+/// File in media directory.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct LibraryEntry {
+    pub name: String,
+    pub size_bytes: u64,
+    /// Missing for unsupported files.
+    pub format: Option<MediaFormat>,
 }
 
 pub struct FilesManager {
     tmp_path: PathBuf,
+    state_path: PathBuf,
     media_user_path: PathBuf,
     files_source_tx: tokio::sync::mpsc::Sender<FilesSourceType>,
+    pending_activation_tx: Arc<tokio::sync::watch::Sender<Option<PendingActivation>>>,
     event_loop_task: tokio::task::JoinHandle<()>, // TODO add gracefull shutdown
+    cancel_token: CancellationToken,
+}
+
+impl Drop for FilesManager {
+    fn drop(&mut self) {
+        // Abort long running procedures, e.g. WiFi connection
+        self.cancel_token.cancel();
+    }
 }
 
 impl FilesManagerSink for FilesManager {
@@ -61,6 +267,8 @@ impl FilesManager {
     pub async fn new<S: FileSubscriber + 'static>(
         subscriber: Option<Arc<S>>,
         wifi_manager_procedure: Option<WiFiCredentialsProcedure>,
+        config_store: Option<Arc<ConfigStore>>,
+        event_bus: Option<Arc<EventBus>>,
     ) -> Result<Self, FilesManagerError> {
         let tmp_path = PathBuf::from(TMP_ROOT_PATH).join(TMP_DIR_NAME);
 
@@ -73,45 +281,94 @@ impl FilesManager {
 
         tracing::info!("Attempt to recreate temporary directory");
         Self::recreate_dir(&tmp_path).await?;
+
+        let state_path = Self::resolve_state_path();
+        tracing::info!("Using state directory {state_path:?}");
+        tokio::fs::create_dir_all(&state_path).await?;
         
         let tmp_path_shared = tmp_path.clone();
 
+        let state_path_shared = state_path.clone();
+
         let media_user_path_shared = media_user_path.clone();
 
+        let cancel_token = CancellationToken::new();
+        let cancel_token_shared = cancel_token.clone();
+
+        let pending_activation_tx = Arc::new(tokio::sync::watch::channel(None).0);
+        let mut pending_activation = PendingActivationSlot::new(cancel_token.clone(), pending_activation_tx.clone());
+
         let (files_source_tx, mut files_source_rx) = tokio::sync::mpsc::channel(Self::EVENTS_CAP);
         // Event loop
         let event_loop_task = tokio::spawn(async move {
             tracing::info!("Starting FilesManager event loop");
             loop {
-                let result = match files_source_rx.recv().await {
-                    Some(FilesSourceType::FlashDrive) => {
+                let Some(source) = files_source_rx.recv().await else {
+                    tracing::info!("Shutting down event loop");
+                    break;
+                };
+                let source_name = match &source {
+                    FilesSourceType::FlashDrive => "flash_drive",
+                    FilesSourceType::UploadedFiles { .. } => "web_server",
+                    FilesSourceType::LibraryDelete { .. } | FilesSourceType::LibraryPlaylist(_) => "library",
+                };
+                // Read for each source, so web server and files manager accept the same formats after config change
+                let media_config = config_store.as_ref().map(|config_store| config_store.get().media).unwrap_or_default();
+
+                let result = match source {
+                    FilesSourceType::FlashDrive => {
+                        pending_activation.cancel("FLASH drive replaces library");
                         Self::process_files_from_flash_drive(
                             &subscriber,
-                            wifi_manager_procedure,
+                            &wifi_manager_procedure,
+                            &cancel_token_shared,
+                            &media_config,
                             &tmp_path_shared, 
+                            &state_path_shared,
                             &media_user_path_shared
                         ).await
                     },
-                    Some(FilesSourceType::UploadedVideo { filename, data }) => {
+                    FilesSourceType::UploadedFiles { files, activation } => {
                         Self::process_files_from_webserver(
                             &subscriber,
+                            &media_config,
                             &tmp_path_shared,
-                            &filename,
-                            data
+                            files,
+                            activation,
+                            &mut pending_activation
                         ).await
                     },
-                    None => {
-                        tracing::info!("Shutting down event loop");
-                        break;
+                    FilesSourceType::LibraryDelete { filename } => {
+                        let cancelled = pending_activation.contains(&filename)
+                            && pending_activation.cancel(&format!("'{filename}' deleted"));
+                        Self::delete_library_file(&tmp_path_shared, &filename).await.map(|mut responses| {
+                            if cancelled {
+                                responses.push("Scheduled playlist cancelled.".to_string());
+                            }
+                            responses
+                        })
+                    },
+                    FilesSourceType::LibraryPlaylist(playlist_cfg) => {
+                        pending_activation.cancel("library playlist played");
+                        Self::play_library_playlist(&subscriber, &media_config, &tmp_path_shared, playlist_cfg).await
                     },
                 };
 
                 // Log responses / errors
+                let succeeded = result.is_ok();
                 let log_lines = match result {
                     Ok(result_lines) => result_lines,
                     Err(e) => vec![e.to_string()],
                 };
 
+                if let Some(event_bus) = &event_bus {
+                    event_bus.publish(SystemEvent::Files(FilesAction {
+                        source: source_name.to_string(),
+                        succeeded,
+                        messages: log_lines.clone(),
+                    }));
+                }
+
                 if !log_lines.is_empty() {
                     if let Some(flash_drive_root) = Self::find_dir_entry_inside(&media_user_path_shared, Duration::from_millis(500)).await {
                         tracing::debug!("Found FLASH drive root dir: {flash_drive_root:?}.");
@@ -136,55 +393,251 @@ impl FilesManager {
             }
         });
 
-        Ok(Self { tmp_path, media_user_path, files_source_tx, event_loop_task })
+        Ok(Self { tmp_path, state_path, media_user_path, files_source_tx, pending_activation_tx, event_loop_task, cancel_token })
+    }
+
+    /// Persistent state directory, can be resolved before manager is created.
+    pub fn resolve_state_path() -> PathBuf {
+        std::env::var_os("HOME")
+            .map(PathBuf::from)
+            .unwrap_or_else(|| PathBuf::from(TMP_ROOT_PATH))
+            .join(STATE_DIR_NAME)
+    }
+
+    /// Playlist uploaded with 'at' activation, if waiting.
+    pub fn subscribe_pending_activation(&self) -> tokio::sync::watch::Receiver<Option<PendingActivation>> {
+        self.pending_activation_tx.subscribe()
     }
 
     pub fn get_media_user_path(&self) -> PathBuf {
         self.media_user_path.clone()
     }
+
+    pub fn get_state_path(&self) -> PathBuf {
+        self.state_path.clone()
+    }
+
+    /// Directory with media being played, i.e. media library.
+    pub fn get_media_path(&self) -> PathBuf {
+        self.tmp_path.clone()
+    }
+
+    /// Lists library files sorted by name.
+    pub async fn list_library(media_path: &Path) -> Result<Vec<LibraryEntry>, FilesManagerError> {
+        let mut entries = vec![];
+        for path in Self::find_all_files(media_path).await? {
+            let Some(name) = path.file_name().and_then(|name| name.to_str()) else {
+                continue;
+            };
+            entries.push(LibraryEntry {
+                name: name.to_string(),
+                size_bytes: tokio::fs::metadata(&path).await?.len(),
+                format: MediaFormat::detect_file(&path).await.ok(),
+            });
+        }
+        Ok(entries)
+    }
+
+    /// Library has no subdirectories, so only plain, not hidden file names are accepted.
+    pub fn is_valid_file_name(filename: &str) -> bool {
+        let is_plain_name = Path::new(filename).file_name().is_some_and(|name| name == filename);
+        is_plain_name && !filename.starts_with('.')
+    }
+
+    fn library_file_path(media_path: &Path, filename: &str) -> Result<PathBuf, FilesManagerError> {
+        if !Self::is_valid_file_name(filename) {
+            return Err(FilesManagerError::InvalidFileName(filename.to_string()));
+        }
+        Ok(media_path.join(filename))
+    }
+
+    /// Playlist file names stay inside FLASH drive, subdirectories are allowed.
+    fn drive_file_path(flash_drive_root: &Path, filename: &str) -> Result<PathBuf, FilesManagerError> {
+        let relative_path = Path::new(filename);
+        let is_inside = relative_path.components().all(|component| matches!(component, Component::Normal(_) | Component::CurDir));
+        if filename.is_empty() || !is_inside {
+            return Err(FilesManagerError::InvalidFileName(filename.to_string()));
+        }
+        Ok(flash_drive_root.join(relative_path))
+    }
+
+    async fn delete_library_file(tmp_path: &Path, filename: &str) -> Result<ResponseMsg, FilesManagerError> {
+        let file_path = Self::library_file_path(tmp_path, filename)?;
+        tokio::fs::remove_file(&file_path).await?;
+        tracing::info!("Deleted {file_path:?} from library.");
+        Ok(vec![format!("Deleted '{filename}' from library.")])
+    }
+
+    async fn play_library_playlist<S: FileSubscriber>(
+        subscriber: &Option<Arc<S>>,
+        media_config: &MediaConfig,
+        tmp_path: &Path,
+        playlist_cfg: PlaylistCfg
+    ) -> Result<ResponseMsg, FilesManagerError> {
+        let mut responses = vec![];
+        let mut playlist = Playlist::default();
+        for item_cfg in playlist_cfg.items {
+            if let Some(stream_item) = item_cfg.stream_item() {
+                match stream_item {
+                    Ok(item) => playlist.items.push(item),
+                    Err(e) => responses.push(format!("Skipped playlist item '{}', reason = {e}", item_cfg.name())),
+                }
+                continue;
+            }
+
+            let file_path = match Self::library_file_path(tmp_path, &item_cfg.file) {
+                Ok(file_path) => file_path,
+                Err(e) => {
+                    tracing::warn!("Playlist item '{}' skipped, reason = {e}", item_cfg.file);
+                    responses.push(format!("Skipped playlist item '{}', reason = {e}", item_cfg.file));
+                    continue;
+                },
+            };
+            match media_config.probe_file(&file_path).await {
+                Ok(format) => {
+                    let mut item = PlaylistItem::new(file_path, format.kind());
+                    item.duration = item_cfg.duration();
+                    item.geometry = item_cfg.geometry;
+                    playlist.items.push(item);
+                },
+                Err(e) => {
+                    tracing::warn!("Playlist item {file_path:?} skipped, reason = {e}");
+                    responses.push(format!("Skipped playlist item '{}', reason = {e}", item_cfg.file));
+                },
+            }
+        }
+
+        if playlist.is_empty() {
+            responses.push("Playlist is empty".to_string());
+            return Ok(responses);
+        }
+
+        if let Some(subs) = subscriber
+            && let Err(e) = subs.on_new_playlist_available(&playlist).await {
+            tracing::warn!("'on_new_playlist_available' failed reason {e}");
+        }
+        responses.push(format!("Playlist of {} items ready.", playlist.items.len()));
+        Ok(responses)
+    }
     
-    async fn process_files_from_webserver<S: FileSubscriber>(
+    async fn process_files_from_webserver<S: FileSubscriber + 'static>(
         subscriber: &Option<Arc<S>>,  
+        media_config: &MediaConfig,
         tmp_path: &Path, 
-        filename: &str,
-        data: bytes::Bytes
+        files: Vec<UploadedFile>,
+        activation: UploadActivation,
+        pending_activation: &mut PendingActivationSlot
     ) -> Result<ResponseMsg, FilesManagerError> {
-        tracing::info!("Attempt to save data received by webserver.");
+        tracing::info!("Attempt to save {} files received by webserver, activation {activation:?}.", files.len());
+        let result = Self::add_files_from_webserver(subscriber, media_config, tmp_path, &files, activation, pending_activation).await;
 
-        tracing::info!("Attempt to notify subscriber: file deletion");
-        // Notify & await subscriber response about incomming file removal
-        if let Some(subs) = subscriber {
-            if let Err(e) = subs.on_file_about_to_be_deleted().await {
-                tracing::warn!("'on_file_about_to_be_deleted' failed reason {e}");
+        // Completed resumable uploads are consumed either way
+        for file in &files {
+            if let UploadedContent::File(upload_path) = &file.content
+                && let Err(e) = tokio::fs::remove_file(upload_path).await
+            {
+                tracing::warn!("Could not remove upload {upload_path:?}, reason = {e}");
             }
         }
-        // Clean temporary directory
-        tracing::info!("Clearing temp directory {tmp_path:?}.");
-        Self::recreate_dir(tmp_path).await.inspect_err(|e| {
-            tracing::warn!("Could not recreate temp dir, reason = {e}");
+        result
+    }
+
+    /// Other library files are kept, file of same name is replaced.
+    async fn add_files_from_webserver<S: FileSubscriber + 'static>(
+        subscriber: &Option<Arc<S>>,
+        media_config: &MediaConfig,
+        tmp_path: &Path,
+        files: &[UploadedFile],
+        activation: UploadActivation,
+        pending_activation: &mut PendingActivationSlot
+    ) -> Result<ResponseMsg, FilesManagerError> {
+        // Reject before anything is changed
+        let mut formats = vec![];
+        for file in files {
+            Self::library_file_path(tmp_path, &file.filename)?;
+            let format = match &file.content {
+                UploadedContent::Bytes(data) => media_config.probe(data),
+                UploadedContent::File(upload_path) => media_config.probe_file(upload_path).await,
+            };
+            formats.push(format.inspect_err(|e| {
+                tracing::warn!("Rejected upload {:?}, reason = {e}", file.filename);
+            })?);
+        }
+
+        // Stage files, current video keeps playing until they are verified
+        let staging_path = Self::staging_path(tmp_path);
+        Self::recreate_dir(&staging_path).await.inspect_err(|e| {
+            tracing::warn!("Could not recreate staging dir, reason = {e}");
         })?;
 
-        // Save file
-        let save_path = tmp_path.join(filename);
-        tokio::fs::write(&save_path, data).await
-            .inspect_err(|e| {
-                tracing::error!("Failed to save file from webserver, reason {e}");
-            })?;
+        let staging_result = async {
+            for file in files {
+                let staged_file_path = staging_path.join(&file.filename);
+                match &file.content {
+                    UploadedContent::Bytes(data) => {
+                        let expected_hash = format!("{:x}", Sha256::digest(data));
+                        tokio::fs::write(&staged_file_path, data).await?;
+                        Self::verify_staged_file(media_config, &staged_file_path, data.len() as u64, &expected_hash).await?;
+                    },
+                    UploadedContent::File(upload_path) => Self::stage_file(media_config, upload_path, &staged_file_path).await?,
+                }
+            }
 
-        // Notify subscriber new file is ready
-        if let Some(subs) = subscriber {
-            if let Err(e) = subs.on_new_file_available(&save_path).await {
-                tracing::warn!("'on_new_file_available' failed reason {e}");
+            // Rename within same filesystem, playing file of same name stays open by VLC
+            for file in files {
+                tokio::fs::rename(staging_path.join(&file.filename), tmp_path.join(&file.filename)).await?;
             }
+            Ok::<_, FilesManagerError>(())
+        }.await;
+        let _ = tokio::fs::remove_dir_all(&staging_path).await;
+
+        if let Err(e) = staging_result {
+            tracing::error!("Failed to stage file from webserver, reason {e}");
+            return Err(e);
+        }
+
+        let mut responses = vec![];
+        let mut playlist = Playlist::default();
+        for (file, format) in files.iter().zip(formats) {
+            let save_path = tmp_path.join(&file.filename);
+            responses.push(format!("Saved {format:?} from webserver to {save_path:?}"));
+            playlist.items.push(PlaylistItem::new(save_path, format.kind()));
         }
 
-        Ok(vec![format!("Saved from webserver to {save_path:?}")])
+        let Some(subs) = subscriber else {
+            return Ok(responses);
+        };
+        match activation {
+            UploadActivation::PlayNow => {
+                pending_activation.cancel("upload played now");
+                if let Err(e) = subs.on_new_playlist_available(&playlist).await {
+                    tracing::warn!("'on_new_playlist_available' failed reason {e}");
+                }
+                responses.push(format!("Playing {} files.", playlist.items.len()));
+            },
+            UploadActivation::StoreOnly => responses.push(format!("Stored {} files in library.", playlist.items.len())),
+            UploadActivation::AfterLoop => {
+                pending_activation.cancel("upload queued after loop");
+                if let Err(e) = subs.on_playlist_queued(&playlist).await {
+                    tracing::warn!("'on_playlist_queued' failed reason {e}");
+                }
+                responses.push(format!("{} files play after current loop.", playlist.items.len()));
+            },
+            UploadActivation::At { time } => {
+                responses.push(format!("{} files play at {}.", playlist.items.len(), time.format("%Y-%m-%d %H:%M:%S")));
+                pending_activation.schedule(subs.clone(), playlist, time);
+            },
+        }
+        Ok(responses)
     }
 
     async fn process_files_from_flash_drive<S: FileSubscriber>(
         subscriber: &Option<Arc<S>>,  
-        wifi_manager_procedure: Option<WiFiCredentialsProcedure>,
+        wifi_manager_procedure: &Option<WiFiCredentialsProcedure>,
+        cancel_token: &CancellationToken,
+        media_config: &MediaConfig,
         tmp_path: &Path, 
+        state_path: &Path,
         media_user_path: &Path
     ) -> Result<ResponseMsg, FilesManagerError> {
         tracing::info!("Attempt to find files in FLASH drive");
@@ -192,8 +645,8 @@ impl FilesManager {
         // Find FLASH drive directory inside media user directory
         let result = if let Some(flash_drive_root) = Self::find_dir_entry_inside(media_user_path, Duration::from_millis(500)).await {
             tracing::debug!("Found FLASH drive root dir: {flash_drive_root:?}.");
-            let mut responses = Self::find_any_video_file_notify_subscriber(subscriber, tmp_path, &flash_drive_root).await?;
-            let responses_wifi_related = Self::find_wifi_credentials_file_and_them(wifi_manager_procedure, &flash_drive_root).await?;
+            let mut responses = Self::find_media_files_notify_subscriber(subscriber, media_config, tmp_path, &flash_drive_root).await?;
+            let responses_wifi_related = Self::find_wifi_credentials_file_and_them(wifi_manager_procedure, cancel_token, state_path, &flash_drive_root).await?;
             responses.extend(responses_wifi_related);
             responses
         } else {
@@ -203,75 +656,210 @@ impl FilesManager {
         Ok(result)
     }
 
-    async fn find_any_video_file_notify_subscriber<S: FileSubscriber>(subscriber: &Option<Arc<S>>, tmp_path: &Path, flash_drive_root: &Path) -> Result<ResponseMsg, std::io::Error> {
-        tracing::debug!("Attempt to find video files.");
+    async fn find_media_files_notify_subscriber<S: FileSubscriber>(subscriber: &Option<Arc<S>>, media_config: &MediaConfig, tmp_path: &Path, flash_drive_root: &Path) -> Result<ResponseMsg, FilesManagerError> {
+        tracing::debug!("Attempt to find media files.");
 
-        if let Some(video_file_path) = Self::find_supported_video_file(flash_drive_root, Duration::from_millis(2500)).await {
-            tracing::info!("Found video file in FLASH drive {video_file_path:?}.");
-        
-            tracing::info!("Attempt to notify subscriber: file deletion");
-            // Notify & await subscriber response about incomming file removal
-            if let Some(subs) = subscriber {
-                if let Err(e) = subs.on_file_about_to_be_deleted().await {
-                    tracing::warn!("'on_file_about_to_be_deleted' failed reason {e}");
+        // Wait for playlist or any media file, content can appear later than FLASH drive root.
+        // Playlist may list streams only.
+        let is_playlist_or_media_file = |path: PathBuf| async move {
+            path.file_name().is_some_and(|name| name == PLAYLIST_FILENAME) || media_config.probe_file(&path).await.is_ok()
+        };
+        if Self::find_file_by(flash_drive_root, is_playlist_or_media_file, Duration::from_millis(2500)).await.is_none() {
+            tracing::info!("Not found any files :(");
+            return Ok(vec![format!("No media files on flash drive")]);
+        }
+
+        let mut responses = vec![];
+        let sources = match Self::find_file_named(flash_drive_root, PLAYLIST_FILENAME, Duration::from_millis(250)).await {
+            Some(playlist_path) => {
+                tracing::info!("Found playlist file in FLASH drive {playlist_path:?}.");
+                let content = tokio::fs::read(&playlist_path).await?;
+                let playlist_cfg: PlaylistCfg = serde_json::from_slice(&content)?;
+
+                let mut sources = vec![];
+                for item_cfg in playlist_cfg.items {
+                    if let Some(stream_item) = item_cfg.stream_item() {
+                        match stream_item {
+                            Ok(item) => sources.push(PlaylistSource::Stream(item)),
+                            Err(e) => responses.push(format!("Skipped playlist item '{}', reason = {e}", item_cfg.name())),
+                        }
+                        continue;
+                    }
+
+                    let source_path = match Self::drive_file_path(flash_drive_root, &item_cfg.file) {
+                        Ok(source_path) => source_path,
+                        Err(e) => {
+                            tracing::warn!("Playlist item '{}' skipped, reason = {e}", item_cfg.file);
+                            responses.push(format!("Skipped playlist item '{}', reason = {e}", item_cfg.file));
+                            continue;
+                        },
+                    };
+                    match media_config.probe_file(&source_path).await {
+                        Ok(format) => sources.push(PlaylistSource::File(source_path, format, item_cfg.duration(), item_cfg.geometry.clone())),
+                        Err(e) => {
+                            tracing::warn!("Playlist item {source_path:?} skipped, reason = {e}");
+                            responses.push(format!("Skipped playlist item '{}', reason = {e}", item_cfg.file));
+                        },
+                    }
                 }
-            }
+                sources
+            },
+            None => {
+                let mut sources = vec![];
+                for source_path in Self::find_all_files(flash_drive_root).await? {
+                    if let Ok(format) = media_config.probe_file(&source_path).await {
+                        sources.push(PlaylistSource::File(source_path, format, None, None));
+                    }
+                }
+                sources
+            },
+        };
 
-            // Clean temporary directory
-            tracing::info!("Clearing temp directory {tmp_path:?}.");
-            Self::recreate_dir(tmp_path).await.inspect_err(|e| {
-                tracing::warn!("Could not recreate temp dir, reason = {e}");
-            })?;
+        if sources.is_empty() {
+            responses.push("Playlist is empty".to_string());
+            return Ok(responses);
+        }
+
+        // Stage files, current video keeps playing until all are verified
+        let staging_path = Self::staging_path(tmp_path);
+        Self::recreate_dir(&staging_path).await.inspect_err(|e| {
+            tracing::warn!("Could not recreate staging dir, reason = {e}");
+        })?;
 
-            // Copy file
-            let video_file_name = video_file_path
+        let mut playlist = Playlist::default();
+        // Library is flat, files of different directories must not share name
+        let mut staged_sources: HashMap<std::ffi::OsString, PathBuf> = HashMap::new();
+        for source in sources {
+            let (source_path, format, duration, geometry) = match source {
+                PlaylistSource::File(source_path, format, duration, geometry) => (source_path, format, duration, geometry),
+                PlaylistSource::Stream(item) => {
+                    playlist.items.push(item);
+                    continue;
+                },
+            };
+            let file_name = source_path
                 .file_name()
-                .ok_or_else(|| tokio::io::Error::new(tokio::io::ErrorKind::Other, "File has no name"))?;
+                .ok_or_else(|| tokio::io::Error::other("File has no name"))?;
 
             // Function 'copy' requires path to file not directory
-            let video_file_destination_path = tmp_path.join(video_file_name);
+            let staged_file_path = staging_path.join(file_name);
 
-            tracing::info!("Attemt to copy file {video_file_path:?} to {video_file_destination_path:?}.");
-            tokio::fs::copy(&video_file_path, &video_file_destination_path).await.inspect_err(|e| {
-                tracing::warn!("Could not copy file from {video_file_path:?} to {video_file_destination_path:?}, reason = {e}");
-            })?;
+            // Same file can be listed multiple times
+            match staged_sources.get(file_name) {
+                Some(staged_source_path) if *staged_source_path == source_path => {},
+                Some(staged_source_path) => {
+                    tracing::warn!("Playlist item {source_path:?} skipped, name is used by {staged_source_path:?}");
+                    responses.push(format!("Skipped playlist item {source_path:?}, file name is already used by {staged_source_path:?}"));
+                    continue;
+                },
+                None => {
+                    tracing::info!("Attemt to copy file {source_path:?} to {staged_file_path:?}.");
+                    if let Err(e) = Self::stage_file(media_config, &source_path, &staged_file_path).await {
+                        tracing::warn!("Could not stage file from {source_path:?} to {staged_file_path:?}, reason = {e}");
+                        let _ = tokio::fs::remove_dir_all(&staging_path).await;
+                        return Err(e);
+                    }
+                    responses.push(format!("Copied file {source_path:?} to {staged_file_path:?}."));
+                    staged_sources.insert(file_name.to_os_string(), source_path.clone());
+                },
+            }
 
-            tracing::info!("File copied. Attempt to notify subscriber: new file available");
-            debug_assert_eq!(Self::count_dir_items(tmp_path).await, 1);
+            let mut item = PlaylistItem::new(tmp_path.join(file_name), format.kind());
+            item.duration = duration;
+            item.geometry = geometry;
+            playlist.items.push(item);
+        }
 
-            // Notify subscriber new file is ready
-            if let Some(subs) = subscriber {
-                if let Err(e) = subs.on_new_file_available(&video_file_destination_path).await {
-                    tracing::warn!("'on_new_file_available' failed reason {e}");
-                }
-            }
-            Ok(vec![format!("Copied file {video_file_path:?} to {video_file_destination_path:?}.")])
-        } else {
-            tracing::info!("Not found any files :(");
-            Ok(vec![format!("No video files on flash drive")])
+        Self::replace_tmp_dir_with_staging(subscriber, tmp_path, &staging_path).await?;
+
+        tracing::info!("Files copied. Attempt to notify subscriber: new playlist available");
+
+        // Notify subscriber new playlist is ready
+        if let Some(subs) = subscriber
+            && let Err(e) = subs.on_new_playlist_available(&playlist).await {
+            tracing::warn!("'on_new_playlist_available' failed reason {e}");
         }
+        responses.push(format!("Playlist of {} items ready.", playlist.items.len()));
+        Ok(responses)
     }
 
-    async fn find_wifi_credentials_file_and_them(wifi_manager_procedure: Option<WiFiCredentialsProcedure>, flash_drive_root: &Path) -> Result<ResponseMsg, FilesManagerError> {
+    async fn find_wifi_credentials_file_and_them(
+        wifi_manager_procedure: &Option<WiFiCredentialsProcedure>,
+        cancel_token: &CancellationToken,
+        state_path: &Path,
+        flash_drive_root: &Path
+    ) -> Result<ResponseMsg, FilesManagerError> {
         tracing::debug!("Attempt to find wifi credentials files.");
 
         if let Some(wifi_credentials_file_path) = Self::find_supported_wifi_credentials_file(flash_drive_root, Duration::from_millis(2500)).await {
             tracing::info!("Found wifi credentials file in FLASH drive {wifi_credentials_file_path:?}.");
             let content = tokio::fs::read(&wifi_credentials_file_path).await?;
-            if let Some(wifi_manager_procedure) = wifi_manager_procedure {
-                let response = tokio::task::spawn_blocking(move || {
-                    wifi_manager_procedure(&content)
-                }).await.expect("WiFi Manager procedure locking task failed")?;
 
-                return Ok(vec![response]);
+            let content_hash = format!("{:x}", Sha256::digest(&content));
+            let hash_path = state_path.join(WIFI_CFG_HASH_FILENAME);
+            let last_applied_hash = tokio::fs::read_to_string(&hash_path).await.unwrap_or_default();
+            if last_applied_hash.trim() == content_hash {
+                tracing::info!("WiFi credentials file unchanged since last apply, skipping.");
+                return Ok(vec!["WiFi credentials unchanged since last apply, skipped.".to_string()]);
+            }
+
+            if let Some(wifi_manager) = wifi_manager_procedure {
+                let mut progress_rx = wifi_manager.subscribe();
+                let result = wifi_manager.apply_credentials(&content, flash_drive_root, cancel_token.child_token()).await;
+
+                // Keep procedure steps in log, even if it failed
+                let mut responses: ResponseMsg = std::iter::from_fn(|| progress_rx.try_recv().ok())
+                    .map(|progress| format!("WiFi {progress}"))
+                    .collect();
+                match result {
+                    Ok(response) => {
+                        responses.push(response);
+
+                        if let Err(e) = tokio::fs::write(&hash_path, &content_hash).await {
+                            tracing::warn!("Could not store WiFi credentials hash in {hash_path:?}, reason = {e}");
+                        }
+
+                        let consume_response = Self::consume_wifi_credentials_file(&wifi_credentials_file_path, &content).await
+                            .unwrap_or_else(|e| format!("Could not consume WiFi credentials file, reason = {e}"));
+                        responses.push(consume_response);
+                    },
+                    Err(e) => responses.push(FilesManagerError::from(e).to_string()),
+                }
+
+                return Ok(responses);
             }
         }
             
         Ok(vec![])
     }
 
+    /// Applies 'after_apply' policy of credentials file, content is already applied.
+    async fn consume_wifi_credentials_file(wifi_credentials_file_path: &Path, content: &[u8]) -> Result<String, FilesManagerError> {
+        let policy: WiFiCredentialsFilePolicy = serde_json::from_slice(content).unwrap_or_default();
+
+        match policy.after_apply {
+            AfterApply::Keep => Ok("WiFi credentials file kept.".to_string()),
+            AfterApply::Redact => {
+                let mut value: serde_json::Value = serde_json::from_slice(content)
+                    .map_err(WifiManagerError::from)?;
+                redact_wifi_credentials(&mut value);
+                let redacted = serde_json::to_vec_pretty(&value)
+                    .map_err(WifiManagerError::from)?;
+
+                let applied_path = wifi_credentials_file_path.with_file_name(WIFI_CFG_APPLIED_FILENAME);
+                tokio::fs::write(&applied_path, redacted).await?;
+                tokio::fs::remove_file(wifi_credentials_file_path).await?;
+                Ok(format!("WiFi credentials file redacted to {applied_path:?}."))
+            },
+            AfterApply::Delete => {
+                tokio::fs::remove_file(wifi_credentials_file_path).await?;
+                Ok("WiFi credentials file deleted.".to_string())
+            },
+        }
+    }
+
     async fn find_file_named(dir: &Path, file_name: &str, timeout_duration: Duration) -> Option<PathBuf> {
-        Self::find_file_by(dir, |entry_path| {
+        Self::find_file_by(dir, |entry_path| async move {
             entry_path.file_name()
                 .and_then(|name| name.to_str())
                 .map(|name| name == file_name)
@@ -279,15 +867,108 @@ impl FilesManager {
         }, timeout_duration).await
     }
 
-    async fn find_supported_video_file(dir: &Path, timeout_duration: Duration) -> Option<PathBuf> {
-        Self::find_file_by(dir, is_supported_video_file, timeout_duration).await
+    /// Directory next to temp directory, so it can be renamed in place.
+    fn staging_path(tmp_path: &Path) -> PathBuf {
+        tmp_path.with_extension("staging")
+    }
+
+    async fn stage_file(media_config: &MediaConfig, source_path: &Path, staged_file_path: &Path) -> Result<(), FilesManagerError> {
+        let expected_len = tokio::fs::metadata(source_path).await?.len();
+        let expected_hash = Self::file_sha256(source_path).await?;
+        tokio::fs::copy(source_path, staged_file_path).await?;
+        Self::verify_staged_file(media_config, staged_file_path, expected_len, &expected_hash).await
+    }
+
+    /// Checks that file was written completely and can be played.
+    async fn verify_staged_file(media_config: &MediaConfig, staged_file_path: &Path, expected_len: u64, expected_hash: &str) -> Result<(), FilesManagerError> {
+        let len = tokio::fs::metadata(staged_file_path).await?.len();
+        if len != expected_len {
+            return Err(FilesManagerError::StagingVerificationFailed(format!("{staged_file_path:?} has {len} bytes, expected {expected_len}")));
+        }
+
+        let hash = Self::file_sha256(staged_file_path).await?;
+        if hash != expected_hash {
+            return Err(FilesManagerError::StagingVerificationFailed(format!("{staged_file_path:?} checksum mismatch")));
+        }
+
+        media_config.probe_file(staged_file_path).await?;
+        Ok(())
+    }
+
+    async fn file_sha256(path: &Path) -> Result<String, tokio::io::Error> {
+        let mut file = tokio::fs::File::open(path).await?;
+        let mut hasher = Sha256::new();
+        let mut buffer = vec![0; 64 * 1024];
+        loop {
+            let read_len = file.read(&mut buffer).await?;
+            if read_len == 0 {
+                break;
+            }
+            hasher.update(&buffer[..read_len]);
+        }
+        Ok(format!("{:x}", hasher.finalize()))
+    }
+
+    /// Old library is kept as backup until new one is in place, playback is stopped only then.
+    async fn replace_tmp_dir_with_staging<S: FileSubscriber>(subscriber: &Option<Arc<S>>, tmp_path: &Path, staging_path: &Path) -> Result<(), FilesManagerError> {
+        tracing::info!("Replacing temp directory {tmp_path:?} with {staging_path:?}.");
+        let backup_path = tmp_path.with_extension("backup");
+        // Leftover of interrupted replacement
+        let _ = tokio::fs::remove_dir_all(&backup_path).await;
+
+        let has_backup = tokio::fs::try_exists(tmp_path).await?;
+        if has_backup {
+            tokio::fs::rename(tmp_path, &backup_path).await.inspect_err(|e| {
+                tracing::warn!("Could not move temp dir to backup, reason = {e}");
+            })?;
+        }
+        if let Err(e) = tokio::fs::rename(staging_path, tmp_path).await {
+            tracing::warn!("Could not move staging dir, reason = {e}");
+            if has_backup && let Err(e) = tokio::fs::rename(&backup_path, tmp_path).await {
+                tracing::error!("Could not restore temp dir from backup, reason = {e}");
+            }
+            return Err(e.into());
+        }
+
+        // Files being played stay open until playback is stopped
+        tracing::info!("Attempt to notify subscriber: file deletion");
+        if let Some(subs) = subscriber
+            && let Err(e) = subs.on_file_about_to_be_deleted().await {
+            tracing::warn!("'on_file_about_to_be_deleted' failed reason {e}");
+        }
+
+        if has_backup && let Err(e) = tokio::fs::remove_dir_all(&backup_path).await {
+            tracing::warn!("Could not remove backup dir, reason = {e}");
+        }
+        Ok(())
+    }
+
+    /// Lists files sorted by name, hidden files are skipped.
+    async fn find_all_files(dir: &Path) -> Result<Vec<PathBuf>, tokio::io::Error> {
+        let mut entries = tokio::fs::read_dir(dir).await?;
+        let mut paths = vec![];
+        while let Some(entry) = entries.next_entry().await? {
+            let path = entry.path();
+            let is_hidden = path.file_name()
+                .and_then(|name| name.to_str())
+                .is_some_and(|name| name.starts_with('.'));
+            if !is_hidden && tokio::fs::metadata(&path).await.is_ok_and(|metadata| metadata.is_file()) {
+                paths.push(path);
+            }
+        }
+        paths.sort();
+        Ok(paths)
     }    
 
     async fn find_supported_wifi_credentials_file(dir: &Path, timeout_duration: Duration) -> Option<PathBuf> {
-        Self::find_file_by(dir, is_supported_wifi_credentials_file, timeout_duration).await
+        Self::find_file_by(dir, |path| async move { is_supported_wifi_credentials_file(&path) }, timeout_duration).await
     }    
 
-    async fn find_file_by<P: Fn(&Path) -> bool>(dir: &Path, predicate: P, timeout_duration: Duration) -> Option<PathBuf> {
+    async fn find_file_by<P, F>(dir: &Path, predicate: P, timeout_duration: Duration) -> Option<PathBuf>
+    where
+        P: Fn(PathBuf) -> F,
+        F: Future<Output = bool>,
+    {
         let fut = async {
             loop {
                 let mut entries = match tokio::fs::read_dir(dir).await {
@@ -302,7 +983,8 @@ impl FilesManager {
     
                 while let Ok(Some(entry)) = entries.next_entry().await {
                     let path = entry.path();
-                    if path.is_file() && predicate(&path) {
+                    let is_file = tokio::fs::metadata(&path).await.is_ok_and(|metadata| metadata.is_file());
+                    if is_file && predicate(path.clone()).await {
                         return Some(path)
                     }
                 }
@@ -402,10 +1084,103 @@ mod tests {
             .try_init();
     }
 
+    #[test]
+    fn test_redact_wifi_credentials() {
+        let mut value: serde_json::Value = serde_json::from_str(r#"{
+            "ssid": "stage", "psswd": "secret", "after_apply": "redact"
+        }"#).unwrap();
+        redact_wifi_credentials(&mut value);
+        assert_eq!(value["psswd"], WIFI_CFG_REDACTED);
+        assert_eq!(value["ssid"], "stage");
+
+        let policy: WiFiCredentialsFilePolicy = serde_json::from_value(value).unwrap();
+        assert_eq!(policy.after_apply, AfterApply::Redact);
+    }
+
+    #[tokio::test]
+    async fn test_stage_file_keeps_current_files_on_failure() {
+        init_test_tracing();
+
+        let test_dir = std::env::temp_dir().join(format!("headlesspiplayer_test_{}", std::process::id()));
+        let tmp_path = test_dir.join("media");
+        FilesManager::recreate_dir(&test_dir).await.unwrap();
+        tokio::fs::create_dir(&tmp_path).await.unwrap();
+        tokio::fs::write(tmp_path.join("current.png"), b"\x89PNG\r\n\x1a\n").await.unwrap();
+
+        let subscriber: Option<Arc<VideoPlayer>> = None;
+        let result = FilesManager::process_files_from_webserver(
+            &subscriber, &MediaConfig::default(), &tmp_path, vec![
+                UploadedFile { filename: "new.png".to_string(), content: UploadedContent::Bytes(bytes::Bytes::from_static(b"\x89PNG\r\n\x1a\n")) },
+                UploadedFile { filename: "renamed.mp4".to_string(), content: UploadedContent::Bytes(bytes::Bytes::from_static(b"not a video")) },
+            ], UploadActivation::PlayNow, &mut PendingActivationSlot::new(CancellationToken::new(), Arc::new(tokio::sync::watch::channel(None).0))
+        ).await;
+        assert!(matches!(result, Err(FilesManagerError::UnsupportedMedia(_))));
+        assert!(tmp_path.join("current.png").exists());
+
+        let staging_path = FilesManager::staging_path(&tmp_path);
+        tokio::fs::create_dir(&staging_path).await.unwrap();
+        let source_path = test_dir.join("source.txt");
+        tokio::fs::write(&source_path, b"plain text").await.unwrap();
+        let result = FilesManager::stage_file(&MediaConfig::default(), &source_path, &staging_path.join("source.txt")).await;
+        assert!(matches!(result, Err(FilesManagerError::UnsupportedMedia(_))));
+
+        let png_path = test_dir.join("new.png");
+        tokio::fs::write(&png_path, b"\x89PNG\r\n\x1a\n").await.unwrap();
+        FilesManager::stage_file(&MediaConfig::default(), &png_path, &staging_path.join("new.png")).await.unwrap();
+        FilesManager::replace_tmp_dir_with_staging(&subscriber, &tmp_path, &staging_path).await.unwrap();
+        assert!(tmp_path.join("new.png").exists());
+        assert!(!tmp_path.join("current.png").exists());
+        assert!(!tmp_path.with_extension("backup").exists());
+
+        // Missing staging dir keeps library
+        assert!(FilesManager::replace_tmp_dir_with_staging(&subscriber, &tmp_path, &staging_path).await.is_err());
+        assert!(tmp_path.join("new.png").exists());
+
+        // Invalid name is skipped like unplayable file
+        let playlist_cfg: PlaylistCfg = serde_json::from_str(r#"{"items": [{"file": "../new.png"}, {"file": "new.png"}]}"#).unwrap();
+        let responses = FilesManager::play_library_playlist(&subscriber, &MediaConfig::default(), &tmp_path, playlist_cfg).await.unwrap();
+        assert!(responses[0].starts_with("Skipped playlist item '../new.png'"), "{responses:?}");
+        assert_eq!(responses[1], "Playlist of 1 items ready.");
+
+        let _ = tokio::fs::remove_dir_all(&test_dir).await;
+    }
+
     #[tokio::test]
     async fn test_file_manager_init() {
         init_test_tracing();
 
-        let _file_manager = FilesManager::new::<VideoPlayer>(None, None).await.unwrap();
+        let _file_manager = FilesManager::new::<VideoPlayer>(None, None, None, None).await.unwrap();
+    }
+
+    #[test]
+    fn test_upload_activation_time() {
+        use chrono::TimeZone;
+
+        let now = chrono::Local.with_ymd_and_hms(2026, 10, 18, 20, 0, 0).unwrap();
+        let parse = |time| UploadActivation::parse_time(time, now);
+        assert_eq!(parse("21:30"), Some(chrono::Local.with_ymd_and_hms(2026, 10, 18, 21, 30, 0).unwrap()));
+        assert_eq!(parse("19:00:30"), Some(chrono::Local.with_ymd_and_hms(2026, 10, 19, 19, 0, 30).unwrap()));
+        assert_eq!(parse("2026-10-20T08:15"), Some(chrono::Local.with_ymd_and_hms(2026, 10, 20, 8, 15, 0).unwrap()));
+        assert_eq!(parse("2026-10-20T08:15:00Z").map(|time| time.timestamp()), Some(1792484100));
+        assert_eq!(parse("tomorrow"), None);
+
+        assert_eq!(UploadActivation::parse("store_only", None), Ok(UploadActivation::StoreOnly));
+        assert!(UploadActivation::parse("at", None).is_err());
+        assert!(UploadActivation::parse("later", None).is_err());
+    }
+
+    #[test]
+    fn test_library_file_path() {
+        let media_path = Path::new("/tmp/headlesspiplayer");
+        assert_eq!(FilesManager::library_file_path(media_path, "clip.mp4").unwrap(), media_path.join("clip.mp4"));
+        for filename in ["../clip.mp4", "dir/clip.mp4", "/etc/passwd", "..", ".hidden", ""] {
+            assert!(matches!(FilesManager::library_file_path(media_path, filename), Err(FilesManagerError::InvalidFileName(_))), "{filename}");
+        }
+
+        let drive_path = Path::new("/media/pi/DRIVE");
+        assert_eq!(FilesManager::drive_file_path(drive_path, "show/clip.mp4").unwrap(), drive_path.join("show/clip.mp4"));
+        for filename in ["../clip.mp4", "show/../../clip.mp4", "/home/pi/.ssh/id_rsa", ""] {
+            assert!(matches!(FilesManager::drive_file_path(drive_path, filename), Err(FilesManagerError::InvalidFileName(_))), "{filename}");
+        }
     }
 }
\ No newline at end of file
@@ -2,7 +2,7 @@ use std::{path::PathBuf, sync::Arc};
 
 use notify::{event::AccessKind, Watcher};
 
-use crate::{FilesManagerSink, FilesSource, FilesSourceHandler, FilesSourceType};
+use crate::{events::{DriveEvent, EventBus, SystemEvent}, FilesManagerSink, FilesSource, FilesSourceHandler, FilesSourceType};
 
 #[derive(Debug, thiserror::Error)]
 pub enum FileSourceFlashDriveError {
@@ -18,6 +18,7 @@ pub enum FileSourceFlashDriveError {
 
 pub struct FileSourceFlashDrive {
     media_user_path: PathBuf,
+    event_bus: Option<Arc<EventBus>>,
 }
 
 pub struct FileSourceFlashDriveHandler {
@@ -41,7 +42,13 @@ impl FilesSourceHandler for FileSourceFlashDriveHandler {
 
 impl FileSourceFlashDrive {
     pub async fn new(media_user_path: PathBuf) -> Self {
-        Self { media_user_path }
+        Self { media_user_path, event_bus: None }
+    }
+
+    /// Publishes drive insertion and ejection.
+    pub fn with_event_bus(mut self, event_bus: Arc<EventBus>) -> Self {
+        self.event_bus = Some(event_bus);
+        self
     }
 }
 
@@ -55,6 +62,13 @@ impl FilesSource for FileSourceFlashDrive {
         watcher.watch(&self.media_user_path, notify::RecursiveMode::Recursive)?;
 
         let files_manager_sink = sink.get_tx();
+        let media_user_path = self.media_user_path.clone();
+        let event_bus = self.event_bus;
+        let publish = move |event: DriveEvent| {
+            if let Some(event_bus) = &event_bus {
+                event_bus.publish(SystemEvent::Drive(event));
+            }
+        };
 
         let watcher_task = tokio::task::spawn_blocking(move || {
             // Allows opening already inserted FLASH drive
@@ -66,14 +80,20 @@ impl FilesSource for FileSourceFlashDrive {
                     Ok(event) => {
                         tracing::trace!("event: {:?}", event);
                         let process_event_result = match event.kind {
+                            // Files created on FLASH drive by this program (log, applied config) are not insertions
+                            notify::EventKind::Create(_) if !event.paths.iter().any(|path| path.parent() == Some(media_user_path.as_path())) => {
+                                Ok(())
+                            },
                             notify::EventKind::Create(_) => {
                                 tracing::debug!("FLASH drive inserted.");
+                                publish(DriveEvent::Inserted);
                                 // Note: finding FLASH drive root can be fast, but content can appear later
                                 flash_drive_was_created = true;
                                 files_manager_sink.blocking_send(FilesSourceType::FlashDrive)
                             },
-                            notify::EventKind::Remove(_) => {
+                            notify::EventKind::Remove(_) if event.paths.iter().any(|path| path.parent() == Some(media_user_path.as_path())) => {
                                 tracing::debug!("FLASH drive ejected.");
+                                publish(DriveEvent::Ejected);
                                 Ok(())
                             },
                             notify::EventKind::Access(AccessKind::Open(_)) => {
@@ -81,6 +101,7 @@ impl FilesSource for FileSourceFlashDrive {
                                 // Edge case - FLASH drive inserted before starting program
                                 if !flash_drive_was_created {
                                     flash_drive_was_created = true;
+                                    publish(DriveEvent::Inserted);
                                     files_manager_sink.blocking_send(FilesSourceType::FlashDrive)
                                 } else {
                                     Ok(())
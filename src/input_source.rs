@@ -0,0 +1,520 @@
+use std::{collections::HashMap, os::unix::fs::FileTypeExt, path::{Path, PathBuf}, sync::Arc, time::{Duration, Instant}};
+
+use serde::{Deserialize, Serialize};
+use tokio::{io::{AsyncBufReadExt, AsyncWriteExt, BufReader}, net::{UnixListener, UnixStream}, process::Command};
+use tokio_util::sync::CancellationToken;
+
+use crate::{playlist::{PlaylistCfg, PlaylistItemCfg}, video_player::{PlaybackState, VideoPlayer}, FilesManagerSink, FilesSource, FilesSourceHandler, FilesSourceType};
+
+const TRIGGERS_CAP: usize = 32;
+/// Unplugged device or broken pipe is opened again after this time.
+const RETRY_DELAY: Duration = Duration::from_secs(5);
+
+#[derive(Debug, thiserror::Error)]
+pub enum InputError {
+    #[error("TokioIoError reason = '{0}'")]
+    TokioIoError(#[from] tokio::io::Error),
+
+    #[error("TokioJoinError")]
+    TokioJoinError(#[from] tokio::task::JoinError),
+
+    #[error("InvalidPipe path = '{0}'")]
+    InvalidPipe(String),
+
+    #[error("CommandFailed reason = '{0}'")]
+    CommandFailed(String),
+
+    #[error("UnknownInput input = '{0}'")]
+    UnknownInput(String),
+
+    #[error("PlayerNotAvailable")]
+    PlayerNotAvailable,
+
+    #[error("Stopped")]
+    Stopped,
+}
+
+/// Local triggers, e.g. button hidden in prop or presenter remote.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct InputConfig {
+    /// evdev devices, stable names are in '/dev/input/by-id'.
+    #[serde(default)]
+    pub devices: Vec<PathBuf>,
+
+    /// Key presses are not passed to console or other programs.
+    #[serde(default = "InputConfig::default_grab")]
+    pub grab: bool,
+
+    /// Named pipe, created if missing, each line is one input.
+    #[serde(default)]
+    pub pipe: Option<PathBuf>,
+
+    /// Each line is one input, answered by 'OK' or 'ERROR <reason>'.
+    #[serde(default)]
+    pub socket: Option<PathBuf>,
+
+    #[serde(default)]
+    pub bindings: Vec<InputBinding>,
+
+    /// Played by 'idle' action, player is stopped if missing.
+    #[serde(default)]
+    pub idle_playlist: Option<PlaylistCfg>,
+
+    /// Repeated input is ignored meanwhile, e.g. switch bounce.
+    #[serde(default = "InputConfig::default_debounce_ms")]
+    pub debounce_ms: u64,
+}
+
+impl InputConfig {
+    fn default_grab() -> bool {
+        true
+    }
+
+    fn default_debounce_ms() -> u64 {
+        200
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        !self.devices.is_empty() || self.pipe.is_some() || self.socket.is_some()
+    }
+}
+
+impl Default for InputConfig {
+    fn default() -> Self {
+        Self {
+            devices: vec![],
+            grab: Self::default_grab(),
+            pipe: None,
+            socket: None,
+            bindings: vec![],
+            idle_playlist: None,
+            debounce_ms: Self::default_debounce_ms(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct InputBinding {
+    /// Key name as 'KEY_1' or 'BTN_LEFT', or line received by pipe or socket.
+    pub input: String,
+
+    #[serde(flatten)]
+    pub action: InputAction,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "action", rename_all = "snake_case")]
+pub enum InputAction {
+    /// Media library file replaces current playback.
+    PlayClip {
+        file: String,
+    },
+    Next,
+    Previous,
+    Pause,
+    Resume,
+    TogglePause,
+    /// Idle playlist, or splash if there is none.
+    Idle,
+}
+
+impl InputAction {
+    /// Commands accepted without binding: 'play <file>', 'next', 'previous', 'pause', 'resume', 'toggle_pause', 'idle'.
+    pub fn parse(command: &str) -> Option<Self> {
+        if let Some(file) = command.strip_prefix("play ") {
+            let file = file.trim();
+            return (!file.is_empty()).then(|| Self::PlayClip { file: file.to_string() });
+        }
+        match command {
+            "next" => Some(Self::Next),
+            "previous" => Some(Self::Previous),
+            "pause" => Some(Self::Pause),
+            "resume" => Some(Self::Resume),
+            "toggle_pause" => Some(Self::TogglePause),
+            "idle" => Some(Self::Idle),
+            _ => None,
+        }
+    }
+}
+
+/// Input of any source, sockets wait for result.
+struct Trigger {
+    input: String,
+    /// Key presses only, commands are sent on purpose.
+    debounced: bool,
+    reply_tx: Option<tokio::sync::oneshot::Sender<Result<(), InputError>>>,
+}
+
+/// Maps key presses and text commands to player actions, clips are passed to files manager.
+pub struct InputSource {
+    config: InputConfig,
+    video_player: Option<Arc<VideoPlayer>>,
+}
+
+pub struct InputSourceHandler {
+    task_handles: Vec<tokio::task::JoinHandle<()>>,
+    cancel_token: CancellationToken,
+}
+
+impl FilesSourceHandler for InputSourceHandler {
+    type Error = InputError;
+
+    async fn shutdown(self) -> Result<(), Self::Error> {
+        self.cancel_token.cancel();
+        self.await_finish().await
+    }
+
+    async fn await_finish(self) -> Result<(), Self::Error> {
+        for task_handle in self.task_handles {
+            task_handle.await?;
+        }
+        Ok(())
+    }
+}
+
+impl InputSource {
+    pub fn new(config: InputConfig) -> Self {
+        Self { config, video_player: None }
+    }
+
+    /// Enables all actions except 'play_clip' and 'idle' with playlist.
+    pub fn with_video_player(mut self, video_player: Arc<VideoPlayer>) -> Self {
+        self.video_player = Some(video_player);
+        self
+    }
+
+    fn resolve(&self, input: &str) -> Option<InputAction> {
+        self.config.bindings.iter()
+            .find(|binding| binding.input == input)
+            .map(|binding| binding.action.clone())
+            .or_else(|| InputAction::parse(input))
+    }
+
+    async fn execute(&self, action: InputAction, files_manager_sink: &tokio::sync::mpsc::Sender<FilesSourceType>) -> Result<(), InputError> {
+        let video_player = || self.video_player.as_ref().ok_or(InputError::PlayerNotAvailable);
+        let play = |playlist: PlaylistCfg| async move {
+            files_manager_sink.send(FilesSourceType::LibraryPlaylist(playlist)).await.map_err(|_| InputError::Stopped)
+        };
+
+        match action {
+            InputAction::PlayClip { file } => {
+                play(PlaylistCfg { items: vec![PlaylistItemCfg { file, url: None, duration_secs: None, geometry: None }] }).await?;
+            },
+            InputAction::Next => video_player()?.next(),
+            InputAction::Previous => video_player()?.previous(),
+            InputAction::Pause => video_player()?.pause(),
+            InputAction::Resume => video_player()?.resume(),
+            InputAction::TogglePause => {
+                let video_player = video_player()?;
+                if video_player.status().state == PlaybackState::Paused {
+                    video_player.resume();
+                } else {
+                    video_player.pause();
+                }
+            },
+            InputAction::Idle => match &self.config.idle_playlist {
+                Some(idle_playlist) => play(idle_playlist.clone()).await?,
+                None => video_player()?.stop().await,
+            },
+        }
+        Ok(())
+    }
+
+    async fn dispatch(
+        &self,
+        mut triggers_rx: tokio::sync::mpsc::Receiver<Trigger>,
+        files_manager_sink: tokio::sync::mpsc::Sender<FilesSourceType>,
+        cancel_token: CancellationToken,
+    ) {
+        let debounce = Duration::from_millis(self.config.debounce_ms);
+        let mut last_triggered: HashMap<String, Instant> = HashMap::new();
+        loop {
+            let trigger = tokio::select! {
+                _ = cancel_token.cancelled() => break,
+                trigger = triggers_rx.recv() => match trigger {
+                    Some(trigger) => trigger,
+                    None => break,
+                },
+            };
+
+            let result = match self.resolve(&trigger.input) {
+                Some(_) if trigger.debounced && last_triggered.get(&trigger.input).is_some_and(|last| last.elapsed() < debounce) => {
+                    tracing::debug!("Input '{}' ignored, repeated within {}ms", trigger.input, self.config.debounce_ms);
+                    Ok(())
+                },
+                Some(action) => {
+                    tracing::info!("Input '{}': {action:?}", trigger.input);
+                    last_triggered.insert(trigger.input.clone(), Instant::now());
+                    self.execute(action, &files_manager_sink).await
+                },
+                None => Err(InputError::UnknownInput(trigger.input.clone())),
+            };
+
+            match trigger.reply_tx {
+                Some(reply_tx) => {
+                    let _ = reply_tx.send(result);
+                },
+                // Unbound keys are common, e.g. keyboard used as keypad
+                None => if let Err(e) = result {
+                    tracing::debug!("Input '{}' not handled, reason = {e}", trigger.input);
+                },
+            }
+        }
+    }
+
+    /// Runs 'task' again after failure until cancelled.
+    async fn keep_running<F, Fut>(what: String, cancel_token: CancellationToken, mut task: F)
+    where
+        F: FnMut() -> Fut,
+        Fut: Future<Output = Result<(), InputError>>,
+    {
+        loop {
+            tokio::select! {
+                _ = cancel_token.cancelled() => return,
+                result = task() => match result {
+                    Err(InputError::Stopped) => return,
+                    Err(e) => tracing::warn!("{what} failed, retry in {}s, reason = {e}", RETRY_DELAY.as_secs()),
+                    Ok(()) => {},
+                },
+            }
+            tokio::select! {
+                _ = cancel_token.cancelled() => return,
+                _ = tokio::time::sleep(RETRY_DELAY) => {},
+            }
+        }
+    }
+
+    async fn send(triggers_tx: &tokio::sync::mpsc::Sender<Trigger>, trigger: Trigger) -> Result<(), InputError> {
+        triggers_tx.send(trigger).await.map_err(|_| InputError::Stopped)
+    }
+
+    async fn read_device(path: PathBuf, grab: bool, triggers_tx: tokio::sync::mpsc::Sender<Trigger>) -> Result<(), InputError> {
+        let mut device = evdev::Device::open(&path)?;
+        if grab {
+            device.grab()?;
+        }
+        tracing::info!("Input device {path:?} opened, name = {:?}", device.name().unwrap_or_default());
+
+        let mut events = device.into_event_stream()?;
+        loop {
+            // Key press only, release and autorepeat are skipped
+            if let evdev::EventSummary::Key(_, key, 1) = events.next_event().await?.destructure() {
+                Self::send(&triggers_tx, Trigger { input: format!("{key:?}"), debounced: true, reply_tx: None }).await?;
+            }
+        }
+    }
+
+    async fn create_pipe(path: &Path) -> Result<(), InputError> {
+        match tokio::fs::metadata(path).await {
+            Ok(metadata) if metadata.file_type().is_fifo() => return Ok(()),
+            Ok(_) => return Err(InputError::InvalidPipe(path.display().to_string())),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {},
+            Err(e) => return Err(e.into()),
+        }
+
+        let output = Command::new("mkfifo").arg(path).output().await?;
+        if output.status.success() {
+            Ok(())
+        } else {
+            let stderr_str = String::from_utf8_lossy(&output.stderr);
+            Err(InputError::CommandFailed(format!("mkfifo failed with status {}: {}", output.status, stderr_str.trim())))
+        }
+    }
+
+    async fn read_pipe(path: PathBuf, triggers_tx: tokio::sync::mpsc::Sender<Trigger>) -> Result<(), InputError> {
+        Self::create_pipe(&path).await?;
+        // Opened for writing too, so pipe does not end when last writer closes it
+        let receiver = tokio::net::unix::pipe::OpenOptions::new()
+            .read_write(true)
+            .open_receiver(&path)?;
+        tracing::info!("Input pipe {path:?} opened");
+
+        let mut lines = BufReader::new(receiver).lines();
+        while let Some(line) = lines.next_line().await? {
+            let input = line.trim();
+            if !input.is_empty() {
+                Self::send(&triggers_tx, Trigger { input: input.to_string(), debounced: false, reply_tx: None }).await?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Removes socket file only, other files are kept.
+    async fn remove_socket(path: &Path) -> Result<(), InputError> {
+        match tokio::fs::symlink_metadata(path).await {
+            Ok(metadata) if metadata.file_type().is_socket() => Ok(tokio::fs::remove_file(path).await?),
+            Ok(_) => Err(InputError::InvalidPipe(path.display().to_string())),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(()),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    async fn serve_socket(path: PathBuf, triggers_tx: tokio::sync::mpsc::Sender<Trigger>, cancel_token: CancellationToken) -> Result<(), InputError> {
+        // Socket file of previous run prevents binding
+        Self::remove_socket(&path).await?;
+        let listener = UnixListener::bind(&path)?;
+        tracing::info!("Input socket {path:?} listening");
+
+        loop {
+            let (stream, _) = listener.accept().await?;
+            let triggers_tx = triggers_tx.clone();
+            let cancel_token = cancel_token.clone();
+            tokio::spawn(async move {
+                tokio::select! {
+                    _ = cancel_token.cancelled() => {},
+                    result = Self::serve_client(stream, triggers_tx) => if let Err(e) = result {
+                        tracing::debug!("Input socket client closed, reason = {e}");
+                    },
+                }
+            });
+        }
+    }
+
+    async fn serve_client(stream: UnixStream, triggers_tx: tokio::sync::mpsc::Sender<Trigger>) -> Result<(), InputError> {
+        let (reader, mut writer) = stream.into_split();
+        let mut lines = BufReader::new(reader).lines();
+        while let Some(line) = lines.next_line().await? {
+            let input = line.trim();
+            if input.is_empty() {
+                continue;
+            }
+
+            let (reply_tx, reply_rx) = tokio::sync::oneshot::channel();
+            Self::send(&triggers_tx, Trigger { input: input.to_string(), debounced: false, reply_tx: Some(reply_tx) }).await?;
+            let reply = match reply_rx.await.map_err(|_| InputError::Stopped)? {
+                Ok(()) => "OK\n".to_string(),
+                Err(e) => format!("ERROR {e}\n"),
+            };
+            writer.write_all(reply.as_bytes()).await?;
+        }
+        Ok(())
+    }
+}
+
+impl FilesSource for InputSource {
+    type Handler = InputSourceHandler;
+    type Error = InputError;
+
+    async fn start(self, sink: Arc<dyn FilesManagerSink>) -> Result<Self::Handler, Self::Error> {
+        let (triggers_tx, triggers_rx) = tokio::sync::mpsc::channel(TRIGGERS_CAP);
+        let cancel_token = CancellationToken::new();
+        let mut task_handles = vec![];
+
+        for path in self.config.devices.clone() {
+            let triggers_tx = triggers_tx.clone();
+            let grab = self.config.grab;
+            task_handles.push(tokio::spawn(Self::keep_running(
+                format!("Input device {path:?}"),
+                cancel_token.clone(),
+                move || Self::read_device(path.clone(), grab, triggers_tx.clone()),
+            )));
+        }
+
+        if let Some(path) = self.config.pipe.clone() {
+            let triggers_tx = triggers_tx.clone();
+            task_handles.push(tokio::spawn(Self::keep_running(
+                format!("Input pipe {path:?}"),
+                cancel_token.clone(),
+                move || Self::read_pipe(path.clone(), triggers_tx.clone()),
+            )));
+        }
+
+        if let Some(path) = self.config.socket.clone() {
+            let triggers_tx = triggers_tx.clone();
+            let task_cancel_token = cancel_token.clone();
+            task_handles.push(tokio::spawn(async move {
+                let socket_path = path.clone();
+                Self::keep_running(
+                    format!("Input socket {path:?}"),
+                    task_cancel_token.clone(),
+                    move || Self::serve_socket(path.clone(), triggers_tx.clone(), task_cancel_token.clone()),
+                ).await;
+                let _ = Self::remove_socket(&socket_path).await;
+            }));
+        }
+
+        tracing::info!("Input source started with {} triggers", task_handles.len());
+        let files_manager_sink = sink.get_tx();
+        let task_cancel_token = cancel_token.clone();
+        task_handles.push(tokio::spawn(async move {
+            self.dispatch(triggers_rx, files_manager_sink, task_cancel_token).await;
+        }));
+
+        Ok(Self::Handler { task_handles, cancel_token })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Write;
+
+    use super::*;
+    use crate::TestSink;
+
+    async fn wait_for(path: &Path) {
+        for _ in 0..100 {
+            if path.exists() {
+                return;
+            }
+            tokio::time::sleep(Duration::from_millis(20)).await;
+        }
+        panic!("{path:?} not created");
+    }
+
+    async fn next_clip(files_rx: &mut tokio::sync::mpsc::Receiver<FilesSourceType>) -> String {
+        match tokio::time::timeout(Duration::from_secs(5), files_rx.recv()).await {
+            Ok(Some(FilesSourceType::LibraryPlaylist(playlist))) => playlist.items[0].file.clone(),
+            other => panic!("Unexpected {other:?}"),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_pipe_and_socket_triggers() {
+        let test_path = std::env::temp_dir().join(format!("input_source_test_{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&test_path);
+        std::fs::create_dir_all(&test_path).unwrap();
+        let pipe_path = test_path.join("input.fifo");
+        let socket_path = test_path.join("input.sock");
+
+        let config: InputConfig = serde_json::from_value(serde_json::json!({
+            "pipe": pipe_path,
+            "socket": socket_path,
+            "bindings": [{ "input": "fx", "action": "play_clip", "file": "effect.mp4" }],
+            "idle_playlist": { "items": [{ "file": "idle.mp4" }] },
+        })).unwrap();
+        let (files_tx, mut files_rx) = tokio::sync::mpsc::channel(8);
+        let handler = InputSource::new(config).start(Arc::new(TestSink { tx: files_tx })).await.unwrap();
+
+        // Commands are not debounced, unlike key presses
+        wait_for(&pipe_path).await;
+        let writer_path = pipe_path.clone();
+        tokio::task::spawn_blocking(move || {
+            let mut pipe = std::fs::OpenOptions::new().write(true).open(writer_path).unwrap();
+            pipe.write_all(b"fx\nfx\n").unwrap();
+        }).await.unwrap();
+        assert_eq!(next_clip(&mut files_rx).await, "effect.mp4");
+        assert_eq!(next_clip(&mut files_rx).await, "effect.mp4");
+
+        wait_for(&socket_path).await;
+        let (reader, mut writer) = UnixStream::connect(&socket_path).await.unwrap().into_split();
+        let mut replies = BufReader::new(reader).lines();
+        writer.write_all(b"idle\nplay intro.mp4\nnext\nbogus\n").await.unwrap();
+        assert_eq!(replies.next_line().await.unwrap().as_deref(), Some("OK"));
+        assert_eq!(next_clip(&mut files_rx).await, "idle.mp4");
+        assert_eq!(replies.next_line().await.unwrap().as_deref(), Some("OK"));
+        assert_eq!(next_clip(&mut files_rx).await, "intro.mp4");
+        assert_eq!(replies.next_line().await.unwrap().as_deref(), Some("ERROR PlayerNotAvailable"));
+        assert_eq!(replies.next_line().await.unwrap().as_deref(), Some("ERROR UnknownInput input = 'bogus'"));
+
+        handler.shutdown().await.unwrap();
+        assert!(!socket_path.exists());
+
+        // Only socket file is replaced
+        let file_path = test_path.join("input.txt");
+        std::fs::write(&file_path, "keep").unwrap();
+        assert!(matches!(InputSource::remove_socket(&file_path).await, Err(InputError::InvalidPipe(_))));
+        assert!(file_path.exists());
+        assert!(files_rx.try_recv().is_err());
+        let _ = std::fs::remove_dir_all(&test_path);
+    }
+}
@@ -1,26 +1,72 @@
-use std::{path::Path, sync::Arc};
+use std::{path::{Path, PathBuf}, sync::Arc};
 
-use crate::wifi_manager::WifiManagerError;
+use crate::{file_manager::UploadActivation, playlist::{Playlist, PlaylistCfg}, wifi_manager::WifiManager};
 
+pub mod config;
+pub mod connectivity_monitor;
+pub mod events;
 pub mod flash_drive_observer;
+pub mod input_source;
+pub mod log_buffer;
+pub mod media_probe;
+pub mod playlist;
+pub mod resumable_upload;
+pub mod scheduler;
 pub mod file_manager;
+pub mod splash;
+pub mod sync;
+pub mod url_fetcher;
 pub mod video_player;
 pub mod webserver;
 pub mod wifi_manager;
 
+#[derive(Debug)]
+pub enum UploadedContent {
+    /// Whole file received in one request.
+    Bytes(bytes::Bytes),
+    /// Completed resumable upload, file is removed once processed.
+    File(PathBuf),
+}
+
+#[derive(Debug)]
+pub struct UploadedFile {
+    pub filename: String,
+    pub content: UploadedContent,
+}
+
 #[derive(Debug)]
 pub enum FilesSourceType {
     FlashDrive,
-    UploadedVideo {
+    /// Files are added to media library and played in order as set by 'activation'.
+    UploadedFiles {
+        files: Vec<UploadedFile>,
+        activation: UploadActivation,
+    },
+    /// File removed from media library by user.
+    LibraryDelete {
         filename: String,
-        data: bytes::Bytes,
     },
+    /// Playlist of files already in media library.
+    LibraryPlaylist(PlaylistCfg),
 }
 
 pub trait FilesManagerSink: Send + Sync {
     fn get_tx(&self) -> tokio::sync::mpsc::Sender<FilesSourceType>;
 }
 
+/// Collects files in tests instead of files manager.
+#[cfg(test)]
+pub(crate) struct TestSink {
+    pub(crate) tx: tokio::sync::mpsc::Sender<FilesSourceType>,
+}
+
+#[cfg(test)]
+impl FilesManagerSink for TestSink {
+    fn get_tx(&self) -> tokio::sync::mpsc::Sender<FilesSourceType> {
+        self.tx.clone()
+    }
+}
+
 pub trait FilesSource: Send + Sync {
     type Handler: FilesSourceHandler<Error = Self::Error> + Send;
     type Error: std::error::Error + Send + Sync + 'static;
@@ -47,6 +93,12 @@ pub trait FileSubscriber: Send + Sync {
 
     /// Called when a new file is ready.
     fn on_new_file_available(&self, file_path: &Path) -> impl std::future::Future<Output = Result<(), FileSubscriberError>> + Send;
+
+    /// Called when a new set of files is ready, items are played in order.
+    fn on_new_playlist_available(&self, playlist: &Playlist) -> impl std::future::Future<Output = Result<(), FileSubscriberError>> + Send;
+
+    /// Called when playlist should replace current one once its loop ends.
+    fn on_playlist_queued(&self, playlist: &Playlist) -> impl std::future::Future<Output = Result<(), FileSubscriberError>> + Send;
 }
 
-pub type WiFiCredentialsProcedure = fn(&[u8]) -> Result<String, WifiManagerError>;
\ No newline at end of file
+pub type WiFiCredentialsProcedure = Arc<dyn WifiManager>;
\ No newline at end of file
@@ -0,0 +1,110 @@
+use std::{collections::VecDeque, fmt::Write, sync::{Arc, Mutex}};
+
+use serde::Serialize;
+use tracing_subscriber::layer::Context;
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct LogLine {
+    pub timestamp: String,
+    pub level: String,
+    pub target: String,
+    pub message: String,
+}
+
+/// Recent log lines kept in memory, e.g. for web dashboard.
+pub struct LogBuffer {
+    capacity: usize,
+    lines: Mutex<VecDeque<LogLine>>,
+}
+
+impl LogBuffer {
+    pub fn new(capacity: usize) -> Arc<Self> {
+        Arc::new(Self { capacity, lines: Mutex::new(VecDeque::with_capacity(capacity)) })
+    }
+
+    /// Oldest line first.
+    pub fn lines(&self) -> Vec<LogLine> {
+        self.lines.lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+            .iter()
+            .cloned()
+            .collect()
+    }
+
+    /// Tracing layer filling this buffer with events up to 'max_level'.
+    pub fn layer(self: &Arc<Self>, max_level: tracing::Level) -> LogBufferLayer {
+        LogBufferLayer { buffer: self.clone(), max_level }
+    }
+
+    fn push(&self, line: LogLine) {
+        let mut lines = self.lines.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+        if lines.len() >= self.capacity {
+            lines.pop_front();
+        }
+        lines.push_back(line);
+    }
+}
+
+pub struct LogBufferLayer {
+    buffer: Arc<LogBuffer>,
+    max_level: tracing::Level,
+}
+
+/// Message followed by other fields as 'name=value'.
+#[derive(Default)]
+struct MessageVisitor {
+    message: String,
+    fields: String,
+}
+
+impl tracing::field::Visit for MessageVisitor {
+    fn record_debug(&mut self, field: &tracing::field::Field, value: &dyn std::fmt::Debug) {
+        if field.name() == "message" {
+            let _ = write!(self.message, "{value:?}");
+        } else {
+            let _ = write!(self.fields, " {}={value:?}", field.name());
+        }
+    }
+}
+
+impl<S: tracing::Subscriber> tracing_subscriber::Layer<S> for LogBufferLayer {
+    fn on_event(&self, event: &tracing::Event<'_>, _ctx: Context<'_, S>) {
+        let metadata = event.metadata();
+        if *metadata.level() > self.max_level {
+            return;
+        }
+
+        let mut visitor = MessageVisitor::default();
+        event.record(&mut visitor);
+        self.buffer.push(LogLine {
+            timestamp: chrono::Local::now().format("%Y-%m-%d %H:%M:%S").to_string(),
+            level: metadata.level().to_string(),
+            target: metadata.target().to_string(),
+            message: visitor.message + &visitor.fields,
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use tracing_subscriber::layer::SubscriberExt;
+
+    use super::*;
+
+    #[test]
+    fn test_log_buffer_keeps_recent_lines() {
+        let log_buffer = LogBuffer::new(2);
+        let subscriber = tracing_subscriber::registry().with(log_buffer.layer(tracing::Level::INFO));
+        tracing::subscriber::with_default(subscriber, || {
+            tracing::info!("first");
+            tracing::debug!("filtered out");
+            tracing::warn!(attempt = 2, "second");
+            tracing::error!("third");
+        });
+
+        let lines = log_buffer.lines();
+        assert_eq!(lines.len(), 2);
+        assert_eq!((lines[0].level.as_str(), lines[0].message.as_str()), ("WARN", "second attempt=2"));
+        assert_eq!(lines[1].message, "third");
+    }
+}
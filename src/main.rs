@@ -1,25 +1,124 @@
 use std::{sync::Arc, time::Duration};
 
-use headless_pi_player::{file_manager::FilesManager, flash_drive_observer::FileSourceFlashDrive, video_player::VideoPlayer, webserver::WebServer, wifi_manager::wifi_manager_procedure, FilesSource, FilesSourceHandler};
+use headless_pi_player::{config::{ConfigStore, CONFIG_FILENAME}, connectivity_monitor::{ConnectivityMonitor, ConnectivityMonitorConfig, ConnectivityState}, events::{EventBus, SystemEvent}, file_manager::FilesManager, flash_drive_observer::FileSourceFlashDrive, input_source::InputSource, log_buffer::LogBuffer, resumable_upload::UploadStore, scheduler::Scheduler, splash::SplashInfo, sync::PlaybackSync, url_fetcher::FileSourceUrlFetch, video_player::{TextOverlay, VideoAdjustment, VideoPlayer}, webserver::WebServer, wifi_manager::{NmcliWifiManager, RetryPolicy, WifiTimeouts}, FilesSource, FilesSourceHandler, WiFiCredentialsProcedure};
 
-fn init_tracing() {
-    let _ = tracing_subscriber::fmt()
-        .with_max_level(tracing::Level::DEBUG)
+const IP_NOTICE_TIMEOUT_MS: u32 = 15000;
+
+/// Log lines shown by web dashboard.
+const LOG_BUFFER_LINES: usize = 200;
+
+/// Shows IP address on screen each time it changes, keeps splash up to date if 'device_name' is given.
+fn spawn_network_notices(
+    video_player: Arc<VideoPlayer>,
+    mut connectivity_rx: tokio::sync::watch::Receiver<ConnectivityState>,
+    device_name: Option<String>,
+) -> tokio::task::JoinHandle<()> {
+    tokio::spawn(async move {
+        let mut last_ip_address = None;
+        if let Some(device_name) = &device_name {
+            video_player.set_splash(SplashInfo { device_name: device_name.clone(), ip_address: None, web_url: None });
+        }
+
+        while connectivity_rx.changed().await.is_ok() {
+            let ip_address = match &*connectivity_rx.borrow_and_update() {
+                ConnectivityState::Online { ip_address, .. }
+                | ConnectivityState::GatewayUnreachable { ip_address, .. } => Some(ip_address.clone()),
+                ConnectivityState::LinkDown => None,
+                ConnectivityState::Unknown | ConnectivityState::Reconnecting { .. } => last_ip_address.clone(),
+            };
+            if ip_address == last_ip_address {
+                continue;
+            }
+
+            if let Some(ip_address) = &ip_address {
+                video_player.show_text(TextOverlay::new(format!("IP: {ip_address}")).with_timeout_ms(IP_NOTICE_TIMEOUT_MS));
+            }
+            if let Some(device_name) = &device_name {
+                video_player.set_splash(SplashInfo {
+                    device_name: device_name.clone(),
+                    web_url: ip_address.as_ref().map(|ip_address| format!("http://{ip_address}:{}/", WebServer::PORT)),
+                    ip_address: ip_address.clone(),
+                });
+            }
+            last_ip_address = ip_address;
+        }
+    })
+}
+
+fn device_hostname() -> String {
+    std::fs::read_to_string("/proc/sys/kernel/hostname")
+        .map(|hostname| hostname.trim().to_string())
+        .unwrap_or_else(|_| env!("CARGO_PKG_NAME").to_string())
+}
+
+fn init_tracing(log_buffer: &Arc<LogBuffer>) {
+    use tracing_subscriber::{layer::SubscriberExt, util::SubscriberInitExt};
+
+    let _ = tracing_subscriber::registry()
+        .with(tracing_subscriber::filter::LevelFilter::DEBUG)
+        .with(tracing_subscriber::fmt::layer())
+        .with(log_buffer.layer(tracing::Level::INFO))
         .try_init();
 }
 
 #[tokio::main]
 async fn main() {
-    init_tracing();
+    let log_buffer = LogBuffer::new(LOG_BUFFER_LINES);
+    init_tracing(&log_buffer);
+
+    let state_path = FilesManager::resolve_state_path();
+    let config_store = Arc::new(ConfigStore::load(state_path.join(CONFIG_FILENAME)).await);
+    let config = config_store.get();
 
-    let video_player = VideoPlayer::run(true).await;
+    let video_player = VideoPlayer::run(true, config.player.clone()).await;
     let video_player = Arc::new(video_player);
 
+    if let Some(logo) = config.logo {
+        video_player.show_logo(logo);
+    }
+
+    if config.adjustment != VideoAdjustment::default() {
+        video_player.set_adjustment(config.adjustment);
+    }
+
+    let wifi_manager: WiFiCredentialsProcedure = Arc::new(NmcliWifiManager::new(
+        WifiTimeouts::default(),
+        RetryPolicy::default(),
+        state_path.join("certs")
+    ));
+
+    let connectivity_monitor = ConnectivityMonitor::new(
+        ConnectivityMonitorConfig::default(),
+        Some(wifi_manager.clone())
+    ).start();
+
+    let splash_device_name = config.splash.enabled
+        .then(|| config.splash.device_name.clone().unwrap_or_else(device_hostname));
+    let _network_notices_task = spawn_network_notices(video_player.clone(), connectivity_monitor.subscribe(), splash_device_name);
+
+    let event_bus = Arc::new(EventBus::new());
+    let _player_events_task = event_bus.forward(video_player.subscribe(), SystemEvent::Player);
+    let _wifi_events_task = event_bus.forward(wifi_manager.subscribe(), SystemEvent::Wifi);
+    let _network_events_task = event_bus.forward_watch(connectivity_monitor.subscribe(), SystemEvent::Network);
+
+    let playback_sync = PlaybackSync::new(config.sync.clone(), video_player.clone()).start().await
+        .unwrap_or_else(|e| {
+            tracing::error!("Could not start playback sync, reason = {e}");
+            None
+        });
+
     let files_manager = FilesManager::new::<VideoPlayer>(
-        Some(video_player),
-        Some(wifi_manager_procedure)
+        Some(video_player.clone()),
+        Some(wifi_manager.clone()),
+        Some(config_store.clone()),
+        Some(event_bus.clone()),
     ).await.expect("Could not create files manager");
     let media_user_path = files_manager.get_media_user_path();
+    let media_path = files_manager.get_media_path();
+    let pending_activation_rx = files_manager.subscribe_pending_activation();
+    let _activation_events_task = event_bus.forward_watch(files_manager.subscribe_pending_activation(), SystemEvent::Activation);
+    let upload_store = UploadStore::open(state_path.join("uploads"), WebServer::MAX_VIDEO_FILESIZE_BYTES as u64).await
+        .expect("Could not open upload store");
 
     // Spawn shutdown signal
     let shutdown_notify = Arc::new(tokio::sync::Notify::new());
@@ -36,9 +135,47 @@ async fn main() {
     let files_manager = Arc::new(files_manager);
 
     let source_flash_drive = FileSourceFlashDrive::new(media_user_path).await
+        .with_event_bus(event_bus.clone())
         .start(files_manager.clone()).await.expect("msg");
 
-    let web_server = WebServer
+    let source_url_fetch = FileSourceUrlFetch::new(state_path.join("downloads"), config.fetch.clone())
+        .with_event_bus(event_bus.clone());
+    let url_fetcher = source_url_fetch.fetcher();
+    let source_url_fetch = source_url_fetch.start(files_manager.clone()).await.expect("Could not start URL fetch");
+
+    let scheduler = Scheduler::new(config.schedule.clone())
+        .with_video_player(video_player.clone());
+    let schedule_rx = scheduler.subscribe();
+    let _schedule_events_task = event_bus.forward_watch(scheduler.subscribe(), SystemEvent::Schedule);
+    let scheduler = scheduler.start(files_manager.clone()).await.expect("Could not start scheduler");
+
+    let source_input = if config.input.is_enabled() {
+        let source_input = InputSource::new(config.input.clone())
+            .with_video_player(video_player.clone())
+            .start(files_manager.clone()).await.expect("Could not start input source");
+        Some(source_input)
+    } else {
+        None
+    };
+
+    let mut web_server = WebServer::new();
+    if let Some(playback_sync) = &playback_sync {
+        let _sync_events_task = event_bus.forward_watch(playback_sync.subscribe(), SystemEvent::Sync);
+        web_server = web_server.with_sync(playback_sync.subscribe());
+    }
+    let web_server = web_server
+        .with_wifi_manager(wifi_manager)
+        .with_connectivity(connectivity_monitor.subscribe())
+        .with_video_player(video_player)
+        .with_config_store(config_store)
+        .with_event_bus(event_bus)
+        .with_media_library(media_path)
+        .with_state_path(state_path)
+        .with_log_buffer(log_buffer)
+        .with_upload_store(Arc::new(upload_store))
+        .with_url_fetcher(url_fetcher)
+        .with_schedule(schedule_rx)
+        .with_pending_activation(pending_activation_rx)
         .start(files_manager.clone()).await.expect("Could not start web server");
 
     // Wait for Ctrl+C
@@ -47,6 +184,15 @@ async fn main() {
     // Gracefully shut down
     source_flash_drive.shutdown().await.expect("Failed to shut down FLASH drive source");
     web_server.shutdown().await.expect("Failed to shut down webserver source");
+    source_url_fetch.shutdown().await.expect("Failed to shut down URL fetch source");
+    scheduler.shutdown().await.expect("Failed to shut down scheduler");
+    if let Some(source_input) = source_input {
+        source_input.shutdown().await.expect("Failed to shut down input source");
+    }
+    connectivity_monitor.shutdown().await.expect("Failed to shut down connectivity monitor");
+    if let Some(playback_sync) = playback_sync {
+        playback_sync.shutdown().await.expect("Failed to shut down playback sync");
+    }
     drop(files_manager);
     tokio::time::sleep(Duration::from_millis(500)).await;
     tracing::info!("Shutdown complete.");
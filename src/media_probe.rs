@@ -0,0 +1,165 @@
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+use tokio::io::AsyncReadExt;
+
+use crate::playlist::MediaKind;
+
+/// Enough to find Matroska DocType and MP4 brand.
+const HEADER_LEN: usize = 4096;
+
+/// QuickTime files without 'ftyp' start with one of these atoms.
+const QUICKTIME_ATOMS: &[&[u8; 4]] = &[b"moov", b"mdat", b"wide", b"free", b"skip", b"pnot"];
+
+/// Major brands of MP4, M4V and 3GP video, other ISO files like HEIC or AVIF stills are rejected.
+const MP4_BRANDS: &[&[u8; 4]] = &[
+    b"isom", b"iso2", b"iso3", b"iso4", b"iso5", b"iso6", b"mp41", b"mp42", b"avc1", b"dash", b"mmp4", b"MSNV", b"XAVC", b"f4v ",
+    b"M4V ", b"M4VH", b"M4VP", b"3gp4", b"3gp5", b"3gp6", b"3g2a",
+];
+
+#[derive(Debug, thiserror::Error)]
+pub enum MediaProbeError {
+    #[error("StdIoError reason = '{0}'")]
+    StdIoError(#[from] std::io::Error),
+
+    #[error("UnknownFormat, file is not a supported video or image")]
+    UnknownFormat,
+
+    #[error("FormatNotAllowed format = '{0:?}'")]
+    FormatNotAllowed(MediaFormat),
+}
+
+/// Container detected from file content, extension is ignored.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum MediaFormat {
+    Avi,
+    /// Also '.m4v'.
+    Mp4,
+    Mov,
+    Matroska,
+    Webm,
+    Png,
+    Jpeg,
+}
+
+impl MediaFormat {
+    pub const ALL: &[MediaFormat] = &[
+        MediaFormat::Avi,
+        MediaFormat::Mp4,
+        MediaFormat::Mov,
+        MediaFormat::Matroska,
+        MediaFormat::Webm,
+        MediaFormat::Png,
+        MediaFormat::Jpeg,
+    ];
+
+    pub fn kind(self) -> MediaKind {
+        match self {
+            MediaFormat::Png | MediaFormat::Jpeg => MediaKind::Image,
+            _ => MediaKind::Video,
+        }
+    }
+
+    /// Detects format by magic bytes.
+    pub fn detect(header: &[u8]) -> Option<Self> {
+        if header.starts_with(b"\x89PNG\r\n\x1a\n") {
+            return Some(MediaFormat::Png);
+        }
+        if header.starts_with(&[0xFF, 0xD8, 0xFF]) {
+            return Some(MediaFormat::Jpeg);
+        }
+        if header.starts_with(b"RIFF") && header.get(8..12) == Some(b"AVI ") {
+            return Some(MediaFormat::Avi);
+        }
+        if header.starts_with(&[0x1A, 0x45, 0xDF, 0xA3]) {
+            let is_webm = header.windows(4).any(|window| window == b"webm");
+            return Some(if is_webm { MediaFormat::Webm } else { MediaFormat::Matroska });
+        }
+
+        let atom = header.get(4..8)?;
+        if atom == b"ftyp" {
+            let brand = header.get(8..12)?;
+            if brand == b"qt  " {
+                return Some(MediaFormat::Mov);
+            }
+            return MP4_BRANDS.iter().any(|&known| brand == known).then_some(MediaFormat::Mp4);
+        }
+        if QUICKTIME_ATOMS.iter().any(|&known| atom == known) {
+            return Some(MediaFormat::Mov);
+        }
+        None
+    }
+
+    pub async fn detect_file(path: &Path) -> Result<Self, MediaProbeError> {
+        let mut header = Vec::with_capacity(HEADER_LEN);
+        tokio::fs::File::open(path).await?
+            .take(HEADER_LEN as u64)
+            .read_to_end(&mut header).await?;
+        Self::detect(&header).ok_or(MediaProbeError::UnknownFormat)
+    }
+}
+
+/// Media accepted from FLASH drive and web server.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MediaConfig {
+    #[serde(default = "MediaConfig::default_allowed_formats")]
+    pub allowed_formats: Vec<MediaFormat>,
+}
+
+impl MediaConfig {
+    fn default_allowed_formats() -> Vec<MediaFormat> {
+        MediaFormat::ALL.to_vec()
+    }
+
+    pub fn probe(&self, header: &[u8]) -> Result<MediaFormat, MediaProbeError> {
+        let format = MediaFormat::detect(header).ok_or(MediaProbeError::UnknownFormat)?;
+        self.check_allowed(format)
+    }
+
+    pub async fn probe_file(&self, path: &Path) -> Result<MediaFormat, MediaProbeError> {
+        let format = MediaFormat::detect_file(path).await?;
+        self.check_allowed(format)
+    }
+
+    fn check_allowed(&self, format: MediaFormat) -> Result<MediaFormat, MediaProbeError> {
+        if self.allowed_formats.contains(&format) {
+            Ok(format)
+        } else {
+            Err(MediaProbeError::FormatNotAllowed(format))
+        }
+    }
+}
+
+impl Default for MediaConfig {
+    fn default() -> Self {
+        Self {
+            allowed_formats: Self::default_allowed_formats(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_detect_media_format() {
+        assert_eq!(MediaFormat::detect(b"RIFF\x10\x00\x00\x00AVI LIST"), Some(MediaFormat::Avi));
+        assert_eq!(MediaFormat::detect(b"\x00\x00\x00\x20ftypisom\x00\x00\x02\x00"), Some(MediaFormat::Mp4));
+        assert_eq!(MediaFormat::detect(b"\x00\x00\x00\x1cftypM4V \x00\x00\x00\x01"), Some(MediaFormat::Mp4));
+        assert_eq!(MediaFormat::detect(b"\x00\x00\x00\x14ftypqt  \x00\x00\x00\x00"), Some(MediaFormat::Mov));
+        assert_eq!(MediaFormat::detect(b"\x00\x00\x00\x18ftypheic\x00\x00\x00\x00mif1heic"), None);
+        assert_eq!(MediaFormat::detect(b"\x00\x00\x00\x1cftypavif\x00\x00\x00\x00avifmif1"), None);
+        assert_eq!(MediaFormat::detect(b"\x1a\x45\xdf\xa3\x9f\x42\x86\x81\x01\x42\x82\x84webm"), Some(MediaFormat::Webm));
+        assert_eq!(MediaFormat::detect(b"\x1a\x45\xdf\xa3\xa3\x42\x86\x81\x01\x42\x82\x88matroska"), Some(MediaFormat::Matroska));
+        assert_eq!(MediaFormat::detect(b"\x89PNG\r\n\x1a\n\x00\x00\x00\x0dIHDR"), Some(MediaFormat::Png));
+        assert_eq!(MediaFormat::detect(b"\xff\xd8\xff\xe0\x00\x10JFIF"), Some(MediaFormat::Jpeg));
+        assert_eq!(MediaFormat::detect(b"{\"ssid\": \"renamed.mp4\"}"), None);
+        assert_eq!(MediaFormat::detect(b""), None);
+
+        let config = MediaConfig { allowed_formats: vec![MediaFormat::Mp4] };
+        assert!(matches!(config.probe(b"\x89PNG\r\n\x1a\n"), Err(MediaProbeError::FormatNotAllowed(MediaFormat::Png))));
+        assert!(matches!(config.probe(b"plain text"), Err(MediaProbeError::UnknownFormat)));
+    }
+}
@@ -0,0 +1,110 @@
+use std::{path::PathBuf, time::Duration};
+
+use serde::{Deserialize, Serialize};
+
+use crate::video_player::DisplayGeometry;
+
+/// Network MRL schemes accepted as stream items.
+const STREAM_SCHEMES: &[&str] = &["http", "https", "rtsp", "rtp", "udp", "rtmp", "mms", "srt"];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum MediaKind {
+    Video,
+    Image,
+    /// Network stream opened by VLC, e.g. HLS, RTSP camera or UDP multicast.
+    Stream,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct PlaylistItem {
+    /// File path, or MRL of stream.
+    pub path: PathBuf,
+    pub kind: MediaKind,
+
+    /// How long image or stream is shown, stream is shown until skipped if missing. Not used by videos.
+    pub duration: Option<Duration>,
+
+    /// Overrides device geometry for this item.
+    pub geometry: Option<DisplayGeometry>,
+}
+
+impl PlaylistItem {
+    pub fn new(path: PathBuf, kind: MediaKind) -> Self {
+        Self { path, kind, duration: None, geometry: None }
+    }
+
+    /// Stream item, e.g. 'rtsp://camera.local/live' or 'udp://@239.0.0.1:1234'.
+    pub fn stream(mrl: &str) -> Result<Self, String> {
+        let is_supported = mrl.split_once("://").is_some_and(|(scheme, rest)| {
+            STREAM_SCHEMES.contains(&scheme.to_ascii_lowercase().as_str()) && !rest.is_empty()
+        });
+        if !is_supported || mrl.chars().any(|c| c.is_whitespace() || c.is_control()) {
+            return Err(format!("Stream URL '{mrl}' not supported, use one of {}", STREAM_SCHEMES.join(", ")));
+        }
+        Ok(Self::new(PathBuf::from(mrl), MediaKind::Stream))
+    }
+}
+
+/// Items are played in order, whole playlist is looped.
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct Playlist {
+    pub items: Vec<PlaylistItem>,
+}
+
+impl Playlist {
+    pub fn single(item: PlaylistItem) -> Self {
+        Self { items: vec![item] }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.items.is_empty()
+    }
+}
+
+/// Playlist file provided by user, e.g. on FLASH drive.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PlaylistCfg {
+    pub items: Vec<PlaylistItemCfg>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PlaylistItemCfg {
+    /// File name relative to playlist file directory.
+    #[serde(default)]
+    pub file: String,
+
+    /// Network stream played instead of 'file'.
+    #[serde(default)]
+    pub url: Option<String>,
+
+    /// Image or stream display time.
+    #[serde(default)]
+    pub duration_secs: Option<f32>,
+
+    #[serde(default)]
+    pub geometry: Option<DisplayGeometry>,
+}
+
+impl PlaylistItemCfg {
+    pub fn duration(&self) -> Option<Duration> {
+        self.duration_secs
+            .filter(|duration_secs| duration_secs.is_finite() && *duration_secs > 0.0)
+            .map(Duration::from_secs_f32)
+    }
+
+    /// Item of 'url', None for file items.
+    pub fn stream_item(&self) -> Option<Result<PlaylistItem, String>> {
+        let url = self.url.as_ref()?;
+        Some(PlaylistItem::stream(url).map(|mut item| {
+            item.duration = self.duration();
+            item.geometry = self.geometry.clone();
+            item
+        }))
+    }
+
+    /// File or URL, for messages.
+    pub fn name(&self) -> &str {
+        self.url.as_deref().unwrap_or(&self.file)
+    }
+}
@@ -0,0 +1,311 @@
+use std::{collections::HashSet, path::{Path, PathBuf}, sync::{atomic::{AtomicU64, Ordering}, Mutex}, time::{Duration, SystemTime}};
+
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use tokio::io::AsyncWriteExt;
+use tokio_stream::{Stream, StreamExt};
+
+use crate::file_manager::{FilesManager, UploadActivation};
+
+/// Unfinished uploads older than this are removed.
+const UPLOAD_EXPIRY: Duration = Duration::from_secs(24 * 60 * 60);
+
+/// Unfinished uploads kept at once, each takes up to 'max_size' of disk.
+const MAX_UPLOADS: usize = 4;
+
+const ID_LEN: usize = 32;
+
+#[derive(Debug, thiserror::Error)]
+pub enum UploadError {
+    #[error("TokioIoError reason = '{0}'")]
+    TokioIoError(#[from] tokio::io::Error),
+
+    #[error("SerdeJsonError reason = '{0}'")]
+    SerdeJsonError(#[from] serde_json::Error),
+
+    #[error("NotFound id = '{0}'")]
+    NotFound(String),
+
+    #[error("InvalidFileName name = '{0}'")]
+    InvalidFileName(String),
+
+    #[error("TooLarge max_size = '{0}'")]
+    TooLarge(u64),
+
+    #[error("OffsetMismatch expected = '{expected}', received = '{received}'")]
+    OffsetMismatch { expected: u64, received: u64 },
+
+    #[error("ExceedsLength length = '{0}'")]
+    ExceedsLength(u64),
+
+    #[error("TooMany max = '{0}'")]
+    TooMany(usize),
+
+    #[error("Busy id = '{0}'")]
+    Busy(String),
+
+    #[error("Interrupted reason = '{0}'")]
+    Interrupted(String),
+}
+
+/// Stored next to received data, offset is length of data file.
+#[derive(Debug, Serialize, Deserialize)]
+struct UploadMeta {
+    filename: String,
+    length: u64,
+    #[serde(default)]
+    activation: UploadActivation,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct UploadInfo {
+    pub id: String,
+    pub filename: String,
+    pub length: u64,
+    pub offset: u64,
+    pub activation: UploadActivation,
+}
+
+impl UploadInfo {
+    pub fn is_complete(&self) -> bool {
+        self.offset == self.length
+    }
+}
+
+/// Uploads received in chunks, interrupted upload continues from stored offset, also after restart.
+pub struct UploadStore {
+    dir: PathBuf,
+    max_size: u64,
+    /// Uploads receiving data, concurrent requests for same upload are rejected.
+    active: Mutex<HashSet<String>>,
+    /// Uploads are counted and created one at a time.
+    create_lock: tokio::sync::Mutex<()>,
+    counter: AtomicU64,
+}
+
+/// Marks upload as active until dropped.
+struct ActiveUpload<'a> {
+    store: &'a UploadStore,
+    id: String,
+}
+
+impl Drop for ActiveUpload<'_> {
+    fn drop(&mut self) {
+        self.store.active.lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+            .remove(&self.id);
+    }
+}
+
+impl UploadStore {
+    /// Creates directory and removes expired uploads.
+    pub async fn open(dir: PathBuf, max_size: u64) -> Result<Self, UploadError> {
+        tokio::fs::create_dir_all(&dir).await?;
+        let store = Self { dir, max_size, active: Mutex::new(HashSet::new()), create_lock: tokio::sync::Mutex::new(()), counter: AtomicU64::new(0) };
+        store.remove_expired().await;
+        Ok(store)
+    }
+
+    pub fn max_size(&self) -> u64 {
+        self.max_size
+    }
+
+    /// Expired uploads are removed first, so abandoned ones do not count.
+    pub async fn create(&self, filename: &str, length: u64, activation: UploadActivation) -> Result<UploadInfo, UploadError> {
+        if !FilesManager::is_valid_file_name(filename) {
+            return Err(UploadError::InvalidFileName(filename.to_string()));
+        }
+        if length > self.max_size {
+            return Err(UploadError::TooLarge(self.max_size));
+        }
+
+        let _create_guard = self.create_lock.lock().await;
+        self.remove_expired().await;
+        if self.unfinished_count().await? >= MAX_UPLOADS {
+            return Err(UploadError::TooMany(MAX_UPLOADS));
+        }
+
+        let id = self.new_id(filename);
+        let meta = UploadMeta { filename: filename.to_string(), length, activation };
+        tokio::fs::write(self.data_path(&id), b"").await?;
+        tokio::fs::write(self.meta_path(&id), serde_json::to_vec(&meta)?).await?;
+        tracing::info!("Created upload {id} of {filename:?}, {length} bytes");
+        Ok(UploadInfo { id, filename: meta.filename, length, offset: 0, activation })
+    }
+
+    pub async fn info(&self, id: &str) -> Result<UploadInfo, UploadError> {
+        if !Self::is_valid_id(id) {
+            return Err(UploadError::NotFound(id.to_string()));
+        }
+        let content = match tokio::fs::read(self.meta_path(id)).await {
+            Ok(content) => content,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Err(UploadError::NotFound(id.to_string())),
+            Err(e) => return Err(e.into()),
+        };
+        let meta: UploadMeta = serde_json::from_slice(&content)?;
+        let offset = tokio::fs::metadata(self.data_path(id)).await?.len();
+        Ok(UploadInfo { id: id.to_string(), filename: meta.filename, length: meta.length, offset, activation: meta.activation })
+    }
+
+    /// Appends chunks at 'offset', data received before error is kept.
+    pub async fn append<S, E>(&self, id: &str, offset: u64, mut chunks: S) -> Result<UploadInfo, UploadError>
+    where
+        S: Stream<Item = Result<bytes::Bytes, E>> + Unpin,
+        E: std::fmt::Display,
+    {
+        let _active = self.activate(id)?;
+        let info = self.info(id).await?;
+        if offset != info.offset {
+            return Err(UploadError::OffsetMismatch { expected: info.offset, received: offset });
+        }
+
+        let mut file = tokio::fs::OpenOptions::new().append(true).open(self.data_path(id)).await?;
+        let mut written = info.offset;
+        let result = loop {
+            match chunks.next().await {
+                Some(Ok(chunk)) => {
+                    if written + chunk.len() as u64 > info.length {
+                        break Err(UploadError::ExceedsLength(info.length));
+                    }
+                    if let Err(e) = file.write_all(&chunk).await {
+                        break Err(e.into());
+                    }
+                    written += chunk.len() as u64;
+                },
+                Some(Err(e)) => break Err(UploadError::Interrupted(e.to_string())),
+                None => break Ok(()),
+            }
+        };
+
+        // Offset must match stored data when client resumes
+        file.flush().await?;
+        file.sync_data().await?;
+        result?;
+        Ok(UploadInfo { offset: written, ..info })
+    }
+
+    /// Forgets finished upload, returned data file is owned by caller.
+    pub async fn take_completed(&self, id: &str) -> Result<(UploadInfo, PathBuf), UploadError> {
+        let _active = self.activate(id)?;
+        let info = self.info(id).await?;
+        if !info.is_complete() {
+            return Err(UploadError::OffsetMismatch { expected: info.length, received: info.offset });
+        }
+        tokio::fs::remove_file(self.meta_path(id)).await?;
+        Ok((info, self.data_path(id)))
+    }
+
+    pub async fn remove(&self, id: &str) -> Result<(), UploadError> {
+        let _active = self.activate(id)?;
+        self.info(id).await?;
+        tokio::fs::remove_file(self.meta_path(id)).await?;
+        tokio::fs::remove_file(self.data_path(id)).await?;
+        tracing::info!("Removed upload {id}");
+        Ok(())
+    }
+
+    fn activate(&self, id: &str) -> Result<ActiveUpload<'_>, UploadError> {
+        let mut active = self.active.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+        if !active.insert(id.to_string()) {
+            return Err(UploadError::Busy(id.to_string()));
+        }
+        Ok(ActiveUpload { store: self, id: id.to_string() })
+    }
+
+    fn new_id(&self, filename: &str) -> String {
+        let mut hasher = Sha256::new();
+        hasher.update(filename.as_bytes());
+        hasher.update(std::process::id().to_le_bytes());
+        hasher.update(self.counter.fetch_add(1, Ordering::Relaxed).to_le_bytes());
+        let nanos = SystemTime::now().duration_since(SystemTime::UNIX_EPOCH).unwrap_or_default().as_nanos();
+        hasher.update(nanos.to_le_bytes());
+        format!("{:x}", hasher.finalize())[..ID_LEN].to_string()
+    }
+
+    /// Id is used in file names, anything else is rejected.
+    fn is_valid_id(id: &str) -> bool {
+        id.len() == ID_LEN && id.bytes().all(|byte| byte.is_ascii_hexdigit())
+    }
+
+    fn meta_path(&self, id: &str) -> PathBuf {
+        self.dir.join(format!("{id}.json"))
+    }
+
+    fn data_path(&self, id: &str) -> PathBuf {
+        self.dir.join(format!("{id}.part"))
+    }
+
+    async fn remove_expired(&self) {
+        let Ok(mut entries) = tokio::fs::read_dir(&self.dir).await else {
+            return;
+        };
+        while let Ok(Some(entry)) = entries.next_entry().await {
+            let path = entry.path();
+            let is_expired = Self::modified_age(&path).await.is_some_and(|age| age > UPLOAD_EXPIRY);
+            if is_expired {
+                tracing::info!("Removing expired upload {path:?}");
+                let _ = tokio::fs::remove_file(&path).await;
+            }
+        }
+    }
+
+    async fn unfinished_count(&self) -> Result<usize, UploadError> {
+        let mut entries = tokio::fs::read_dir(&self.dir).await?;
+        let mut count = 0;
+        while let Some(entry) = entries.next_entry().await? {
+            if entry.path().extension().is_some_and(|extension| extension == "json") {
+                count += 1;
+            }
+        }
+        Ok(count)
+    }
+
+    async fn modified_age(path: &Path) -> Option<Duration> {
+        let modified = tokio::fs::metadata(path).await.ok()?.modified().ok()?;
+        modified.elapsed().ok()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn chunks(parts: &[&'static [u8]]) -> impl Stream<Item = Result<bytes::Bytes, String>> + Unpin {
+        tokio_stream::iter(parts.iter().map(|part| Ok(bytes::Bytes::from_static(part))).collect::<Vec<_>>())
+    }
+
+    #[tokio::test]
+    async fn test_upload_resumes_from_stored_offset() {
+        let dir = std::env::temp_dir().join(format!("headlesspiplayer_uploads_test_{}", std::process::id()));
+        let store = UploadStore::open(dir.clone(), 16).await.unwrap();
+        assert!(matches!(store.create("big.mp4", 17, UploadActivation::PlayNow).await, Err(UploadError::TooLarge(16))));
+        assert!(matches!(store.create("../big.mp4", 8, UploadActivation::PlayNow).await, Err(UploadError::InvalidFileName(_))));
+
+        let info = store.create("clip.mp4", 8, UploadActivation::StoreOnly).await.unwrap();
+        let interrupted = tokio_stream::iter(vec![Ok(bytes::Bytes::from_static(b"abc")), Err("connection reset".to_string())]);
+        let result = store.append(&info.id, 0, interrupted).await;
+        assert!(matches!(result, Err(UploadError::Interrupted(_))));
+
+        // Store opened again, e.g. after restart
+        let store = UploadStore::open(dir.clone(), 16).await.unwrap();
+        assert_eq!(store.info(&info.id).await.unwrap().offset, 3);
+        assert!(matches!(store.append(&info.id, 0, chunks(&[b"abc"])).await, Err(UploadError::OffsetMismatch { expected: 3, received: 0 })));
+        assert!(matches!(store.append(&info.id, 3, chunks(&[b"defghi"])).await, Err(UploadError::ExceedsLength(8))));
+        assert!(store.take_completed(&info.id).await.is_err());
+
+        let info = store.append(&info.id, 3, chunks(&[b"de", b"fgh"])).await.unwrap();
+        assert!(info.is_complete());
+        let (info, data_path) = store.take_completed(&info.id).await.unwrap();
+        assert_eq!(tokio::fs::read(&data_path).await.unwrap(), b"abcdefgh");
+        assert_eq!(info.activation, UploadActivation::StoreOnly);
+        assert!(matches!(store.info(&info.id).await, Err(UploadError::NotFound(_))));
+        assert!(matches!(store.info("../../etc/passwd").await, Err(UploadError::NotFound(_))));
+
+        for _ in 0..MAX_UPLOADS {
+            store.create("clip.mp4", 8, UploadActivation::StoreOnly).await.unwrap();
+        }
+        assert!(matches!(store.create("clip.mp4", 8, UploadActivation::StoreOnly).await, Err(UploadError::TooMany(MAX_UPLOADS))));
+
+        let _ = tokio::fs::remove_dir_all(&dir).await;
+    }
+}
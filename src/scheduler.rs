@@ -0,0 +1,525 @@
+use std::{sync::Arc, time::Duration};
+
+use chrono::{Datelike, NaiveDate, NaiveDateTime, Timelike};
+use serde::{Deserialize, Serialize};
+use tokio::process::Command;
+use tokio_util::sync::CancellationToken;
+
+use crate::{playlist::PlaylistCfg, video_player::VideoPlayer, FilesManagerSink, FilesSource, FilesSourceHandler, FilesSourceType};
+
+/// Rules are checked at least this often, wall clock may jump, e.g. after NTP sync.
+const MAX_WAIT: Duration = Duration::from_secs(60);
+const DISPLAY_COMMAND_TIMEOUT: Duration = Duration::from_secs(10);
+/// Occurrences are searched this far, covers February 29th.
+const SEARCH_DAYS: u64 = 4 * 366;
+const TIME_FORMAT: &str = "%Y-%m-%d %H:%M";
+
+#[derive(Debug, thiserror::Error)]
+pub enum ScheduleError {
+    #[error("TokioIoError reason = '{0}'")]
+    TokioIoError(#[from] tokio::io::Error),
+
+    #[error("TokioJoinError")]
+    TokioJoinError(#[from] tokio::task::JoinError),
+
+    #[error("InvalidCron expression = '{0}'")]
+    InvalidCron(String),
+
+    #[error("CommandFailed reason = '{0}'")]
+    CommandFailed(String),
+
+    #[error("Timeout")]
+    Timeout,
+
+    #[error("Stopped")]
+    Stopped,
+}
+
+/// Time-of-day rules, e.g. morning and evening playlists and display off at night.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ScheduleConfig {
+    #[serde(default)]
+    pub rules: Vec<ScheduleRule>,
+
+    #[serde(default)]
+    pub display: DisplayPowerConfig,
+
+    /// Latest past playback and display actions are applied at startup.
+    #[serde(default = "ScheduleConfig::default_catch_up")]
+    pub catch_up: bool,
+}
+
+impl ScheduleConfig {
+    fn default_catch_up() -> bool {
+        true
+    }
+}
+
+impl Default for ScheduleConfig {
+    fn default() -> Self {
+        Self {
+            rules: vec![],
+            display: DisplayPowerConfig::default(),
+            catch_up: Self::default_catch_up(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ScheduleRule {
+    /// Shown in status, cron expression if missing.
+    #[serde(default)]
+    pub name: Option<String>,
+
+    /// 'minute hour day-of-month month day-of-week' in local time, e.g. '30 7 * * 1-5'.
+    pub cron: String,
+
+    #[serde(flatten)]
+    pub action: ScheduleAction,
+}
+
+impl ScheduleRule {
+    fn label(&self) -> String {
+        self.name.clone().unwrap_or_else(|| self.cron.clone())
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "action", rename_all = "snake_case")]
+pub enum ScheduleAction {
+    /// Files of media library or streams, replaces current playback.
+    PlayPlaylist(PlaylistCfg),
+    /// Splash is shown if enabled.
+    Stop,
+    DisplayOn,
+    DisplayOff,
+}
+
+/// Only latest due action of each kind is applied after a gap, e.g. at startup.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ActionKind {
+    Playback,
+    Display,
+}
+
+impl ScheduleAction {
+    pub fn name(&self) -> &'static str {
+        match self {
+            ScheduleAction::PlayPlaylist(_) => "play_playlist",
+            ScheduleAction::Stop => "stop",
+            ScheduleAction::DisplayOn => "display_on",
+            ScheduleAction::DisplayOff => "display_off",
+        }
+    }
+
+    fn kind(&self) -> ActionKind {
+        match self {
+            ScheduleAction::PlayPlaylist(_) | ScheduleAction::Stop => ActionKind::Playback,
+            ScheduleAction::DisplayOn | ScheduleAction::DisplayOff => ActionKind::Display,
+        }
+    }
+}
+
+/// Command that switches display power.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "backend", rename_all = "snake_case")]
+pub enum DisplayPowerConfig {
+    /// Wayland compositors, e.g. labwc or wayfire on Raspberry Pi OS.
+    WlrRandr {
+        #[serde(default = "DisplayPowerConfig::default_output")]
+        output: String,
+    },
+    /// X11 DPMS.
+    Xset,
+    /// Legacy Raspberry Pi firmware.
+    Vcgencmd,
+}
+
+impl DisplayPowerConfig {
+    fn default_output() -> String {
+        "HDMI-A-1".to_string()
+    }
+
+    /// Program and arguments.
+    pub fn command(&self, on: bool) -> Vec<String> {
+        let args: Vec<&str> = match self {
+            DisplayPowerConfig::WlrRandr { output } => vec!["wlr-randr", "--output", output, if on { "--on" } else { "--off" }],
+            DisplayPowerConfig::Xset => vec!["xset", "dpms", "force", if on { "on" } else { "off" }],
+            DisplayPowerConfig::Vcgencmd => vec!["vcgencmd", "display_power", if on { "1" } else { "0" }],
+        };
+        args.into_iter().map(String::from).collect()
+    }
+
+    pub async fn set_power(&self, on: bool) -> Result<(), ScheduleError> {
+        let command = self.command(on);
+        let (program, args) = command.split_first()
+            .ok_or_else(|| ScheduleError::CommandFailed("Missing display command".to_string()))?;
+
+        let output = tokio::time::timeout(
+            DISPLAY_COMMAND_TIMEOUT,
+            Command::new(program).args(args).kill_on_drop(true).output(),
+        ).await.map_err(|_| ScheduleError::Timeout)??;
+
+        if output.status.success() {
+            Ok(())
+        } else {
+            let stderr_str = String::from_utf8_lossy(&output.stderr);
+            Err(ScheduleError::CommandFailed(format!("{program} failed with status {}: {}", output.status, stderr_str.trim())))
+        }
+    }
+}
+
+impl Default for DisplayPowerConfig {
+    fn default() -> Self {
+        DisplayPowerConfig::WlrRandr { output: Self::default_output() }
+    }
+}
+
+/// Parsed cron expression, day of month and day of week match either when both are restricted.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CronSchedule {
+    minutes: u64,
+    hours: u64,
+    days: u64,
+    months: u64,
+    weekdays: u64,
+    days_restricted: bool,
+    weekdays_restricted: bool,
+}
+
+impl CronSchedule {
+    /// Fields accept '*', 'N', 'N-M', lists and '/step', day of week 0 or 7 is Sunday.
+    pub fn parse(expression: &str) -> Result<Self, ScheduleError> {
+        let invalid = || ScheduleError::InvalidCron(expression.to_string());
+        let fields: Vec<&str> = expression.split_whitespace().collect();
+        let [minutes, hours, days, months, weekdays] = fields[..] else {
+            return Err(invalid());
+        };
+
+        let mut weekdays_mask = Self::parse_field(weekdays, 0, 7).ok_or_else(invalid)?;
+        if weekdays_mask & (1 << 7) != 0 {
+            weekdays_mask = (weekdays_mask | 1) & !(1 << 7);
+        }
+
+        Ok(Self {
+            minutes: Self::parse_field(minutes, 0, 59).ok_or_else(invalid)?,
+            hours: Self::parse_field(hours, 0, 23).ok_or_else(invalid)?,
+            days: Self::parse_field(days, 1, 31).ok_or_else(invalid)?,
+            months: Self::parse_field(months, 1, 12).ok_or_else(invalid)?,
+            weekdays: weekdays_mask,
+            days_restricted: !days.starts_with('*'),
+            weekdays_restricted: !weekdays.starts_with('*'),
+        })
+    }
+
+    fn parse_field(field: &str, min: u32, max: u32) -> Option<u64> {
+        let mut mask = 0;
+        for part in field.split(',') {
+            let (range, step) = match part.split_once('/') {
+                Some((range, step)) => (range, step.parse::<u32>().ok().filter(|step| *step > 0)?),
+                None => (part, 1),
+            };
+            let (start, end) = if range == "*" {
+                (min, max)
+            } else if let Some((start, end)) = range.split_once('-') {
+                (start.parse().ok()?, end.parse().ok()?)
+            } else {
+                // 'N/step' runs to end of range
+                let start = range.parse().ok()?;
+                (start, if step > 1 { max } else { start })
+            };
+            if start < min || end > max || start > end {
+                return None;
+            }
+            for value in (start..=end).step_by(step as usize) {
+                mask |= 1 << value;
+            }
+        }
+        Some(mask)
+    }
+
+    fn matches_date(&self, date: NaiveDate) -> bool {
+        if self.months & (1 << date.month()) == 0 {
+            return false;
+        }
+        let day_matches = self.days & (1 << date.day()) != 0;
+        let weekday_matches = self.weekdays & (1 << date.weekday().num_days_from_sunday()) != 0;
+        match (self.days_restricted, self.weekdays_restricted) {
+            (true, true) => day_matches || weekday_matches,
+            _ => day_matches && weekday_matches,
+        }
+    }
+
+    /// First occurrence later than 'time'.
+    pub fn next_after(&self, time: NaiveDateTime) -> Option<NaiveDateTime> {
+        let time = time.with_second(0)?.with_nanosecond(0)? + chrono::Duration::minutes(1);
+        let mut date = time.date();
+        let (mut from_hour, mut from_minute) = (time.hour(), time.minute());
+        for _ in 0..SEARCH_DAYS {
+            if self.matches_date(date) {
+                for hour in (from_hour..24).filter(|hour| self.hours & (1 << hour) != 0) {
+                    let first_minute = if hour == from_hour { from_minute } else { 0 };
+                    if let Some(minute) = (first_minute..60).find(|minute| self.minutes & (1 << minute) != 0) {
+                        return date.and_hms_opt(hour, minute, 0);
+                    }
+                }
+            }
+            date = date.succ_opt()?;
+            (from_hour, from_minute) = (0, 0);
+        }
+        None
+    }
+
+    /// Latest occurrence not later than 'time'.
+    pub fn previous(&self, time: NaiveDateTime) -> Option<NaiveDateTime> {
+        let mut date = time.date();
+        let (mut until_hour, mut until_minute) = (time.hour(), time.minute());
+        for _ in 0..SEARCH_DAYS {
+            if self.matches_date(date) {
+                for hour in (0..=until_hour).rev().filter(|hour| self.hours & (1 << hour) != 0) {
+                    let last_minute = if hour == until_hour { until_minute } else { 59 };
+                    if let Some(minute) = (0..=last_minute).rev().find(|minute| self.minutes & (1 << minute) != 0) {
+                        return date.and_hms_opt(hour, minute, 0);
+                    }
+                }
+            }
+            date = date.pred_opt()?;
+            (until_hour, until_minute) = (23, 59);
+        }
+        None
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ScheduledAction {
+    /// Local time, 'YYYY-MM-DD HH:MM'.
+    pub at: String,
+    pub rule: String,
+    pub action: &'static str,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct ScheduleStatus {
+    pub next: Option<ScheduledAction>,
+    pub last: Option<ScheduledAction>,
+    /// Unknown until a display action runs.
+    pub display_on: Option<bool>,
+}
+
+/// Rules with occurrence in ('from', 'to'], latest of each action kind in config order.
+fn due_rules(rules: &[(CronSchedule, ScheduleRule)], from: Option<NaiveDateTime>, to: NaiveDateTime) -> Vec<usize> {
+    let mut latest: Vec<(ActionKind, NaiveDateTime, usize)> = vec![];
+    for (index, (cron, rule)) in rules.iter().enumerate() {
+        let Some(time) = cron.previous(to) else {
+            continue;
+        };
+        if from.is_some_and(|from| time <= from) {
+            continue;
+        }
+        let kind = rule.action.kind();
+        match latest.iter_mut().find(|(latest_kind, ..)| *latest_kind == kind) {
+            Some(entry) if time >= entry.1 => *entry = (kind, time, index),
+            Some(_) => {},
+            None => latest.push((kind, time, index)),
+        }
+    }
+
+    let mut indexes: Vec<usize> = latest.into_iter().map(|(_, _, index)| index).collect();
+    indexes.sort_unstable();
+    indexes
+}
+
+/// Nearest occurrence after 'time', first rule wins ties.
+fn next_rule(rules: &[(CronSchedule, ScheduleRule)], time: NaiveDateTime) -> Option<(NaiveDateTime, usize)> {
+    rules.iter()
+        .enumerate()
+        .filter_map(|(index, (cron, _))| Some((cron.next_after(time)?, index)))
+        .min()
+}
+
+/// Runs time-of-day rules, playlists are passed to files manager.
+pub struct Scheduler {
+    config: ScheduleConfig,
+    video_player: Option<Arc<VideoPlayer>>,
+    status_tx: tokio::sync::watch::Sender<ScheduleStatus>,
+}
+
+pub struct SchedulerHandler {
+    task_handle: tokio::task::JoinHandle<()>,
+    cancel_token: CancellationToken,
+}
+
+impl FilesSourceHandler for SchedulerHandler {
+    type Error = ScheduleError;
+
+    async fn shutdown(self) -> Result<(), Self::Error> {
+        self.cancel_token.cancel();
+        self.task_handle.await.map_err(Self::Error::from)
+    }
+
+    async fn await_finish(self) -> Result<(), Self::Error> {
+        self.task_handle.await.map_err(Self::Error::from)
+    }
+}
+
+impl Scheduler {
+    pub fn new(config: ScheduleConfig) -> Self {
+        let (status_tx, _) = tokio::sync::watch::channel(ScheduleStatus::default());
+        Self { config, video_player: None, status_tx }
+    }
+
+    /// Enables 'stop' action.
+    pub fn with_video_player(mut self, video_player: Arc<VideoPlayer>) -> Self {
+        self.video_player = Some(video_player);
+        self
+    }
+
+    /// Next and last action.
+    pub fn subscribe(&self) -> tokio::sync::watch::Receiver<ScheduleStatus> {
+        self.status_tx.subscribe()
+    }
+
+    async fn execute(&self, action: &ScheduleAction, files_manager_sink: &tokio::sync::mpsc::Sender<FilesSourceType>) -> Result<(), ScheduleError> {
+        match action {
+            ScheduleAction::PlayPlaylist(playlist) => files_manager_sink.send(FilesSourceType::LibraryPlaylist(playlist.clone())).await
+                .map_err(|_| ScheduleError::Stopped),
+            ScheduleAction::Stop => {
+                match &self.video_player {
+                    Some(video_player) => video_player.stop().await,
+                    None => tracing::warn!("Video player not available, schedule stop ignored"),
+                }
+                Ok(())
+            },
+            ScheduleAction::DisplayOn => self.config.display.set_power(true).await,
+            ScheduleAction::DisplayOff => self.config.display.set_power(false).await,
+        }
+    }
+}
+
+impl FilesSource for Scheduler {
+    type Handler = SchedulerHandler;
+    type Error = ScheduleError;
+
+    async fn start(self, sink: Arc<dyn FilesManagerSink>) -> Result<Self::Handler, Self::Error> {
+        let rules: Vec<(CronSchedule, ScheduleRule)> = self.config.rules.iter()
+            .filter_map(|rule| match CronSchedule::parse(&rule.cron) {
+                Ok(cron) => Some((cron, rule.clone())),
+                Err(e) => {
+                    tracing::warn!("Schedule rule '{}' skipped, reason = {e}", rule.label());
+                    None
+                },
+            })
+            .collect();
+        tracing::info!("Scheduler started with {} rules", rules.len());
+
+        let files_manager_sink = sink.get_tx();
+        let cancel_token = CancellationToken::new();
+        let task_cancel_token = cancel_token.clone();
+
+        let task_handle = tokio::spawn(async move {
+            let now = chrono::Local::now().naive_local();
+            let mut last_check = (!self.config.catch_up).then_some(now);
+            loop {
+                // Local time runs backwards on DST change, repeated hour is not run twice
+                let now = chrono::Local::now().naive_local();
+                for index in due_rules(&rules, last_check, now) {
+                    let rule = &rules[index].1;
+                    tracing::info!("Schedule rule '{}': {}", rule.label(), rule.action.name());
+                    if let Err(e) = self.execute(&rule.action, &files_manager_sink).await {
+                        tracing::warn!("Schedule rule '{}' failed, reason = {e}", rule.label());
+                        if matches!(e, ScheduleError::Stopped) {
+                            return;
+                        }
+                        continue;
+                    }
+                    self.status_tx.send_modify(|status| {
+                        status.last = Some(ScheduledAction { at: now.format(TIME_FORMAT).to_string(), rule: rule.label(), action: rule.action.name() });
+                        match rule.action {
+                            ScheduleAction::DisplayOn => status.display_on = Some(true),
+                            ScheduleAction::DisplayOff => status.display_on = Some(false),
+                            _ => {},
+                        }
+                    });
+                }
+                last_check = Some(now);
+
+                let next = next_rule(&rules, now);
+                let next_action = next.map(|(time, index)| ScheduledAction {
+                    at: time.format(TIME_FORMAT).to_string(),
+                    rule: rules[index].1.label(),
+                    action: rules[index].1.action.name(),
+                });
+                self.status_tx.send_if_modified(|status| {
+                    let modified = status.next != next_action;
+                    status.next = next_action;
+                    modified
+                });
+
+                let wait = next
+                    .and_then(|(time, _)| (time - now).to_std().ok())
+                    .map_or(MAX_WAIT, |wait| wait.min(MAX_WAIT));
+                tokio::select! {
+                    _ = task_cancel_token.cancelled() => break,
+                    _ = tokio::time::sleep(wait) => {},
+                }
+            }
+        });
+
+        Ok(Self::Handler { task_handle, cancel_token })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn time(text: &str) -> NaiveDateTime {
+        NaiveDateTime::parse_from_str(text, "%Y-%m-%d %H:%M").unwrap()
+    }
+
+    #[test]
+    fn test_cron_occurrences() {
+        // 2026-10-16 is Friday
+        let weekdays = CronSchedule::parse("30 7 * * 1-5").unwrap();
+        assert_eq!(weekdays.next_after(time("2026-10-16 07:29")), Some(time("2026-10-16 07:30")));
+        assert_eq!(weekdays.next_after(time("2026-10-16 07:30")), Some(time("2026-10-19 07:30")));
+        assert_eq!(weekdays.previous(time("2026-10-18 12:00")), Some(time("2026-10-16 07:30")));
+        assert_eq!(weekdays.previous(time("2026-10-16 07:30")), Some(time("2026-10-16 07:30")));
+
+        let steps = CronSchedule::parse("*/20 22-23,0 * * 0,7").unwrap();
+        assert_eq!(steps.next_after(time("2026-10-18 22:45")), Some(time("2026-10-18 23:00")));
+        assert_eq!(steps.next_after(time("2026-10-18 23:40")), Some(time("2026-10-18 00:00") + chrono::Duration::days(7)));
+
+        // Either restricted day field matches
+        let first_or_monday = CronSchedule::parse("0 9 1 * 1").unwrap();
+        assert_eq!(first_or_monday.next_after(time("2026-10-27 10:00")), Some(time("2026-11-01 09:00")));
+        assert_eq!(first_or_monday.next_after(time("2026-11-01 10:00")), Some(time("2026-11-02 09:00")));
+
+        for invalid in ["", "0 8 * *", "60 8 * * *", "0 8 0 * *", "0 8 * * 8", "*/0 8 * * *", "5-1 8 * * *", "a 8 * * *"] {
+            assert!(CronSchedule::parse(invalid).is_err(), "{invalid}");
+        }
+
+        let config: ScheduleConfig = serde_json::from_str(r#"{"rules": [
+            {"name": "evening", "cron": "0 18 * * *", "action": "play_playlist", "items": [{"file": "evening.mp4"}]},
+            {"cron": "0 22 * * *", "action": "display_off"}
+        ], "display": {"backend": "wlr_randr"}}"#).unwrap();
+        assert!(matches!(&config.rules[0].action, ScheduleAction::PlayPlaylist(playlist) if playlist.items[0].file == "evening.mp4"));
+        assert_eq!(config.rules[1].label(), "0 22 * * *");
+        assert_eq!(config.display.command(false), ["wlr-randr", "--output", "HDMI-A-1", "--off"]);
+
+        // Latest of each kind is due after gap, e.g. at startup
+        let rule = |cron: &str, action: ScheduleAction| (CronSchedule::parse(cron).unwrap(), ScheduleRule { name: None, cron: cron.to_string(), action });
+        let rules = vec![
+            rule("0 8 * * *", ScheduleAction::PlayPlaylist(PlaylistCfg { items: vec![] })),
+            rule("0 18 * * *", ScheduleAction::PlayPlaylist(PlaylistCfg { items: vec![] })),
+            rule("0 22 * * *", ScheduleAction::Stop),
+            rule("0 22 * * *", ScheduleAction::DisplayOff),
+            rule("0 7 * * *", ScheduleAction::DisplayOn),
+        ];
+        assert_eq!(due_rules(&rules, None, time("2026-10-18 19:00")), vec![1, 4]);
+        assert_eq!(due_rules(&rules, None, time("2026-10-18 23:00")), vec![2, 3]);
+        assert_eq!(due_rules(&rules, Some(time("2026-10-18 07:59")), time("2026-10-18 08:00")), vec![0]);
+        assert!(due_rules(&rules, Some(time("2026-10-18 08:00")), time("2026-10-18 08:01")).is_empty());
+        assert_eq!(next_rule(&rules, time("2026-10-18 19:00")), Some((time("2026-10-18 22:00"), 2)));
+    }
+}
@@ -0,0 +1,220 @@
+use serde::Serialize;
+
+#[derive(Debug, thiserror::Error)]
+pub enum SplashError {
+    #[error("PngEncodingError reason = '{0}'")]
+    PngEncodingError(#[from] png::EncodingError),
+
+    #[error("QrCodeError reason = '{0}'")]
+    QrCodeError(#[from] qrcode::types::QrError),
+}
+
+/// Content of idle screen.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct SplashInfo {
+    pub device_name: String,
+    pub ip_address: Option<String>,
+    pub web_url: Option<String>,
+}
+
+impl SplashInfo {
+    fn lines(&self) -> Vec<String> {
+        let mut lines = vec![self.device_name.clone()];
+        match &self.ip_address {
+            Some(ip_address) => lines.push(format!("IP {ip_address}")),
+            None => lines.push("NO NETWORK".to_string()),
+        }
+        if let Some(web_url) = &self.web_url {
+            lines.push(web_url.clone());
+        }
+        lines.push("INSERT DRIVE OR UPLOAD VIDEO".to_string());
+        lines
+    }
+}
+
+const GLYPH_WIDTH: usize = 5;
+const GLYPH_HEIGHT: usize = 7;
+
+/// Cell includes 1 pixel spacing.
+const CELL_WIDTH: usize = GLYPH_WIDTH + 1;
+const CELL_HEIGHT: usize = GLYPH_HEIGHT + 3;
+
+const QR_QUIET_ZONE: usize = 4;
+
+const BACKGROUND: [u8; 3] = [0, 0, 0];
+const FOREGROUND: [u8; 3] = [255, 255, 255];
+
+/// Minimal font, lowercase is drawn as uppercase.
+fn glyph(c: char) -> [&'static str; GLYPH_HEIGHT] {
+    match c.to_ascii_uppercase() {
+        'A' => [" ### ", "#   #", "#   #", "#####", "#   #", "#   #", "#   #"],
+        'B' => ["#### ", "#   #", "#   #", "#### ", "#   #", "#   #", "#### "],
+        'C' => [" ### ", "#   #", "#    ", "#    ", "#    ", "#   #", " ### "],
+        'D' => ["#### ", "#   #", "#   #", "#   #", "#   #", "#   #", "#### "],
+        'E' => ["#####", "#    ", "#    ", "#### ", "#    ", "#    ", "#####"],
+        'F' => ["#####", "#    ", "#    ", "#### ", "#    ", "#    ", "#    "],
+        'G' => [" ### ", "#   #", "#    ", "# ###", "#   #", "#   #", " ####"],
+        'H' => ["#   #", "#   #", "#   #", "#####", "#   #", "#   #", "#   #"],
+        'I' => [" ### ", "  #  ", "  #  ", "  #  ", "  #  ", "  #  ", " ### "],
+        'J' => ["  ###", "   # ", "   # ", "   # ", "   # ", "#  # ", " ##  "],
+        'K' => ["#   #", "#  # ", "# #  ", "##   ", "# #  ", "#  # ", "#   #"],
+        'L' => ["#    ", "#    ", "#    ", "#    ", "#    ", "#    ", "#####"],
+        'M' => ["#   #", "## ##", "# # #", "# # #", "#   #", "#   #", "#   #"],
+        'N' => ["#   #", "#   #", "##  #", "# # #", "#  ##", "#   #", "#   #"],
+        'O' => [" ### ", "#   #", "#   #", "#   #", "#   #", "#   #", " ### "],
+        'P' => ["#### ", "#   #", "#   #", "#### ", "#    ", "#    ", "#    "],
+        'Q' => [" ### ", "#   #", "#   #", "#   #", "# # #", "#  # ", " ## #"],
+        'R' => ["#### ", "#   #", "#   #", "#### ", "# #  ", "#  # ", "#   #"],
+        'S' => [" ####", "#    ", "#    ", " ### ", "    #", "    #", "#### "],
+        'T' => ["#####", "  #  ", "  #  ", "  #  ", "  #  ", "  #  ", "  #  "],
+        'U' => ["#   #", "#   #", "#   #", "#   #", "#   #", "#   #", " ### "],
+        'V' => ["#   #", "#   #", "#   #", "#   #", "#   #", " # # ", "  #  "],
+        'W' => ["#   #", "#   #", "#   #", "# # #", "# # #", "# # #", " # # "],
+        'X' => ["#   #", "#   #", " # # ", "  #  ", " # # ", "#   #", "#   #"],
+        'Y' => ["#   #", "#   #", " # # ", "  #  ", "  #  ", "  #  ", "  #  "],
+        'Z' => ["#####", "    #", "   # ", "  #  ", " #   ", "#    ", "#####"],
+        '0' => [" ### ", "#   #", "#  ##", "# # #", "##  #", "#   #", " ### "],
+        '1' => ["  #  ", " ##  ", "  #  ", "  #  ", "  #  ", "  #  ", " ### "],
+        '2' => [" ### ", "#   #", "    #", "   # ", "  #  ", " #   ", "#####"],
+        '3' => ["#####", "   # ", "  #  ", "   # ", "    #", "#   #", " ### "],
+        '4' => ["   # ", "  ## ", " # # ", "#  # ", "#####", "   # ", "   # "],
+        '5' => ["#####", "#    ", "#### ", "    #", "    #", "#   #", " ### "],
+        '6' => ["  ## ", " #   ", "#    ", "#### ", "#   #", "#   #", " ### "],
+        '7' => ["#####", "    #", "   # ", "  #  ", " #   ", " #   ", " #   "],
+        '8' => [" ### ", "#   #", "#   #", " ### ", "#   #", "#   #", " ### "],
+        '9' => [" ### ", "#   #", "#   #", " ####", "    #", "   # ", " ##  "],
+        ' ' => ["     ", "     ", "     ", "     ", "     ", "     ", "     "],
+        '.' => ["     ", "     ", "     ", "     ", "     ", " ##  ", " ##  "],
+        ',' => ["     ", "     ", "     ", "     ", " ##  ", "  #  ", " #   "],
+        ':' => ["     ", " ##  ", " ##  ", "     ", " ##  ", " ##  ", "     "],
+        '/' => ["     ", "    #", "   # ", "  #  ", " #   ", "#    ", "     "],
+        '-' => ["     ", "     ", "     ", "#####", "     ", "     ", "     "],
+        '_' => ["     ", "     ", "     ", "     ", "     ", "     ", "#####"],
+        '(' => ["   # ", "  #  ", " #   ", " #   ", " #   ", "  #  ", "   # "],
+        ')' => [" #   ", "  #  ", "   # ", "   # ", "   # ", "  #  ", " #   "],
+        '!' => ["  #  ", "  #  ", "  #  ", "  #  ", "  #  ", "     ", "  #  "],
+        _ => [" ### ", "#   #", "    #", "   # ", "  #  ", "     ", "  #  "],
+    }
+}
+
+struct Canvas {
+    width: usize,
+    height: usize,
+    pixels: Vec<u8>,
+}
+
+impl Canvas {
+    fn new(width: usize, height: usize) -> Self {
+        let pixels = BACKGROUND.repeat(width * height);
+        Self { width, height, pixels }
+    }
+
+    fn fill_rect(&mut self, x: usize, y: usize, width: usize, height: usize, color: [u8; 3]) {
+        for row in y..(y + height).min(self.height) {
+            for col in x..(x + width).min(self.width) {
+                let offset = (row * self.width + col) * 3;
+                self.pixels[offset..offset + 3].copy_from_slice(&color);
+            }
+        }
+    }
+
+    /// Draws text clipped to canvas, returns drawn text width.
+    fn draw_text(&mut self, text: &str, x: usize, y: usize, scale: usize) -> usize {
+        for (index, c) in text.chars().enumerate() {
+            let glyph_x = x + index * CELL_WIDTH * scale;
+            for (row, line) in glyph(c).iter().enumerate() {
+                for (col, pixel) in line.chars().enumerate() {
+                    if pixel == '#' {
+                        self.fill_rect(glyph_x + col * scale, y + row * scale, scale, scale, FOREGROUND);
+                    }
+                }
+            }
+        }
+        text.chars().count() * CELL_WIDTH * scale
+    }
+
+    fn draw_qr_code(&mut self, data: &str, x: usize, y: usize, max_size: usize) -> Result<(), SplashError> {
+        let code = qrcode::QrCode::new(data.as_bytes())?;
+        let modules = code.width();
+        let module_size = max_size / (modules + 2 * QR_QUIET_ZONE);
+        if module_size == 0 {
+            tracing::warn!("Splash too small for QR code");
+            return Ok(());
+        }
+
+        let size = module_size * (modules + 2 * QR_QUIET_ZONE);
+        self.fill_rect(x, y, size, size, FOREGROUND);
+        for (index, color) in code.to_colors().into_iter().enumerate() {
+            if color == qrcode::Color::Dark {
+                let module_x = x + (QR_QUIET_ZONE + index % modules) * module_size;
+                let module_y = y + (QR_QUIET_ZONE + index / modules) * module_size;
+                self.fill_rect(module_x, module_y, module_size, module_size, BACKGROUND);
+            }
+        }
+        Ok(())
+    }
+
+    fn encode_png(&self) -> Result<Vec<u8>, SplashError> {
+        let mut png_data = Vec::new();
+        let mut encoder = png::Encoder::new(&mut png_data, self.width as u32, self.height as u32);
+        encoder.set_color(png::ColorType::Rgb);
+        encoder.set_depth(png::BitDepth::Eight);
+        let mut writer = encoder.write_header()?;
+        writer.write_image_data(&self.pixels)?;
+        writer.finish()?;
+        Ok(png_data)
+    }
+}
+
+/// Renders text on the left and QR code of web URL on the right.
+pub fn render_splash_png(info: &SplashInfo, width: usize, height: usize) -> Result<Vec<u8>, SplashError> {
+    let mut canvas = Canvas::new(width, height);
+    let margin = height / 12;
+
+    let qr_size = match &info.web_url {
+        Some(_) => (height - 2 * margin).min(width / 2),
+        None => 0,
+    };
+
+    let lines = info.lines();
+    let text_width = width.saturating_sub(qr_size + 3 * margin);
+    let longest_line = lines.iter().map(|line| line.chars().count()).max().unwrap_or(1).max(1);
+    let scale = (text_width / (longest_line * CELL_WIDTH))
+        .min((height - 2 * margin) / (lines.len() * CELL_HEIGHT))
+        .max(1);
+
+    let text_height = lines.len() * CELL_HEIGHT * scale;
+    let mut y = height.saturating_sub(text_height) / 2;
+    for line in &lines {
+        canvas.draw_text(line, margin, y, scale);
+        y += CELL_HEIGHT * scale;
+    }
+
+    if let Some(web_url) = &info.web_url {
+        let qr_x = width - margin - qr_size;
+        let qr_y = (height - qr_size) / 2;
+        canvas.draw_qr_code(web_url, qr_x, qr_y, qr_size)?;
+    }
+
+    canvas.encode_png()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_render_splash_png() {
+        let info = SplashInfo {
+            device_name: "cosplay-pi".to_string(),
+            ip_address: Some("192.168.1.23".to_string()),
+            web_url: Some("http://192.168.1.23:8080/".to_string()),
+        };
+        let png_data = render_splash_png(&info, 960, 540).unwrap();
+        assert_eq!(&png_data[..8], b"\x89PNG\r\n\x1a\n");
+
+        let decoder = png::Decoder::new(png_data.as_slice());
+        let reader = decoder.read_info().unwrap();
+        assert_eq!((reader.info().width, reader.info().height), (960, 540));
+    }
+}
@@ -0,0 +1,530 @@
+use std::{collections::VecDeque, net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr}, path::{Path, PathBuf}, sync::Arc, time::{Duration, Instant}};
+
+use serde::{Deserialize, Serialize};
+use tokio_util::sync::CancellationToken;
+
+const MESSAGE_MAX_LEN: usize = 1024;
+
+/// Recent messages searched for lowest network delay.
+const DELAY_WINDOW: usize = 32;
+
+/// Position reported right after seek may be stale.
+const SEEK_SETTLE: Duration = Duration::from_secs(1);
+
+/// Rates closer than this are not changed, VLC rate change can cause a stutter.
+const RATE_EPSILON: f32 = 0.002;
+
+/// Follower status with changed drift only is published this often.
+const STATUS_INTERVAL: Duration = Duration::from_secs(1);
+
+#[derive(Debug, thiserror::Error)]
+pub enum SyncError {
+    #[error("StdIoError reason = '{0}'")]
+    StdIoError(#[from] std::io::Error),
+
+    #[error("InvalidAddress address = '{0}'")]
+    InvalidAddress(String),
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SyncRole {
+    #[default]
+    Off,
+    /// Broadcasts position of its playback.
+    Leader,
+    /// Follows position broadcast by leader.
+    Follower,
+}
+
+/// Playback of several devices kept in sync over LAN.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SyncConfig {
+    #[serde(default)]
+    pub role: SyncRole,
+
+    /// Messages of other groups on same LAN are ignored.
+    #[serde(default = "SyncConfig::default_group")]
+    pub group: String,
+
+    /// Leader sends to this address, followers listen on its port.
+    #[serde(default = "SyncConfig::default_address")]
+    pub address: String,
+
+    /// Leader message period.
+    #[serde(default = "SyncConfig::default_interval_ms")]
+    pub interval_ms: u32,
+
+    /// Target drift, smaller drift is not corrected.
+    #[serde(default = "SyncConfig::default_max_drift_ms")]
+    pub max_drift_ms: u32,
+
+    /// Larger drift is corrected by seek, smaller one by playback rate.
+    #[serde(default = "SyncConfig::default_seek_drift_ms")]
+    pub seek_drift_ms: u32,
+
+    /// Largest playback rate change, e.g. 0.05 means 95% - 105% speed.
+    #[serde(default = "SyncConfig::default_max_rate_adjust")]
+    pub max_rate_adjust: f32,
+
+    /// Drift is corrected by rate within about this time, unless rate change is limited.
+    #[serde(default = "SyncConfig::default_correction_window_ms")]
+    pub correction_window_ms: u32,
+}
+
+impl SyncConfig {
+    fn default_group() -> String {
+        "default".to_string()
+    }
+
+    fn default_address() -> String {
+        "255.255.255.255:5405".to_string()
+    }
+
+    fn default_interval_ms() -> u32 {
+        250
+    }
+
+    fn default_max_drift_ms() -> u32 {
+        40
+    }
+
+    fn default_seek_drift_ms() -> u32 {
+        1000
+    }
+
+    fn default_max_rate_adjust() -> f32 {
+        0.05
+    }
+
+    fn default_correction_window_ms() -> u32 {
+        2000
+    }
+
+    fn interval(&self) -> Duration {
+        Duration::from_millis(self.interval_ms.max(1) as u64)
+    }
+}
+
+impl Default for SyncConfig {
+    fn default() -> Self {
+        Self {
+            role: SyncRole::default(),
+            group: Self::default_group(),
+            address: Self::default_address(),
+            interval_ms: Self::default_interval_ms(),
+            max_drift_ms: Self::default_max_drift_ms(),
+            seek_drift_ms: Self::default_seek_drift_ms(),
+            max_rate_adjust: Self::default_max_rate_adjust(),
+            correction_window_ms: Self::default_correction_window_ms(),
+        }
+    }
+}
+
+/// Current item of player.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PlaybackPosition {
+    pub path: PathBuf,
+    pub time_ms: i64,
+    pub length_ms: Option<i64>,
+    pub playing: bool,
+}
+
+/// Player kept in sync, e.g. 'VideoPlayer'.
+pub trait SyncPlayer: Send + Sync {
+    /// None when nothing is played.
+    fn position(&self) -> impl std::future::Future<Output = Option<PlaybackPosition>> + Send;
+
+    fn set_time(&self, time_ms: i64);
+
+    fn set_rate(&self, rate: f32);
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct SyncStatus {
+    pub role: SyncRole,
+    /// Follower: address of last leader heard.
+    pub leader: Option<SocketAddr>,
+    /// Follower: positive when behind leader.
+    pub drift_ms: Option<i64>,
+    pub rate: f32,
+    pub seeks: u32,
+}
+
+/// Sent by leader each 'interval_ms'.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+struct SyncMessage {
+    group: String,
+    /// Leader clock, time since leader started.
+    clock_ms: u64,
+    /// File name, library paths may differ between devices.
+    item: String,
+    time_ms: i64,
+    length_ms: Option<i64>,
+    playing: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Correction {
+    Seek,
+    Rate(f32),
+}
+
+/// Network delay of leader messages, estimated from leader clock.
+#[derive(Debug, Default)]
+struct LeaderClock {
+    started: Option<Instant>,
+    last_clock_ms: Option<u64>,
+    offsets: VecDeque<i64>,
+}
+
+impl LeaderClock {
+    /// Delay of message compared to fastest recent one.
+    fn delay(&mut self, clock_ms: u64, received: Instant) -> Duration {
+        let started = *self.started.get_or_insert(received);
+        // Leader restarted
+        if self.last_clock_ms.is_some_and(|last_clock_ms| clock_ms < last_clock_ms) {
+            self.offsets.clear();
+        }
+        self.last_clock_ms = Some(clock_ms);
+
+        let offset = received.duration_since(started).as_millis() as i64 - clock_ms as i64;
+        if self.offsets.len() >= DELAY_WINDOW {
+            self.offsets.pop_front();
+        }
+        self.offsets.push_back(offset);
+        let min_offset = self.offsets.iter().copied().min().unwrap_or(offset);
+        Duration::from_millis((offset - min_offset) as u64)
+    }
+}
+
+pub struct PlaybackSyncHandler {
+    task_handle: tokio::task::JoinHandle<()>,
+    cancel_token: CancellationToken,
+    local_addr: SocketAddr,
+    status_rx: tokio::sync::watch::Receiver<SyncStatus>,
+}
+
+impl PlaybackSyncHandler {
+    pub fn local_addr(&self) -> SocketAddr {
+        self.local_addr
+    }
+
+    pub fn status(&self) -> SyncStatus {
+        self.status_rx.borrow().clone()
+    }
+
+    /// Changes of drift alone are sent at most each second.
+    pub fn subscribe(&self) -> tokio::sync::watch::Receiver<SyncStatus> {
+        self.status_rx.clone()
+    }
+
+    pub async fn shutdown(self) -> Result<(), tokio::task::JoinError> {
+        self.cancel_token.cancel();
+        self.task_handle.await
+    }
+}
+
+pub struct PlaybackSync<P> {
+    config: SyncConfig,
+    player: Arc<P>,
+}
+
+impl<P: SyncPlayer + 'static> PlaybackSync<P> {
+    pub fn new(config: SyncConfig, player: Arc<P>) -> Self {
+        Self { config, player }
+    }
+
+    /// Starts role set in config, None if sync is off.
+    pub async fn start(self) -> Result<Option<PlaybackSyncHandler>, SyncError> {
+        let address: SocketAddr = self.config.address.parse()
+            .map_err(|_| SyncError::InvalidAddress(self.config.address.clone()))?;
+        match self.config.role {
+            SyncRole::Off => Ok(None),
+            SyncRole::Leader => self.start_leader(address).await.map(Some),
+            SyncRole::Follower => {
+                let listen_address = SocketAddr::new(Self::unspecified(address), address.port());
+                self.start_follower(listen_address).await.map(Some)
+            },
+        }
+    }
+
+    /// Sends position to 'target', e.g. broadcast address.
+    pub async fn start_leader(self, target: SocketAddr) -> Result<PlaybackSyncHandler, SyncError> {
+        let socket = tokio::net::UdpSocket::bind(SocketAddr::new(Self::unspecified(target), 0)).await?;
+        socket.set_broadcast(true)?;
+        let local_addr = socket.local_addr()?;
+        // Leader status does not change
+        let (_, status_rx) = tokio::sync::watch::channel(self.initial_status());
+        let cancel_token = CancellationToken::new();
+        let task_cancel_token = cancel_token.clone();
+
+        let task_handle = tokio::spawn(async move {
+            tracing::info!("Playback sync leader sending to {target}, group {:?}", self.config.group);
+            let started = Instant::now();
+            let mut interval = tokio::time::interval(self.config.interval());
+            interval.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Skip);
+            loop {
+                tokio::select! {
+                    _ = task_cancel_token.cancelled() => break,
+                    _ = interval.tick() => {},
+                }
+
+                let Some(position) = self.player.position().await else {
+                    continue;
+                };
+                let message = SyncMessage {
+                    group: self.config.group.clone(),
+                    clock_ms: started.elapsed().as_millis() as u64,
+                    item: Self::item_name(&position.path),
+                    time_ms: position.time_ms,
+                    length_ms: position.length_ms,
+                    playing: position.playing,
+                };
+                let Ok(data) = serde_json::to_vec(&message) else {
+                    continue;
+                };
+                // Network may be down for a while
+                if let Err(e) = socket.send_to(&data, target).await {
+                    tracing::debug!("Playback sync could not send to {target}, reason = {e}");
+                }
+            }
+            tracing::info!("Playback sync leader shutting down.");
+        });
+
+        Ok(PlaybackSyncHandler { task_handle, cancel_token, local_addr, status_rx })
+    }
+
+    /// Receives leader position on 'listen_address' and corrects own playback.
+    pub async fn start_follower(self, listen_address: SocketAddr) -> Result<PlaybackSyncHandler, SyncError> {
+        let socket = tokio::net::UdpSocket::bind(listen_address).await?;
+        let local_addr = socket.local_addr()?;
+        let (status_tx, status_rx) = tokio::sync::watch::channel(self.initial_status());
+        let cancel_token = CancellationToken::new();
+        let task_cancel_token = cancel_token.clone();
+
+        let task_handle = tokio::spawn(async move {
+            tracing::info!("Playback sync follower listening on {local_addr}, group {:?}", self.config.group);
+            let mut buffer = [0u8; MESSAGE_MAX_LEN];
+            let mut leader_clock = LeaderClock::default();
+            let mut rate = 1.0;
+            let mut last_seek: Option<Instant> = None;
+            let mut last_status_sent = Instant::now();
+            loop {
+                let (len, leader) = tokio::select! {
+                    _ = task_cancel_token.cancelled() => break,
+                    result = socket.recv_from(&mut buffer) => match result {
+                        Ok(received) => received,
+                        Err(e) => {
+                            tracing::debug!("Playback sync receive failed, reason = {e}");
+                            continue;
+                        },
+                    },
+                };
+                let received = Instant::now();
+                let message: SyncMessage = match serde_json::from_slice(&buffer[..len]) {
+                    Ok(message) => message,
+                    Err(e) => {
+                        tracing::debug!("Playback sync ignored message from {leader}, reason = {e}");
+                        continue;
+                    },
+                };
+                if message.group != self.config.group {
+                    continue;
+                }
+                let delay = leader_clock.delay(message.clock_ms, received);
+
+                if !message.playing || last_seek.is_some_and(|last_seek| last_seek.elapsed() < SEEK_SETTLE) {
+                    continue;
+                }
+                let Some(position) = self.player.position().await else {
+                    continue;
+                };
+                if !position.playing || Self::item_name(&position.path) != message.item {
+                    tracing::debug!("Playback sync: leader plays {:?}, not current item {:?}", message.item, position.path);
+                    continue;
+                }
+
+                let length_ms = position.length_ms.or(message.length_ms);
+                let leader_time_ms = message.time_ms + (delay + received.elapsed()).as_millis() as i64;
+                let drift_ms = Self::drift_ms(leader_time_ms, position.time_ms, length_ms);
+                match Self::correction(drift_ms, &self.config) {
+                    Correction::Seek => {
+                        let seek_time_ms = length_ms.filter(|length_ms| *length_ms > 0)
+                            .map_or(leader_time_ms, |length_ms| leader_time_ms.rem_euclid(length_ms));
+                        tracing::info!("Playback sync: drift {drift_ms} ms, seeking to {seek_time_ms} ms");
+                        self.player.set_time(seek_time_ms);
+                        last_seek = Some(Instant::now());
+                        if rate != 1.0 {
+                            rate = 1.0;
+                            self.player.set_rate(rate);
+                        }
+                        status_tx.send_modify(|status| status.seeks += 1);
+                    },
+                    Correction::Rate(new_rate) => {
+                        if (new_rate - rate).abs() > RATE_EPSILON || (new_rate == 1.0 && rate != 1.0) {
+                            tracing::debug!("Playback sync: drift {drift_ms} ms, rate {new_rate:.3}");
+                            rate = new_rate;
+                            self.player.set_rate(rate);
+                        }
+                    },
+                }
+                let status_sent = status_tx.send_if_modified(|status| {
+                    let modified = status.leader != Some(leader)
+                        || status.rate != rate
+                        || last_status_sent.elapsed() >= STATUS_INTERVAL;
+                    status.leader = Some(leader);
+                    status.drift_ms = Some(drift_ms);
+                    status.rate = rate;
+                    modified
+                });
+                if status_sent {
+                    last_status_sent = Instant::now();
+                }
+            }
+            if rate != 1.0 {
+                self.player.set_rate(1.0);
+            }
+            tracing::info!("Playback sync follower shutting down.");
+        });
+
+        Ok(PlaybackSyncHandler { task_handle, cancel_token, local_addr, status_rx })
+    }
+
+    fn initial_status(&self) -> SyncStatus {
+        SyncStatus { role: self.config.role, leader: None, drift_ms: None, rate: 1.0, seeks: 0 }
+    }
+
+    fn unspecified(address: SocketAddr) -> IpAddr {
+        match address {
+            SocketAddr::V4(_) => IpAddr::V4(Ipv4Addr::UNSPECIFIED),
+            SocketAddr::V6(_) => IpAddr::V6(Ipv6Addr::UNSPECIFIED),
+        }
+    }
+
+    fn item_name(path: &Path) -> String {
+        path.file_name().unwrap_or(path.as_os_str()).to_string_lossy().into_owned()
+    }
+
+    /// Positive when follower is behind, looped item takes shorter way around.
+    fn drift_ms(leader_time_ms: i64, follower_time_ms: i64, length_ms: Option<i64>) -> i64 {
+        let drift_ms = leader_time_ms - follower_time_ms;
+        match length_ms.filter(|length_ms| *length_ms > 0) {
+            Some(length_ms) => (drift_ms + length_ms / 2).rem_euclid(length_ms) - length_ms / 2,
+            None => drift_ms,
+        }
+    }
+
+    fn correction(drift_ms: i64, config: &SyncConfig) -> Correction {
+        let abs_drift_ms = drift_ms.unsigned_abs();
+        if abs_drift_ms > config.seek_drift_ms as u64 {
+            return Correction::Seek;
+        }
+        if abs_drift_ms <= config.max_drift_ms as u64 {
+            return Correction::Rate(1.0);
+        }
+        let max_rate_adjust = config.max_rate_adjust.clamp(0.0, 0.5);
+        let rate_adjust = drift_ms as f32 / config.correction_window_ms.max(1) as f32;
+        Correction::Rate(1.0 + rate_adjust.clamp(-max_rate_adjust, max_rate_adjust))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Mutex;
+
+    use super::*;
+
+    /// Clock running at given rate, stands in for VLC.
+    struct TestPlayer {
+        clock: Mutex<(Instant, i64, f32)>,
+        max_rate: Mutex<f32>,
+    }
+
+    impl TestPlayer {
+        fn new(time_ms: i64) -> Arc<Self> {
+            Arc::new(Self { clock: Mutex::new((Instant::now(), time_ms, 1.0)), max_rate: Mutex::new(1.0) })
+        }
+
+        fn time_ms(&self) -> i64 {
+            let (base, base_time_ms, rate) = *self.clock.lock().unwrap();
+            base_time_ms + (base.elapsed().as_millis() as f32 * rate) as i64
+        }
+    }
+
+    impl SyncPlayer for TestPlayer {
+        async fn position(&self) -> Option<PlaybackPosition> {
+            Some(PlaybackPosition { path: PathBuf::from("/tmp/loop.mp4"), time_ms: self.time_ms(), length_ms: None, playing: true })
+        }
+
+        fn set_time(&self, time_ms: i64) {
+            let mut clock = self.clock.lock().unwrap();
+            *clock = (Instant::now(), time_ms, clock.2);
+        }
+
+        fn set_rate(&self, rate: f32) {
+            let time_ms = self.time_ms();
+            *self.clock.lock().unwrap() = (Instant::now(), time_ms, rate);
+            let mut max_rate = self.max_rate.lock().unwrap();
+            *max_rate = max_rate.max(rate);
+        }
+    }
+
+    async fn wait_for_drift(leader: &TestPlayer, follower: &TestPlayer, max_drift_ms: i64) -> i64 {
+        let deadline = Instant::now() + Duration::from_secs(5);
+        loop {
+            tokio::time::sleep(Duration::from_millis(20)).await;
+            let drift_ms = leader.time_ms() - follower.time_ms();
+            if drift_ms.abs() <= max_drift_ms || Instant::now() > deadline {
+                return drift_ms;
+            }
+        }
+    }
+
+    #[test]
+    fn test_drift_correction() {
+        let config = SyncConfig::default();
+        assert_eq!(PlaybackSync::<TestPlayer>::drift_ms(1000, 1200, None), -200);
+        // Leader already looped
+        assert_eq!(PlaybackSync::<TestPlayer>::drift_ms(100, 9900, Some(10000)), 200);
+        assert_eq!(PlaybackSync::<TestPlayer>::correction(20, &config), Correction::Rate(1.0));
+        assert_eq!(PlaybackSync::<TestPlayer>::correction(-1500, &config), Correction::Seek);
+        assert_eq!(PlaybackSync::<TestPlayer>::correction(500, &config), Correction::Rate(1.05));
+        assert_eq!(PlaybackSync::<TestPlayer>::correction(-50, &config), Correction::Rate(0.975));
+    }
+
+    #[tokio::test]
+    async fn test_two_instances_on_localhost() {
+        let config = SyncConfig {
+            interval_ms: 20,
+            seek_drift_ms: 300,
+            max_rate_adjust: 0.5,
+            correction_window_ms: 200,
+            ..Default::default()
+        };
+        let max_drift_ms = config.max_drift_ms as i64;
+        let leader_player = TestPlayer::new(10_000);
+        let follower_player = TestPlayer::new(9_000);
+
+        let follower = PlaybackSync::new(config.clone(), follower_player.clone())
+            .start_follower("127.0.0.1:0".parse().unwrap()).await.unwrap();
+        let leader = PlaybackSync::new(config, leader_player.clone())
+            .start_leader(follower.local_addr()).await.unwrap();
+
+        // Large drift is corrected by seek
+        let drift_ms = wait_for_drift(&leader_player, &follower_player, max_drift_ms).await;
+        assert!(drift_ms.abs() <= max_drift_ms, "drift {drift_ms} ms after seek");
+        assert_eq!(follower.status().seeks, 1);
+
+        // Small drift is corrected by faster playback
+        tokio::time::sleep(SEEK_SETTLE).await;
+        follower_player.set_time(follower_player.time_ms() - 150);
+        let drift_ms = wait_for_drift(&leader_player, &follower_player, max_drift_ms).await;
+        assert!(drift_ms.abs() <= max_drift_ms, "drift {drift_ms} ms after rate change");
+        assert_eq!(follower.status().seeks, 1);
+        assert!(*follower_player.max_rate.lock().unwrap() > 1.0);
+
+        leader.shutdown().await.unwrap();
+        follower.shutdown().await.unwrap();
+    }
+}
@@ -0,0 +1,392 @@
+use std::{path::{Path, PathBuf}, sync::Arc, time::Duration};
+
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use tokio::io::AsyncWriteExt;
+use tokio_util::sync::CancellationToken;
+
+use crate::{events::{EventBus, FetchProgress, SystemEvent}, file_manager::{FilesManager, UploadActivation}, webserver::WebServer, FilesManagerSink, FilesSource, FilesSourceHandler, FilesSourceType, UploadedContent, UploadedFile};
+
+/// Waiting requests, further ones are rejected until a download finishes.
+const REQUESTS_CAP: usize = 8;
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(10);
+const PROGRESS_STEP_BYTES: u64 = 1024 * 1024;
+
+#[derive(Debug, thiserror::Error)]
+pub enum UrlFetchError {
+    #[error("TokioIoError reason = '{0}'")]
+    TokioIoError(#[from] tokio::io::Error),
+
+    #[error("ReqwestError reason = '{0}'")]
+    ReqwestError(#[from] reqwest::Error),
+
+    #[error("TokioJoinError")]
+    TokioJoinError(#[from] tokio::task::JoinError),
+
+    #[error("InvalidUrl url = '{0}'")]
+    InvalidUrl(String),
+
+    #[error("InvalidFileName name = '{0}'")]
+    InvalidFileName(String),
+
+    #[error("InvalidChecksum sha256 = '{0}'")]
+    InvalidChecksum(String),
+
+    #[error("HttpStatus status = '{0}'")]
+    HttpStatus(u16),
+
+    #[error("TooLarge max_size = '{0}'")]
+    TooLarge(u64),
+
+    #[error("ChecksumMismatch expected = '{expected}', actual = '{actual}'")]
+    ChecksumMismatch { expected: String, actual: String },
+
+    #[error("QueueFull")]
+    QueueFull,
+
+    #[error("Stopped")]
+    Stopped,
+}
+
+/// Limits of media downloaded by URL.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FetchConfig {
+    #[serde(default = "FetchConfig::default_max_size_bytes")]
+    pub max_size_bytes: u64,
+
+    /// Download fails when no data arrives for this long.
+    #[serde(default = "FetchConfig::default_read_timeout_secs")]
+    pub read_timeout_secs: u64,
+}
+
+impl FetchConfig {
+    fn default_max_size_bytes() -> u64 {
+        WebServer::MAX_VIDEO_FILESIZE_BYTES as u64
+    }
+
+    fn default_read_timeout_secs() -> u64 {
+        30
+    }
+}
+
+impl Default for FetchConfig {
+    fn default() -> Self {
+        Self {
+            max_size_bytes: Self::default_max_size_bytes(),
+            read_timeout_secs: Self::default_read_timeout_secs(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FetchRequest {
+    /// 'http' or 'https'.
+    pub url: String,
+    /// Hex SHA-256, download is discarded if it differs.
+    pub sha256: Option<String>,
+    /// Last segment of URL path if missing.
+    pub filename: Option<String>,
+    pub activation: UploadActivation,
+}
+
+/// Validated request waiting for download.
+#[derive(Debug)]
+struct FetchJob {
+    url: reqwest::Url,
+    filename: String,
+    sha256: Option<String>,
+    activation: UploadActivation,
+}
+
+/// Queues downloads of 'FileSourceUrlFetch', e.g. for web server.
+#[derive(Clone)]
+pub struct UrlFetcher {
+    requests_tx: tokio::sync::mpsc::Sender<FetchJob>,
+}
+
+impl UrlFetcher {
+    /// Returns file name media is stored as, download continues in background.
+    pub fn fetch(&self, request: FetchRequest) -> Result<String, UrlFetchError> {
+        let url = reqwest::Url::parse(&request.url)
+            .ok()
+            .filter(|url| matches!(url.scheme(), "http" | "https"))
+            .ok_or_else(|| UrlFetchError::InvalidUrl(request.url.clone()))?;
+
+        let filename = match request.filename {
+            Some(filename) => filename,
+            None => {
+                let segment = url.path_segments()
+                    .and_then(|mut segments| segments.next_back())
+                    .unwrap_or_default();
+                percent_encoding::percent_decode_str(segment)
+                    .decode_utf8()
+                    .map_err(|_| UrlFetchError::InvalidFileName(segment.to_string()))?
+                    .into_owned()
+            }
+        };
+        if !FilesManager::is_valid_file_name(&filename) {
+            return Err(UrlFetchError::InvalidFileName(filename));
+        }
+
+        let sha256 = request.sha256.map(|sha256| sha256.to_ascii_lowercase());
+        if let Some(sha256) = &sha256
+            && (sha256.len() != 64 || !sha256.bytes().all(|byte| byte.is_ascii_hexdigit()))
+        {
+            return Err(UrlFetchError::InvalidChecksum(sha256.clone()));
+        }
+
+        let job = FetchJob { url, filename: filename.clone(), sha256, activation: request.activation };
+        self.requests_tx.try_send(job).map_err(|e| match e {
+            tokio::sync::mpsc::error::TrySendError::Full(_) => UrlFetchError::QueueFull,
+            tokio::sync::mpsc::error::TrySendError::Closed(_) => UrlFetchError::Stopped,
+        })?;
+        Ok(filename)
+    }
+}
+
+/// Downloads media by URL one at a time and passes it to files manager like an upload.
+pub struct FileSourceUrlFetch {
+    download_path: PathBuf,
+    config: FetchConfig,
+    event_bus: Option<Arc<EventBus>>,
+    requests_tx: tokio::sync::mpsc::Sender<FetchJob>,
+    requests_rx: tokio::sync::mpsc::Receiver<FetchJob>,
+}
+
+pub struct FileSourceUrlFetchHandler {
+    task_handle: tokio::task::JoinHandle<()>,
+    cancel_token: CancellationToken,
+}
+
+impl FilesSourceHandler for FileSourceUrlFetchHandler {
+    type Error = UrlFetchError;
+
+    /// Running download is abandoned.
+    async fn shutdown(self) -> Result<(), Self::Error> {
+        self.cancel_token.cancel();
+        self.task_handle.await.map_err(Self::Error::from)
+    }
+
+    async fn await_finish(self) -> Result<(), Self::Error> {
+        self.task_handle.await.map_err(Self::Error::from)
+    }
+}
+
+impl FileSourceUrlFetch {
+    /// Partial downloads are kept in 'download_path'.
+    pub fn new(download_path: PathBuf, config: FetchConfig) -> Self {
+        let (requests_tx, requests_rx) = tokio::sync::mpsc::channel(REQUESTS_CAP);
+        Self { download_path, config, event_bus: None, requests_tx, requests_rx }
+    }
+
+    /// Publishes download progress.
+    pub fn with_event_bus(mut self, event_bus: Arc<EventBus>) -> Self {
+        self.event_bus = Some(event_bus);
+        self
+    }
+
+    pub fn fetcher(&self) -> UrlFetcher {
+        UrlFetcher { requests_tx: self.requests_tx.clone() }
+    }
+
+    async fn download(
+        client: &reqwest::Client,
+        config: &FetchConfig,
+        job: &FetchJob,
+        file_path: &Path,
+        publish: &(impl Fn(FetchProgress) + Send + Sync),
+    ) -> Result<u64, UrlFetchError> {
+        let mut response = client.get(job.url.clone()).send().await?;
+        if !response.status().is_success() {
+            return Err(UrlFetchError::HttpStatus(response.status().as_u16()));
+        }
+        let total_bytes = response.content_length();
+        if total_bytes.is_some_and(|total_bytes| total_bytes > config.max_size_bytes) {
+            return Err(UrlFetchError::TooLarge(config.max_size_bytes));
+        }
+
+        let mut file = tokio::fs::File::create(file_path).await?;
+        let mut hasher = Sha256::new();
+        let mut received_bytes = 0;
+        let mut reported_bytes = 0;
+        while let Some(chunk) = response.chunk().await? {
+            received_bytes += chunk.len() as u64;
+            // Content-Length may be missing or wrong
+            if received_bytes > config.max_size_bytes {
+                return Err(UrlFetchError::TooLarge(config.max_size_bytes));
+            }
+            hasher.update(&chunk);
+            file.write_all(&chunk).await?;
+
+            if received_bytes - reported_bytes >= PROGRESS_STEP_BYTES {
+                reported_bytes = received_bytes;
+                publish(FetchProgress::Downloading {
+                    url: job.url.to_string(),
+                    filename: job.filename.clone(),
+                    received_bytes,
+                    total_bytes,
+                });
+            }
+        }
+        file.flush().await?;
+        file.sync_data().await?;
+
+        if let Some(expected) = &job.sha256 {
+            let actual = format!("{:x}", hasher.finalize());
+            if actual != *expected {
+                return Err(UrlFetchError::ChecksumMismatch { expected: expected.clone(), actual });
+            }
+        }
+        Ok(received_bytes)
+    }
+
+    /// Leftovers of previous run are removed.
+    async fn recreate_dir(path: &Path) -> Result<(), UrlFetchError> {
+        match tokio::fs::remove_dir_all(path).await {
+            Ok(()) => {},
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {},
+            Err(e) => return Err(e.into()),
+        }
+        tokio::fs::create_dir_all(path).await?;
+        Ok(())
+    }
+}
+
+impl FilesSource for FileSourceUrlFetch {
+    type Handler = FileSourceUrlFetchHandler;
+    type Error = UrlFetchError;
+
+    async fn start(self, sink: Arc<dyn FilesManagerSink>) -> Result<Self::Handler, Self::Error> {
+        Self::recreate_dir(&self.download_path).await?;
+        let client = reqwest::Client::builder()
+            .connect_timeout(CONNECT_TIMEOUT)
+            .read_timeout(Duration::from_secs(self.config.read_timeout_secs))
+            .build()?;
+
+        let files_manager_sink = sink.get_tx();
+        let event_bus = self.event_bus;
+        let publish = move |progress: FetchProgress| {
+            if let Some(event_bus) = &event_bus {
+                event_bus.publish(SystemEvent::Fetch(progress));
+            }
+        };
+
+        let cancel_token = CancellationToken::new();
+        let task_cancel_token = cancel_token.clone();
+        let FileSourceUrlFetch { download_path, config, mut requests_rx, .. } = self;
+
+        let task_handle = tokio::spawn(async move {
+            let mut download_count: u64 = 0;
+            loop {
+                let job = tokio::select! {
+                    _ = task_cancel_token.cancelled() => break,
+                    job = requests_rx.recv() => match job {
+                        Some(job) => job,
+                        None => break,
+                    },
+                };
+
+                // File manager removes file once processed, unique name keeps it apart from next download
+                download_count += 1;
+                let file_path = download_path.join(format!("{download_count}.part"));
+                tracing::info!("Downloading {} as {:?}", job.url, job.filename);
+
+                let result = tokio::select! {
+                    _ = task_cancel_token.cancelled() => Err(UrlFetchError::Stopped),
+                    result = Self::download(&client, &config, &job, &file_path, &publish) => result,
+                };
+                let size_bytes = match result {
+                    Ok(size_bytes) => size_bytes,
+                    Err(e) => {
+                        tracing::warn!("Download of {} failed, reason = {e}", job.url);
+                        let _ = tokio::fs::remove_file(&file_path).await;
+                        publish(FetchProgress::Failed { url: job.url.to_string(), filename: job.filename, reason: e.to_string() });
+                        continue;
+                    },
+                };
+
+                tracing::info!("Downloaded {} ({size_bytes} bytes)", job.url);
+                publish(FetchProgress::Downloaded { url: job.url.to_string(), filename: job.filename.clone(), size_bytes });
+                let files = vec![UploadedFile { filename: job.filename, content: UploadedContent::File(file_path.clone()) }];
+                if files_manager_sink.send(FilesSourceType::UploadedFiles { files, activation: job.activation }).await.is_err() {
+                    tracing::warn!("Files manager is closed, download discarded");
+                    let _ = tokio::fs::remove_file(&file_path).await;
+                    break;
+                }
+            }
+        });
+
+        Ok(Self::Handler { task_handle, cancel_token })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use tokio::io::AsyncReadExt;
+
+    use super::*;
+    use crate::TestSink;
+
+    /// Answers every request with 'body', returns base URL.
+    async fn serve(body: &'static [u8]) -> String {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let address = listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            while let Ok((mut stream, _)) = listener.accept().await {
+                let mut request = vec![0; 1024];
+                let _ = stream.read(&mut request).await;
+                let header = format!("HTTP/1.1 200 OK\r\nContent-Length: {}\r\nConnection: close\r\n\r\n", body.len());
+                let _ = stream.write_all(header.as_bytes()).await;
+                let _ = stream.write_all(body).await;
+            }
+        });
+        format!("http://{address}")
+    }
+
+    fn request(url: String, sha256: Option<String>) -> FetchRequest {
+        FetchRequest { url, sha256, filename: None, activation: UploadActivation::StoreOnly }
+    }
+
+    #[tokio::test]
+    async fn test_fetch_verifies_checksum_and_size() {
+        const BODY: &[u8] = b"\x89PNG\r\n\x1a\nimage";
+        let base_url = serve(BODY).await;
+        let large_url = serve(b"\x89PNG\r\n\x1a\nlarger image").await;
+        let download_path = std::env::temp_dir().join(format!("headlesspiplayer_fetch_test_{}", std::process::id()));
+        let (files_tx, mut files_rx) = tokio::sync::mpsc::channel(4);
+        let event_bus = Arc::new(EventBus::new());
+        let mut events_rx = event_bus.subscribe();
+
+        let config = FetchConfig { max_size_bytes: BODY.len() as u64, ..FetchConfig::default() };
+        let source = FileSourceUrlFetch::new(download_path.clone(), config).with_event_bus(event_bus);
+        let fetcher = source.fetcher();
+        let handler = source.start(Arc::new(TestSink { tx: files_tx })).await.unwrap();
+
+        assert!(matches!(fetcher.fetch(request("ftp://example.com/a.png".to_string(), None)), Err(UrlFetchError::InvalidUrl(_))));
+        assert!(matches!(fetcher.fetch(request(format!("{base_url}/"), None)), Err(UrlFetchError::InvalidFileName(_))));
+        assert!(matches!(fetcher.fetch(request(format!("{base_url}/a.png"), Some("abc".to_string()))), Err(UrlFetchError::InvalidChecksum(_))));
+
+        fetcher.fetch(request(format!("{base_url}/wrong.png"), Some("0".repeat(64)))).unwrap();
+        let event = events_rx.recv().await.unwrap();
+        assert!(matches!(event, SystemEvent::Fetch(FetchProgress::Failed { ref filename, .. }) if filename == "wrong.png"), "{event:?}");
+
+        fetcher.fetch(request(format!("{large_url}/large.png"), None)).unwrap();
+        let event = events_rx.recv().await.unwrap();
+        assert!(matches!(event, SystemEvent::Fetch(FetchProgress::Failed { ref reason, .. }) if reason.starts_with("TooLarge")), "{event:?}");
+
+        let sha256 = format!("{:X}", Sha256::digest(BODY));
+        assert_eq!(fetcher.fetch(request(format!("{base_url}/dir/my%20image.png?version=2"), Some(sha256))).unwrap(), "my image.png");
+        let Some(FilesSourceType::UploadedFiles { files, activation }) = files_rx.recv().await else {
+            panic!("Download not passed to files manager");
+        };
+        assert_eq!(activation, UploadActivation::StoreOnly);
+        assert_eq!(files[0].filename, "my image.png");
+        let UploadedContent::File(file_path) = &files[0].content else {
+            panic!("Download not stored in file");
+        };
+        assert_eq!(tokio::fs::read(file_path).await.unwrap(), BODY);
+
+        handler.shutdown().await.unwrap();
+        let _ = tokio::fs::remove_dir_all(&download_path).await;
+    }
+}
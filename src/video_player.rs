@@ -1,95 +1,1744 @@
-use std::path::PathBuf;
+use std::{path::PathBuf, sync::{mpsc::RecvTimeoutError, Arc, Mutex}, time::{Duration, Instant}};
 
-use vlc::MediaPlayerAudioEx;
+use serde::{Deserialize, Serialize};
+use vlc::{EventType, LogoOption, MarqueeOption, MediaPlayerAudioEx, MediaPlayerVideoEx, State, VideoAdjustOption};
 
-use crate::{FileSubscriber, FileSubscriberError};
+use crate::{media_probe::MediaFormat, playlist::{MediaKind, Playlist, PlaylistItem}, splash::{render_splash_png, SplashInfo}, sync::{PlaybackPosition, SyncPlayer}, FileSubscriber, FileSubscriberError};
+
+const SPLASH_FILENAME: &str = "headlesspiplayer_splash.png";
+const SPLASH_WIDTH: usize = 960;
+const SPLASH_HEIGHT: usize = 540;
+
+/// Brightness update period while fading.
+const FADE_STEP: Duration = Duration::from_millis(40);
+
+/// Playback progress check period.
+const WATCHDOG_CHECK_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Failed recoveries are forgotten after playback runs this long.
+const WATCHDOG_RECOVERED_AFTER: Duration = Duration::from_secs(30);
+
+const PLAYER_EVENTS_CAP: usize = 64;
+
+/// Minimal period of position events.
+const POSITION_TICK_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Time jump back larger than this means repeated media restarted.
+const LOOP_DETECTION_THRESHOLD_MS: i64 = 500;
+
+/// Stream ending this close to its display time ended normally, otherwise it dropped.
+const STREAM_END_TOLERANCE_MS: i64 = 1000;
 
 pub struct VideoPlayer {
     _video_player_task: tokio::task::JoinHandle<()>,
     player_ctrl_tx: std::sync::mpsc::Sender<VideoPlayerCommand>,
+    watchdog_status: Arc<Mutex<WatchdogStatus>>,
+    status: Arc<Mutex<PlayerStatus>>,
+    events_tx: tokio::sync::broadcast::Sender<PlayerEvent>,
+}
+
+impl Drop for VideoPlayer {
+    fn drop(&mut self) {
+        // VLC event callback holds a sender, so channel is never closed by itself
+        let _ = self.player_ctrl_tx.send(VideoPlayerCommand::Shutdown);
+    }
 }
 
 #[derive(Debug)]
 enum VideoPlayerCommand {
-    Play(PathBuf),
+    Play(Playlist),
+    /// Replaces current playlist when its loop ends.
+    Queue(Playlist),
+    /// Sent from VLC callbacks with index of player.
+    ItemEnded(usize),
+    ItemOpening(usize),
+    ItemPlaying(usize),
+    ItemPaused(usize),
+    TimeChanged(usize),
+    EncounteredError(usize),
+    /// Periodic fade and watchdog update.
+    Tick,
+    Shutdown,
     Stop(tokio::sync::oneshot::Sender<()>),
+    Pause,
+    Resume,
+    /// Jumps relative to current playlist item, wraps around.
+    Skip(isize),
+    Position(tokio::sync::oneshot::Sender<Option<PlaybackPosition>>),
+    SetTime(i64),
+    SetRate(f32),
+    ShowText(TextOverlay),
+    HideText,
+    ShowLogo(LogoOverlay),
+    HideLogo,
+    SetAdjustment(VideoAdjustment),
+    SetSplash(SplashInfo),
+}
+
+/// Playback state change, path is missing for media outside playlist.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum PlayerEvent {
+    Opened {
+        path: Option<PathBuf>,
+    },
+    Playing {
+        path: Option<PathBuf>,
+    },
+    Paused {
+        path: Option<PathBuf>,
+    },
+    Ended {
+        path: Option<PathBuf>,
+    },
+    /// Repeated item or whole playlist started again.
+    Looped {
+        path: Option<PathBuf>,
+    },
+    Stopped,
+    Error {
+        path: Option<PathBuf>,
+        reason: String,
+    },
+    /// Dropped stream is opened again after 'delay_ms'.
+    Reconnecting {
+        path: Option<PathBuf>,
+        attempt: u32,
+        delay_ms: u64,
+    },
+    Position {
+        path: Option<PathBuf>,
+        time_ms: i64,
+        length_ms: Option<i64>,
+    },
+}
+
+impl std::fmt::Display for PlayerEvent {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            PlayerEvent::Opened { path } => write!(f, "opened {path:?}"),
+            PlayerEvent::Playing { path } => write!(f, "playing {path:?}"),
+            PlayerEvent::Paused { path } => write!(f, "paused {path:?}"),
+            PlayerEvent::Ended { path } => write!(f, "ended {path:?}"),
+            PlayerEvent::Looped { path } => write!(f, "looped {path:?}"),
+            PlayerEvent::Stopped => write!(f, "stopped"),
+            PlayerEvent::Error { path, reason } => write!(f, "error {path:?}, reason = {reason}"),
+            PlayerEvent::Reconnecting { path, attempt, delay_ms } => write!(f, "reconnecting {path:?}, attempt {attempt} in {delay_ms} ms"),
+            PlayerEvent::Position { path, time_ms, length_ms } => write!(f, "position {path:?} {time_ms}/{length_ms:?} ms"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct VideoPlayerConfig {
+    /// Image display time in playlist when item has no own duration.
+    #[serde(default = "VideoPlayerConfig::default_image_duration_secs")]
+    pub image_duration_secs: f32,
+
+    /// Device output geometry, e.g. rotation of sideways mounted screen.
+    #[serde(default)]
+    pub geometry: DisplayGeometry,
+
+    /// Switching between playlist items.
+    #[serde(default)]
+    pub transition: TransitionConfig,
+
+    #[serde(default)]
+    pub watchdog: WatchdogConfig,
+
+    #[serde(default)]
+    pub stream: StreamConfig,
+}
+
+impl VideoPlayerConfig {
+    fn default_image_duration_secs() -> f32 {
+        5.0
+    }
+}
+
+impl Default for VideoPlayerConfig {
+    fn default() -> Self {
+        Self {
+            image_duration_secs: Self::default_image_duration_secs(),
+            geometry: DisplayGeometry::default(),
+            transition: TransitionConfig::default(),
+            watchdog: WatchdogConfig::default(),
+            stream: StreamConfig::default(),
+        }
+    }
+}
+
+/// Recovers playback from VLC errors and stalled video.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct WatchdogConfig {
+    #[serde(default = "WatchdogConfig::default_enabled")]
+    pub enabled: bool,
+
+    /// Video time not advancing this long is a stall.
+    #[serde(default = "WatchdogConfig::default_stall_timeout_ms")]
+    pub stall_timeout_ms: u32,
+
+    /// Media reloads in a row before VLC instance is recreated.
+    #[serde(default = "WatchdogConfig::default_reloads_before_restart")]
+    pub reloads_before_restart: u32,
+}
+
+impl WatchdogConfig {
+    fn default_enabled() -> bool {
+        true
+    }
+
+    fn default_stall_timeout_ms() -> u32 {
+        5000
+    }
+
+    fn default_reloads_before_restart() -> u32 {
+        3
+    }
+
+    fn stall_timeout(&self) -> Duration {
+        Duration::from_millis(self.stall_timeout_ms as u64)
+    }
+}
+
+impl Default for WatchdogConfig {
+    fn default() -> Self {
+        Self {
+            enabled: Self::default_enabled(),
+            stall_timeout_ms: Self::default_stall_timeout_ms(),
+            reloads_before_restart: Self::default_reloads_before_restart(),
+        }
+    }
+}
+
+/// Network stream items, dropped stream is reconnected.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct StreamConfig {
+    /// VLC buffer of network input, more is smoother on weak WiFi, less has lower latency.
+    #[serde(default = "StreamConfig::default_network_caching_ms")]
+    pub network_caching_ms: u32,
+
+    /// First reconnect delay, doubled after each attempt.
+    #[serde(default = "StreamConfig::default_reconnect_delay_ms")]
+    pub reconnect_delay_ms: u32,
+
+    #[serde(default = "StreamConfig::default_max_reconnect_delay_ms")]
+    pub max_reconnect_delay_ms: u32,
+
+    /// Playlist of more items moves on after this many failed attempts, single stream is retried forever.
+    #[serde(default = "StreamConfig::default_max_reconnect_attempts")]
+    pub max_reconnect_attempts: u32,
+}
+
+impl StreamConfig {
+    fn default_network_caching_ms() -> u32 {
+        1000
+    }
+
+    fn default_reconnect_delay_ms() -> u32 {
+        1000
+    }
+
+    fn default_max_reconnect_delay_ms() -> u32 {
+        30000
+    }
+
+    fn default_max_reconnect_attempts() -> u32 {
+        5
+    }
+
+    /// Delay before attempt following 'attempts' failed ones.
+    fn reconnect_delay(&self, attempts: u32) -> Duration {
+        let delay_ms = (self.reconnect_delay_ms as u64)
+            .saturating_mul(1 << attempts.min(16))
+            .min(self.max_reconnect_delay_ms as u64);
+        Duration::from_millis(delay_ms)
+    }
+}
+
+impl Default for StreamConfig {
+    fn default() -> Self {
+        Self {
+            network_caching_ms: Self::default_network_caching_ms(),
+            reconnect_delay_ms: Self::default_reconnect_delay_ms(),
+            max_reconnect_delay_ms: Self::default_max_reconnect_delay_ms(),
+            max_reconnect_attempts: Self::default_max_reconnect_attempts(),
+        }
+    }
+}
+
+/// Pending reconnect of current stream item.
+#[derive(Debug, Default)]
+struct StreamReconnect {
+    at: Option<Instant>,
+    attempts: u32,
+    last_scheduled: Option<Instant>,
+    /// First connection of current item.
+    item_started: Option<Instant>,
+    /// Skip to next item is requested.
+    moving_on: bool,
+}
+
+impl StreamReconnect {
+    /// Stops dropped stream, returns event to publish. Playlist of more items skips to next one instead
+    /// once stream display time passed or attempts ran out.
+    fn schedule(
+        &mut self,
+        player: &vlc::MediaPlayer,
+        config: &StreamConfig,
+        playlist: &Playlist,
+        index: usize,
+        played_ms: Option<i64>,
+        player_ctrl_tx: &std::sync::mpsc::Sender<VideoPlayerCommand>,
+    ) -> Option<PlayerEvent> {
+        player.stop();
+        let item = playlist.items.get(index)?;
+        if self.moving_on {
+            return None;
+        }
+        if self.moves_on(config, item, playlist.items.len(), played_ms) {
+            tracing::warn!("Stream {:?} dropped after {} reconnect attempts, moving to next item.", item.path, self.attempts);
+            self.moving_on = true;
+            let _ = player_ctrl_tx.send(VideoPlayerCommand::Skip(1));
+            return None;
+        }
+
+        let path = Some(item.path.clone());
+        let delay = config.reconnect_delay(self.attempts);
+        self.attempts += 1;
+        self.at = Some(Instant::now() + delay);
+        self.last_scheduled = Some(Instant::now());
+        tracing::warn!("Stream {path:?} dropped, reconnect attempt {} in {delay:?}.", self.attempts);
+        Some(PlayerEvent::Reconnecting { path, attempt: self.attempts, delay_ms: delay.as_millis() as u64 })
+    }
+
+    fn moves_on(&mut self, config: &StreamConfig, item: &PlaylistItem, playlist_len: usize, played_ms: Option<i64>) -> bool {
+        let played = Duration::from_millis(played_ms.unwrap_or_default().max(0) as u64);
+        let item_started = *self.item_started.get_or_insert_with(|| Instant::now().checked_sub(played).unwrap_or_else(Instant::now));
+        let display_time_passed = item.duration.is_some_and(|duration| item_started.elapsed() >= duration);
+        playlist_len > 1 && (display_time_passed || self.attempts >= config.max_reconnect_attempts)
+    }
+
+    fn is_due(&self) -> bool {
+        self.at.is_some_and(|at| at <= Instant::now())
+    }
+
+    /// Attempts are counted again once stream runs for a while.
+    fn on_progress(&mut self) {
+        if self.attempts > 0 && self.last_scheduled.is_some_and(|last_scheduled| last_scheduled.elapsed() > WATCHDOG_RECOVERED_AFTER) {
+            tracing::info!("Stream recovered.");
+            self.attempts = 0;
+        }
+    }
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PlaybackState {
+    /// Nothing loaded, splash may be shown.
+    #[default]
+    Idle,
+    Opening,
+    Playing,
+    Paused,
+    Error,
+    /// Dropped stream waits for reconnect.
+    Reconnecting,
+}
+
+/// Now playing information, kept up to date from player events.
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct PlayerStatus {
+    pub state: PlaybackState,
+    pub path: Option<PathBuf>,
+    pub time_ms: Option<i64>,
+    pub length_ms: Option<i64>,
+    pub error: Option<String>,
+    pub playlist: Playlist,
+    /// Played when current playlist loop ends.
+    pub queued: Option<Playlist>,
+}
+
+impl PlayerStatus {
+    fn update(&mut self, event: &PlayerEvent) {
+        match event {
+            PlayerEvent::Opened { path } => {
+                *self = Self {
+                    state: PlaybackState::Opening,
+                    path: path.clone(),
+                    playlist: std::mem::take(&mut self.playlist),
+                    queued: self.queued.take(),
+                    ..Default::default()
+                };
+            },
+            PlayerEvent::Playing { path } => {
+                self.state = PlaybackState::Playing;
+                self.path = path.clone();
+                self.error = None;
+            },
+            PlayerEvent::Paused { .. } => self.state = PlaybackState::Paused,
+            PlayerEvent::Ended { .. } => self.state = PlaybackState::Idle,
+            PlayerEvent::Looped { path } => self.path = path.clone(),
+            PlayerEvent::Stopped => {
+                *self = Self { playlist: std::mem::take(&mut self.playlist), queued: self.queued.take(), ..Default::default() };
+            },
+            PlayerEvent::Error { path, reason } => {
+                self.state = PlaybackState::Error;
+                self.path = path.clone();
+                self.error = Some(reason.clone());
+            },
+            PlayerEvent::Reconnecting { path, .. } => {
+                self.state = PlaybackState::Reconnecting;
+                self.path = path.clone();
+            },
+            PlayerEvent::Position { path, time_ms, length_ms } => {
+                self.path = path.clone();
+                self.time_ms = Some(*time_ms);
+                self.length_ms = *length_ms;
+            },
+        }
+    }
+}
+
+/// Recovery counters since start.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct WatchdogStatus {
+    pub media_reloads: u32,
+    pub instance_restarts: u32,
+    pub last_recovery_reason: Option<String>,
+    pub last_recovery_at: Option<String>,
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TransitionKind {
+    /// Next item replaces previous one, short black frame may be visible.
+    #[default]
+    Cut,
+    /// Next item is started on second player before previous one ends.
+    Gapless,
+    /// Brightness is faded out before item ends and faded in on next item.
+    FadeToBlack,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TransitionConfig {
+    #[serde(default)]
+    pub kind: TransitionKind,
+
+    /// Fade duration or how early next item is started.
+    #[serde(default = "TransitionConfig::default_duration_ms")]
+    pub duration_ms: u32,
+}
+
+impl TransitionConfig {
+    fn default_duration_ms() -> u32 {
+        500
+    }
+
+    fn duration(&self) -> Duration {
+        Duration::from_millis(self.duration_ms as u64)
+    }
+}
+
+impl Default for TransitionConfig {
+    fn default() -> Self {
+        Self {
+            kind: TransitionKind::default(),
+            duration_ms: Self::default_duration_ms(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum FadeDirection {
+    Out,
+    In,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Fade {
+    direction: FadeDirection,
+    started: Instant,
+    duration: Duration,
+}
+
+impl Fade {
+    fn new(direction: FadeDirection, duration: Duration) -> Self {
+        Self { direction, started: Instant::now(), duration }
+    }
+
+    /// Brightness factor 0.0 - 1.0 and if fade is finished.
+    fn progress(&self) -> (f32, bool) {
+        let elapsed = self.started.elapsed().as_secs_f32();
+        let ratio = (elapsed / self.duration.as_secs_f32().max(f32::EPSILON)).min(1.0);
+        let factor = match self.direction {
+            FadeDirection::Out => 1.0 - ratio,
+            FadeDirection::In => ratio,
+        };
+        (factor, ratio >= 1.0)
+    }
+}
+
+/// Clockwise rotation done by VLC transform filter.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(try_from = "u16", into = "u16")]
+pub enum Rotation {
+    #[default]
+    None,
+    Rotate90,
+    Rotate180,
+    Rotate270,
+}
+
+impl TryFrom<u16> for Rotation {
+    type Error = String;
+
+    fn try_from(degrees: u16) -> Result<Self, Self::Error> {
+        match degrees {
+            0 => Ok(Rotation::None),
+            90 => Ok(Rotation::Rotate90),
+            180 => Ok(Rotation::Rotate180),
+            270 => Ok(Rotation::Rotate270),
+            _ => Err(format!("Rotation {degrees} not supported, use 0, 90, 180 or 270")),
+        }
+    }
+}
+
+impl From<Rotation> for u16 {
+    fn from(rotation: Rotation) -> Self {
+        match rotation {
+            Rotation::None => 0,
+            Rotation::Rotate90 => 90,
+            Rotation::Rotate180 => 180,
+            Rotation::Rotate270 => 270,
+        }
+    }
+}
+
+/// Output geometry, missing values keep VLC defaults.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct DisplayGeometry {
+    #[serde(default)]
+    pub rotation: Option<Rotation>,
+
+    /// Forced aspect ratio, e.g. "16:9".
+    #[serde(default)]
+    pub aspect_ratio: Option<String>,
+
+    /// VLC crop geometry, e.g. "4:3", "1280x720+0+180" or "10+20+10+20" (left+top+right+bottom).
+    #[serde(default)]
+    pub crop: Option<String>,
+
+    /// Scale factor, 0 means fit to screen.
+    #[serde(default)]
+    pub scale: Option<f32>,
+}
+
+impl DisplayGeometry {
+    /// Values set in 'other' take precedence.
+    pub fn overridden_by(&self, other: Option<&DisplayGeometry>) -> DisplayGeometry {
+        let Some(other) = other else {
+            return self.clone();
+        };
+        DisplayGeometry {
+            rotation: other.rotation.or(self.rotation),
+            aspect_ratio: other.aspect_ratio.clone().or_else(|| self.aspect_ratio.clone()),
+            crop: other.crop.clone().or_else(|| self.crop.clone()),
+            scale: other.scale.or(self.scale),
+        }
+    }
+
+    /// Rotation has to be set before media is played.
+    fn apply_to_media(&self, media: &vlc::Media) {
+        let transform_type = match self.rotation.unwrap_or_default() {
+            Rotation::None => return,
+            Rotation::Rotate90 => "90",
+            Rotation::Rotate180 => "180",
+            Rotation::Rotate270 => "270",
+        };
+        media.add_option(":video-filter=transform");
+        media.add_option(&format!(":transform-type={transform_type}"));
+    }
+
+    fn apply_to_player(&self, player: &vlc::MediaPlayer) {
+        // Null bytes cannot be passed to VLC
+        let aspect_ratio = self.aspect_ratio.as_ref().map(|aspect_ratio| aspect_ratio.replace('\0', ""));
+        let crop = self.crop.as_ref().map(|crop| crop.replace('\0', ""));
+        player.set_aspect_ratio(aspect_ratio.as_deref());
+        player.set_crop_geometry(crop.as_deref());
+        player.set_scale(self.scale.filter(|scale| scale.is_finite() && *scale >= 0.0).unwrap_or(0.0));
+    }
+}
+
+/// Overlay anchor on screen.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum OverlayPosition {
+    Center,
+    Left,
+    Right,
+    Top,
+    TopLeft,
+    TopRight,
+    Bottom,
+    #[default]
+    BottomLeft,
+    BottomRight,
+}
+
+impl OverlayPosition {
+    /// VLC subpicture filters use bit flags: 1 left, 2 right, 4 top, 8 bottom.
+    fn to_vlc_flags(self) -> i32 {
+        match self {
+            OverlayPosition::Center => 0,
+            OverlayPosition::Left => 1,
+            OverlayPosition::Right => 2,
+            OverlayPosition::Top => 4,
+            OverlayPosition::TopLeft => 4 | 1,
+            OverlayPosition::TopRight => 4 | 2,
+            OverlayPosition::Bottom => 8,
+            OverlayPosition::BottomLeft => 8 | 1,
+            OverlayPosition::BottomRight => 8 | 2,
+        }
+    }
+}
+
+/// Text drawn over video by VLC marquee filter.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TextOverlay {
+    pub text: String,
+
+    #[serde(default)]
+    pub position: OverlayPosition,
+
+    /// Offset from anchor in pixels.
+    #[serde(default)]
+    pub x: i32,
+
+    #[serde(default)]
+    pub y: i32,
+
+    /// Color as 0xRRGGBB.
+    #[serde(default = "TextOverlay::default_color")]
+    pub color: u32,
+
+    /// Font size in pixels, 0 means VLC default.
+    #[serde(default)]
+    pub size: u32,
+
+    /// 0 transparent, 255 opaque.
+    #[serde(default = "TextOverlay::default_opacity")]
+    pub opacity: u8,
+
+    /// Hide after given time, 0 means never.
+    #[serde(default)]
+    pub timeout_ms: u32,
+}
+
+impl TextOverlay {
+    fn default_color() -> u32 {
+        0xFFFFFF
+    }
+
+    fn default_opacity() -> u8 {
+        255
+    }
+
+    pub fn new(text: impl Into<String>) -> Self {
+        Self {
+            text: text.into(),
+            position: OverlayPosition::default(),
+            x: 0,
+            y: 0,
+            color: Self::default_color(),
+            size: 0,
+            opacity: Self::default_opacity(),
+            timeout_ms: 0,
+        }
+    }
+
+    pub fn with_timeout_ms(mut self, timeout_ms: u32) -> Self {
+        self.timeout_ms = timeout_ms;
+        self
+    }
 }
 
 
 impl FileSubscriber for VideoPlayer {
     async fn on_file_about_to_be_deleted(&self) -> Result<(), FileSubscriberError> {
         tracing::info!("'on_file_about_to_be_deleted'");
-        let (stop_tx, stop_rx) = tokio::sync::oneshot::channel();
-        let _ = self.player_ctrl_tx.send(VideoPlayerCommand::Stop(stop_tx));
-        let _ = stop_rx.await;
+        self.stop().await;
         Ok(())
     }
 
     async fn on_new_file_available(&self, file_path: &std::path::Path) -> Result<(), FileSubscriberError> {
         tracing::info!("'on_new_file_available' {file_path:?}");
-        let _ = self.player_ctrl_tx.send(VideoPlayerCommand::Play(file_path.to_path_buf()));
+        // Unknown format is handed to VLC as video
+        let kind = MediaFormat::detect_file(file_path).await.map_or(MediaKind::Video, MediaFormat::kind);
+        let item = PlaylistItem::new(file_path.to_path_buf(), kind);
+        let _ = self.player_ctrl_tx.send(VideoPlayerCommand::Play(Playlist::single(item)));
+        Ok(())
+    }
+
+    async fn on_new_playlist_available(&self, playlist: &Playlist) -> Result<(), FileSubscriberError> {
+        tracing::info!("'on_new_playlist_available' {} items", playlist.items.len());
+        let _ = self.player_ctrl_tx.send(VideoPlayerCommand::Play(playlist.clone()));
+        Ok(())
+    }
+
+    async fn on_playlist_queued(&self, playlist: &Playlist) -> Result<(), FileSubscriberError> {
+        tracing::info!("'on_playlist_queued' {} items", playlist.items.len());
+        self.queue(playlist.clone());
         Ok(())
     }
 }
 
+impl SyncPlayer for VideoPlayer {
+    async fn position(&self) -> Option<PlaybackPosition> {
+        let (position_tx, position_rx) = tokio::sync::oneshot::channel();
+        let _ = self.player_ctrl_tx.send(VideoPlayerCommand::Position(position_tx));
+        position_rx.await.ok().flatten()
+    }
+
+    fn set_time(&self, time_ms: i64) {
+        let _ = self.player_ctrl_tx.send(VideoPlayerCommand::SetTime(time_ms));
+    }
+
+    fn set_rate(&self, rate: f32) {
+        let _ = self.player_ctrl_tx.send(VideoPlayerCommand::SetRate(rate));
+    }
+}
+
+/// Image drawn over video by VLC logo filter.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LogoOverlay {
+    /// PNG file path.
+    pub file: PathBuf,
+
+    #[serde(default = "LogoOverlay::default_position")]
+    pub position: OverlayPosition,
+
+    /// Offset from anchor in pixels.
+    #[serde(default)]
+    pub x: i32,
+
+    #[serde(default)]
+    pub y: i32,
+
+    /// 0 transparent, 255 opaque.
+    #[serde(default = "LogoOverlay::default_opacity")]
+    pub opacity: u8,
+
+    /// Logo is alternately shown and hidden each 'delay_ms'.
+    #[serde(default)]
+    pub blink: bool,
+
+    /// Animation frame duration.
+    #[serde(default = "LogoOverlay::default_delay_ms")]
+    pub delay_ms: u32,
+
+    /// Animation loops, -1 means forever.
+    #[serde(default = "LogoOverlay::default_repeat")]
+    pub repeat: i32,
+}
+
+impl LogoOverlay {
+    fn default_position() -> OverlayPosition {
+        OverlayPosition::TopRight
+    }
+
+    fn default_opacity() -> u8 {
+        255
+    }
+
+    fn default_delay_ms() -> u32 {
+        1000
+    }
+
+    fn default_repeat() -> i32 {
+        -1
+    }
+
+    /// VLC logo file format: 'file[,delay[,alpha]][;file[,delay[,alpha]]]...'.
+    fn to_vlc_file_list(&self) -> String {
+        // Separators cannot be escaped in file list
+        let file = self.file.to_string_lossy().replace(['\0', ',', ';'], "");
+        if self.blink {
+            format!("{file},{delay},{opacity};{file},{delay},0", delay = self.delay_ms, opacity = self.opacity)
+        } else {
+            file
+        }
+    }
+}
+
+/// Image correction by VLC adjust filter, defaults leave image unchanged.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct VideoAdjustment {
+    /// 0.0 - 2.0
+    #[serde(default = "VideoAdjustment::default_one")]
+    pub brightness: f32,
+
+    /// 0.0 - 2.0
+    #[serde(default = "VideoAdjustment::default_one")]
+    pub contrast: f32,
+
+    /// 0.0 - 3.0
+    #[serde(default = "VideoAdjustment::default_one")]
+    pub saturation: f32,
+
+    /// 0.01 - 10.0
+    #[serde(default = "VideoAdjustment::default_one")]
+    pub gamma: f32,
+
+    /// Hue rotation in degrees, -180 - 180.
+    #[serde(default)]
+    pub hue: f32,
+}
+
+impl VideoAdjustment {
+    fn default_one() -> f32 {
+        1.0
+    }
+
+    /// Darkened copy used by fade transitions.
+    fn faded(&self, factor: f32) -> Self {
+        Self { brightness: self.brightness * factor, ..*self }
+    }
+
+    /// Limits values to ranges accepted by VLC, invalid numbers become defaults.
+    pub fn clamped(&self) -> Self {
+        let clamp = |value: f32, min: f32, max: f32, default: f32| {
+            if value.is_finite() { value.clamp(min, max) } else { default }
+        };
+        Self {
+            brightness: clamp(self.brightness, 0.0, 2.0, 1.0),
+            contrast: clamp(self.contrast, 0.0, 2.0, 1.0),
+            saturation: clamp(self.saturation, 0.0, 3.0, 1.0),
+            gamma: clamp(self.gamma, 0.01, 10.0, 1.0),
+            hue: clamp(self.hue, -180.0, 180.0, 0.0),
+        }
+    }
+}
+
+impl Default for VideoAdjustment {
+    fn default() -> Self {
+        Self {
+            brightness: Self::default_one(),
+            contrast: Self::default_one(),
+            saturation: Self::default_one(),
+            gamma: Self::default_one(),
+            hue: 0.0,
+        }
+    }
+}
+
 impl VideoPlayer {
-    pub async fn run(looping: bool) -> Self {
+    /// Shows text over video, replaces previous one.
+    pub fn show_text(&self, overlay: TextOverlay) {
+        tracing::info!("Show text overlay {:?}", overlay.text);
+        let _ = self.player_ctrl_tx.send(VideoPlayerCommand::ShowText(overlay));
+    }
+
+    pub fn hide_text(&self) {
+        let _ = self.player_ctrl_tx.send(VideoPlayerCommand::HideText);
+    }
+
+    /// Shows image over video, replaces previous one.
+    pub fn show_logo(&self, logo: LogoOverlay) {
+        tracing::info!("Show logo overlay {:?}", logo.file);
+        let _ = self.player_ctrl_tx.send(VideoPlayerCommand::ShowLogo(logo));
+    }
+
+    pub fn hide_logo(&self) {
+        let _ = self.player_ctrl_tx.send(VideoPlayerCommand::HideLogo);
+    }
+
+    /// Applied immediately to current and next media.
+    pub fn set_adjustment(&self, adjustment: VideoAdjustment) {
+        tracing::info!("Set video adjustment {adjustment:?}");
+        let _ = self.player_ctrl_tx.send(VideoPlayerCommand::SetAdjustment(adjustment));
+    }
+
+    /// Events are bridged from libvlc callbacks, slow receivers lose oldest events.
+    pub fn subscribe(&self) -> tokio::sync::broadcast::Receiver<PlayerEvent> {
+        self.events_tx.subscribe()
+    }
+
+    pub fn status(&self) -> PlayerStatus {
+        self.status.lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+            .clone()
+    }
+
+    /// Playlist replaces current one when its loop ends, or immediately if nothing is played.
+    pub fn queue(&self, playlist: Playlist) {
+        tracing::info!("Queue playlist of {} items", playlist.items.len());
+        let _ = self.player_ctrl_tx.send(VideoPlayerCommand::Queue(playlist));
+    }
+
+    pub fn pause(&self) {
+        let _ = self.player_ctrl_tx.send(VideoPlayerCommand::Pause);
+    }
+
+    pub fn resume(&self) {
+        let _ = self.player_ctrl_tx.send(VideoPlayerCommand::Resume);
+    }
+
+    pub fn next(&self) {
+        let _ = self.player_ctrl_tx.send(VideoPlayerCommand::Skip(1));
+    }
+
+    pub fn previous(&self) {
+        let _ = self.player_ctrl_tx.send(VideoPlayerCommand::Skip(-1));
+    }
+
+    /// Returns after playback is stopped, splash is shown if set.
+    pub async fn stop(&self) {
+        let (stop_tx, stop_rx) = tokio::sync::oneshot::channel();
+        let _ = self.player_ctrl_tx.send(VideoPlayerCommand::Stop(stop_tx));
+        let _ = stop_rx.await;
+    }
+
+    pub fn watchdog_status(&self) -> WatchdogStatus {
+        self.watchdog_status.lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+            .clone()
+    }
+
+    /// Idle screen is shown whenever no media is loaded.
+    pub fn set_splash(&self, info: SplashInfo) {
+        let _ = self.player_ctrl_tx.send(VideoPlayerCommand::SetSplash(info));
+    }
+
+    fn show_splash(vlc_instance: &vlc::Instance, player: &vlc::MediaPlayer, info: &SplashInfo, geometry: &DisplayGeometry) {
+        // Outside media temp directory, which is recreated on each new file
+        let splash_path = std::env::temp_dir().join(SPLASH_FILENAME);
+        let write_result = render_splash_png(info, SPLASH_WIDTH, SPLASH_HEIGHT)
+            .map_err(|e| e.to_string())
+            .and_then(|png_data| std::fs::write(&splash_path, png_data).map_err(|e| e.to_string()));
+        if let Err(e) = write_result {
+            tracing::warn!("Could not create splash {splash_path:?}, reason = {e}");
+            return;
+        }
+
+        tracing::info!("VLC showing splash {info:?}");
+        if let Some(media) = vlc::Media::new_path(vlc_instance, &splash_path) {
+            media.add_option(":image-duration=-1");
+            media.add_option(":no-audio");
+            geometry.apply_to_media(&media);
+            player.set_media(&media);
+            player.set_fullscreen(true);
+            if player.play().is_err() {
+                tracing::warn!("Video Player could not show splash.");
+            }
+            geometry.apply_to_player(player);
+        }
+    }
+
+    /// Single item is repeated by VLC itself, otherwise items are switched on end.
+    fn play_item(vlc_instance: &vlc::Instance, player: &vlc::MediaPlayer, item: &PlaylistItem, single: bool, looping: bool, config: &VideoPlayerConfig) -> bool {
+        tracing::info!("VLC playing {:?}", item.path);
+        let media = match item.kind {
+            MediaKind::Stream => vlc::Media::new_location(vlc_instance, &item.path.to_string_lossy()),
+            _ => vlc::Media::new_path(vlc_instance, &item.path),
+        };
+        let Some(media) = media else {
+            tracing::warn!("Media {:?} not found by VLC", item.path);
+            return false;
+        };
+
+        match item.kind {
+            MediaKind::Video if single && looping => media.add_option(":input-repeat=65535"),
+            MediaKind::Video => {},
+            MediaKind::Image if single => media.add_option(":image-duration=-1"),
+            MediaKind::Image => {
+                let duration_secs = item.duration.map_or(config.image_duration_secs, |duration| duration.as_secs_f32());
+                media.add_option(&format!(":image-duration={duration_secs:.3}"));
+            },
+            MediaKind::Stream => {
+                media.add_option(&format!(":network-caching={}", config.stream.network_caching_ms));
+                // Input is ended after display time, otherwise stream is shown until skipped
+                if let Some(duration) = item.duration {
+                    media.add_option(&format!(":run-time={:.3}", duration.as_secs_f32()));
+                }
+            },
+        }
+
+        media.add_option(":no-audio");
+        media.add_option(":fullscreen");
+
+        let geometry = config.geometry.overridden_by(item.geometry.as_ref());
+        geometry.apply_to_media(&media);
+
+        player.set_media(&media);
+        player.set_fullscreen(true);
+
+        let is_playing = player.play().is_ok();
+        if !is_playing {
+            tracing::warn!("Video Player could not play {:?}.", item.path);
+        }
+        geometry.apply_to_player(player);
+        is_playing
+    }
+
+    fn apply_logo_overlay(player: &vlc::MediaPlayer, logo: &LogoOverlay) {
+        player.set_logo_string(LogoOption::File, &logo.to_vlc_file_list());
+        player.set_logo_int(LogoOption::Position, logo.position.to_vlc_flags());
+        player.set_logo_int(LogoOption::X, logo.x);
+        player.set_logo_int(LogoOption::Y, logo.y);
+        player.set_logo_int(LogoOption::Opacity, logo.opacity as i32);
+        player.set_logo_int(LogoOption::Delay, logo.delay_ms.min(i32::MAX as u32) as i32);
+        player.set_logo_int(LogoOption::Repeat, logo.repeat);
+        player.set_logo_int(LogoOption::Enable, 1);
+    }
+
+    /// Filter is kept enabled if 'keep_enabled', re-enabling it can flash screen.
+    fn apply_adjustment(player: &vlc::MediaPlayer, adjustment: &VideoAdjustment, keep_enabled: bool) {
+        let adjustment = adjustment.clamped();
+        if adjustment == VideoAdjustment::default() && !keep_enabled {
+            player.set_adjust_int(VideoAdjustOption::Enable, 0);
+            return;
+        }
+        player.set_adjust_float(VideoAdjustOption::Brightness, adjustment.brightness);
+        player.set_adjust_float(VideoAdjustOption::Contrast, adjustment.contrast);
+        player.set_adjust_float(VideoAdjustOption::Saturation, adjustment.saturation);
+        player.set_adjust_float(VideoAdjustOption::Gamma, adjustment.gamma);
+        player.set_adjust_float(VideoAdjustOption::Hue, adjustment.hue);
+        player.set_adjust_int(VideoAdjustOption::Enable, 1);
+    }
+
+    fn apply_text_overlay(player: &vlc::MediaPlayer, overlay: &TextOverlay) {
+        // Null bytes cannot be passed to VLC
+        let text = overlay.text.replace('\0', "");
+        player.set_marquee_string(MarqueeOption::Text, &text);
+        player.set_marquee_int(MarqueeOption::Position, overlay.position.to_vlc_flags());
+        player.set_marquee_int(MarqueeOption::X, overlay.x);
+        player.set_marquee_int(MarqueeOption::Y, overlay.y);
+        player.set_marquee_int(MarqueeOption::Color, (overlay.color & 0xFFFFFF) as i32);
+        player.set_marquee_int(MarqueeOption::Size, overlay.size.min(i32::MAX as u32) as i32);
+        player.set_marquee_int(MarqueeOption::Opacity, overlay.opacity as i32);
+        player.set_marquee_int(MarqueeOption::Timeout, overlay.timeout_ms.min(i32::MAX as u32) as i32);
+        player.set_marquee_int(MarqueeOption::Enable, 1);
+    }
+
+    /// Index of item after 'current_index', None when playlist is finished.
+    fn next_index(playlist: &Playlist, current_index: usize, looping: bool) -> Option<usize> {
+        let next_index = current_index + 1;
+        if next_index < playlist.items.len() {
+            Some(next_index)
+        } else if looping && !playlist.is_empty() {
+            Some(0)
+        } else {
+            None
+        }
+    }
+
+    fn item_path(playlist: &Playlist, index: Option<usize>) -> Option<PathBuf> {
+        index.and_then(|index| playlist.items.get(index)).map(|item| item.path.clone())
+    }
+
+    /// Remaining time of current media, if known.
+    fn remaining_time(player: &vlc::MediaPlayer) -> Option<Duration> {
+        let length = player.get_length()?;
+        let time = player.get_time()?;
+        Some(Duration::from_millis(length.saturating_sub(time).max(0) as u64))
+    }
+
+    fn attach_events(player: &vlc::MediaPlayer, player_index: usize, player_ctrl_tx: &std::sync::mpsc::Sender<VideoPlayerCommand>) {
+        // libvlc must not be called from its callbacks, work is passed to run loop
+        let events = [
+            (EventType::MediaPlayerEndReached, VideoPlayerCommand::ItemEnded as fn(usize) -> VideoPlayerCommand),
+            (EventType::MediaPlayerOpening, VideoPlayerCommand::ItemOpening),
+            (EventType::MediaPlayerPlaying, VideoPlayerCommand::ItemPlaying),
+            (EventType::MediaPlayerPaused, VideoPlayerCommand::ItemPaused),
+            (EventType::MediaPlayerTimeChanged, VideoPlayerCommand::TimeChanged),
+            (EventType::MediaPlayerEncounteredError, VideoPlayerCommand::EncounteredError),
+        ];
+        for (event_type, command) in events {
+            let event_tx = player_ctrl_tx.clone();
+            let attach_result = player.event_manager().attach(event_type, move |_, _| {
+                let _ = event_tx.send(command(player_index));
+            });
+            if attach_result.is_err() {
+                tracing::warn!("Video Player could not observe {event_type:?}, playlists may stop after first item.");
+            }
+        }
+    }
+
+    fn create_instance(players_count: usize, event_tx: &std::sync::mpsc::Sender<VideoPlayerCommand>) -> Option<(vlc::Instance, Vec<vlc::MediaPlayer>)> {
+        let vlc_instance = vlc::Instance::with_args(&[
+            "--aout=dummy", 
+            "--fullscreen", 
+             
+            "--no-video-title-show",
+            "--avcodec-hw=none",
+        ])?;
+
+        let players = (0..players_count)
+            .map(|player_index| {
+                let player = vlc::MediaPlayer::new(&vlc_instance)?;
+                player.set_mute(true);
+                Self::attach_events(&player, player_index, event_tx);
+                Some(player)
+            })
+            .collect::<Option<Vec<_>>>()?;
+        Some((vlc_instance, players))
+    }
+
+    pub async fn run(looping: bool, config: VideoPlayerConfig) -> Self {
         let (player_ctrl_tx, player_ctrl_rx) = std::sync::mpsc::channel();
-        
+        let event_tx = player_ctrl_tx.clone();
+        let watchdog_status = Arc::new(Mutex::new(WatchdogStatus::default()));
+        let watchdog_status_shared = watchdog_status.clone();
+        let status = Arc::new(Mutex::new(PlayerStatus::default()));
+        let status_shared = status.clone();
+        let (events_tx, _) = tokio::sync::broadcast::channel(PLAYER_EVENTS_CAP);
+        let events_tx_shared = events_tx.clone();
+
         let _video_player_task = tokio::task::spawn_blocking(move || {
-            let vlc_instance = vlc::Instance::with_args(&[
-                "--aout=dummy", 
-                "--fullscreen", 
-                 
-                "--no-video-title-show",
-                "--avcodec-hw=none",
-            ]).expect("Failed to create VLC instance");
+            // Second player starts next item while previous one is still shown
+            let players_count = if config.transition.kind == TransitionKind::Gapless { 2 } else { 1 };
+            let (mut vlc_instance, mut players) = Self::create_instance(players_count, &event_tx)
+                .expect("Failed to create VLC instance");
+
+            let watchdog = config.watchdog;
+            let mut last_progress = Instant::now();
+            let mut last_recovery = Instant::now();
+            let mut failed_recoveries = 0;
+            let mut text_overlay: Option<TextOverlay> = None;
+            let mut logo: Option<LogoOverlay> = None;
+            let mut last_time_ms: Option<i64> = None;
+            let mut last_position_event = Instant::now();
+            let publish = |event: PlayerEvent| {
+                tracing::debug!("Player event: {event}");
+                status_shared.lock().unwrap_or_else(|poisoned| poisoned.into_inner()).update(&event);
+                // No receivers is not an error
+                let _ = events_tx_shared.send(event);
+            };
 
-            let player = vlc::MediaPlayer::new(&vlc_instance).expect("Failed to create MediaPlayer");
-            player.set_mute(true);
+            let transition = config.transition;
+            let fade_enabled = transition.kind == TransitionKind::FadeToBlack;
+            let mut active = 0;
+            let mut splash: Option<SplashInfo> = None;
+            let mut media_loaded = false;
+            let mut playlist = Playlist::default();
+            let mut current_index = 0;
+            let mut adjustment = VideoAdjustment::default();
+            let mut fade: Option<Fade> = None;
+            // Current item was faded out and stays black until it ends
+            let mut faded_out = false;
+            // Gapless: next item index started on inactive player
+            let mut pending_index: Option<usize> = None;
+            // Preloaded item which failed, it is skipped when current one ends
+            let mut skipped_index: Option<usize> = None;
+            let mut queued: Option<Playlist> = None;
+            let mut reconnect = StreamReconnect::default();
+            let set_queued_status = |queued: &Option<Playlist>| {
+                status_shared.lock().unwrap_or_else(|poisoned| poisoned.into_inner()).queued = queued.clone();
+            };
 
             loop {
-                match player_ctrl_rx.recv()  {
-                    Ok(VideoPlayerCommand::Play(path_buf)) => {
-                        tracing::info!("VLC playing {path_buf:?}");
-                        if let Some(media) = vlc::Media::new_path(&vlc_instance, &path_buf) {
-                            if looping {
-                                media.add_option(":input-repeat=65535");
+                let tick_interval = if fade.is_some() {
+                    Some(FADE_STEP)
+                } else if let Some(reconnect_at) = reconnect.at {
+                    Some(reconnect_at.saturating_duration_since(Instant::now()))
+                } else if media_loaded && watchdog.enabled {
+                    Some(WATCHDOG_CHECK_INTERVAL)
+                } else {
+                    None
+                };
+                let command = match tick_interval {
+                    Some(tick_interval) => match player_ctrl_rx.recv_timeout(tick_interval) {
+                        Err(RecvTimeoutError::Timeout) => Some(VideoPlayerCommand::Tick),
+                        result => result.ok(),
+                    },
+                    None => player_ctrl_rx.recv().ok(),
+                };
+
+                let mut recovery_reason: Option<String> = None;
+                match command {
+                    Some(VideoPlayerCommand::Tick) => {
+                        if let Some(current_fade) = fade {
+                            let (factor, finished) = current_fade.progress();
+                            Self::apply_adjustment(&players[active], &adjustment.faded(factor), true);
+                            // Faded out screen stays black until next item starts
+                            if finished {
+                                fade = None;
                             }
-                            
-                            media.add_option(":no-audio");
-                            media.add_option(":fullscreen");
+                        }
 
-                            player.set_media(&media);
-                            player.set_fullscreen(true);
+                        if reconnect.is_due() {
+                            reconnect.at = None;
+                            last_progress = Instant::now();
+                            last_time_ms = None;
+                            if let Some(item) = playlist.items.get(current_index)
+                                && !Self::play_item(&vlc_instance, &players[active], item, playlist.items.len() == 1, looping, &config)
+                                && let Some(event) = reconnect.schedule(&players[active], &config.stream, &playlist, current_index, last_time_ms, &event_tx)
+                            {
+                                publish(event);
+                            }
+                            continue;
+                        }
 
-                            if player.play().is_err() {
-                                tracing::warn!("Video Player could not play {path_buf:?}.");
+                        // Still images do not advance time
+                        let current_kind = playlist.items.get(current_index).map(|item| item.kind);
+                        let is_stream = current_kind == Some(MediaKind::Stream);
+                        let advances_time = current_kind == Some(MediaKind::Video) || is_stream;
+                        if media_loaded && watchdog.enabled && advances_time && reconnect.at.is_none() {
+                            match players[active].state() {
+                                State::Error => recovery_reason = Some("VLC player in error state".to_string()),
+                                State::Opening | State::Buffering | State::Playing if last_progress.elapsed() > watchdog.stall_timeout() => {
+                                    recovery_reason = Some(format!("playback stalled for {:?}", last_progress.elapsed()));
+                                },
+                                _ => {},
                             }
-                        } else {
-                            tracing::warn!("Video {path_buf:?} not found by VLC");
+                            if let Some(reason) = &recovery_reason {
+                                publish(PlayerEvent::Error { path: Self::item_path(&playlist, Some(current_index)), reason: reason.clone() });
+                            }
+                            // Stream is opened again without counting as watchdog recovery
+                            if recovery_reason.is_some() && is_stream {
+                                recovery_reason = None;
+                                if let Some(event) = reconnect.schedule(&players[active], &config.stream, &playlist, current_index, last_time_ms, &event_tx) {
+                                    publish(event);
+                                }
+                            }
+                        }
+                    },
+                    Some(VideoPlayerCommand::EncounteredError(player_index)) => {
+                        if !media_loaded {
+                            continue;
+                        }
+                        let item_index = if player_index == active { Some(current_index) } else { pending_index };
+                        let reason = format!("VLC player {player_index} encountered error");
+                        publish(PlayerEvent::Error { path: Self::item_path(&playlist, item_index), reason: reason.clone() });
+                        let is_stream = playlist.items.get(current_index).is_some_and(|item| item.kind == MediaKind::Stream);
+                        if player_index == active && is_stream && reconnect.at.is_none() {
+                            if let Some(event) = reconnect.schedule(&players[active], &config.stream, &playlist, current_index, last_time_ms, &event_tx) {
+                                publish(event);
+                            }
+                            continue;
+                        }
+                        // Current item keeps playing, failed one is not preloaded again
+                        if player_index != active {
+                            if let Some(failed_index) = pending_index.take() {
+                                players[player_index].stop();
+                                skipped_index = Some(failed_index);
+                                // Current item already ended and waits for failed one
+                                if players[active].state() == State::Ended {
+                                    let _ = event_tx.send(VideoPlayerCommand::ItemEnded(active));
+                                }
+                            }
+                            continue;
+                        }
+                        if watchdog.enabled {
+                            recovery_reason = Some(reason);
+                        }
+                    },
+                    Some(VideoPlayerCommand::ItemOpening(player_index)) => {
+                        if media_loaded {
+                            let item_index = if player_index == active { Some(current_index) } else { pending_index };
+                            publish(PlayerEvent::Opened { path: Self::item_path(&playlist, item_index) });
+                        }
+                    },
+                    Some(VideoPlayerCommand::ItemPaused(player_index)) => {
+                        if media_loaded && player_index == active {
+                            publish(PlayerEvent::Paused { path: Self::item_path(&playlist, Some(current_index)) });
+                        }
+                    },
+                    Some(VideoPlayerCommand::Play(new_playlist)) => {
+                        for player in players.iter().filter(|player| player.state() != State::Stopped) {
+                            player.stop();
+                        }
+                        active = 0;
+                        pending_index = None;
+                        skipped_index = None;
+                        playlist = new_playlist;
+                        current_index = 0;
+                        queued = None;
+                        reconnect = StreamReconnect::default();
+                        {
+                            let mut status = status_shared.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+                            status.playlist = playlist.clone();
+                            status.queued = None;
+                        }
+                        last_progress = Instant::now();
+                        last_time_ms = None;
+                        failed_recoveries = 0;
+
+                        fade = None;
+                        faded_out = false;
+                        if fade_enabled {
+                            // First item is faded in as well
+                            let factor = if playlist.items.len() > 1 { 0.0 } else { 1.0 };
+                            Self::apply_adjustment(&players[active], &adjustment.faded(factor), true);
+                        }
+
+                        media_loaded = playlist.items.first().is_some_and(|item| {
+                            Self::play_item(&vlc_instance, &players[active], item, playlist.items.len() == 1, looping, &config)
+                        });
+
+                        if let (false, Some(info)) = (media_loaded, &splash) {
+                            Self::show_splash(&vlc_instance, &players[active], info, &config.geometry);
                         }
                     },
-                    Ok(VideoPlayerCommand::Stop(stop_feedback_tx)) => {
+                    Some(VideoPlayerCommand::TimeChanged(player_index)) => {
+                        if player_index == active {
+                            last_progress = Instant::now();
+                            if failed_recoveries > 0 && last_recovery.elapsed() > WATCHDOG_RECOVERED_AFTER {
+                                tracing::info!("Watchdog: playback recovered.");
+                                failed_recoveries = 0;
+                            }
+                            reconnect.on_progress();
+
+                            if media_loaded && let Some(time_ms) = players[active].get_time() {
+                                let path = Self::item_path(&playlist, Some(current_index));
+                                // Single item is repeated by VLC without end event
+                                if last_time_ms.is_some_and(|last_time_ms| time_ms + LOOP_DETECTION_THRESHOLD_MS < last_time_ms) {
+                                    publish(PlayerEvent::Looped { path: path.clone() });
+                                    if let Some(next_playlist) = queued.take() {
+                                        tracing::info!("Loop ended, playing queued playlist.");
+                                        let _ = event_tx.send(VideoPlayerCommand::Play(next_playlist));
+                                        continue;
+                                    }
+                                }
+                                last_time_ms = Some(time_ms);
+
+                                if last_position_event.elapsed() >= POSITION_TICK_INTERVAL {
+                                    last_position_event = Instant::now();
+                                    publish(PlayerEvent::Position { path, time_ms, length_ms: players[active].get_length() });
+                                }
+                            }
+                        }
+                        // Length of live stream is unknown
+                        let is_stream = playlist.items.get(current_index).is_some_and(|item| item.kind == MediaKind::Stream);
+                        if !media_loaded || player_index != active || playlist.items.len() < 2 || pending_index.is_some() || skipped_index.is_some() || is_stream {
+                            continue;
+                        }
+                        let Some(remaining) = Self::remaining_time(&players[active]) else {
+                            continue;
+                        };
+                        if remaining > transition.duration() {
+                            continue;
+                        }
+
+                        match transition.kind {
+                            TransitionKind::Cut => {},
+                            TransitionKind::Gapless => {
+                                let Some(next_index) = Self::next_index(&playlist, current_index, looping) else {
+                                    continue;
+                                };
+                                // Queued playlist is started on end instead
+                                if next_index == 0 && queued.is_some() {
+                                    continue;
+                                }
+                                let next_player = &players[(active + 1) % players.len()];
+                                tracing::debug!("Preloading playlist item {next_index}.");
+                                if Self::play_item(&vlc_instance, next_player, &playlist.items[next_index], false, looping, &config) {
+                                    pending_index = Some(next_index);
+                                }
+                            },
+                            TransitionKind::FadeToBlack => {
+                                if !faded_out {
+                                    faded_out = true;
+                                    fade = Some(Fade::new(FadeDirection::Out, remaining));
+                                }
+                            },
+                        }
+                    },
+                    Some(VideoPlayerCommand::ItemPlaying(player_index)) => {
+                        if media_loaded {
+                            let item_index = if player_index == active { Some(current_index) } else { pending_index };
+                            publish(PlayerEvent::Playing { path: Self::item_path(&playlist, item_index) });
+                        }
+
+                        if let Some(next_index) = pending_index && player_index != active {
+                            // New item covers previous one, it can be stopped now
+                            players[active].stop();
+                            active = player_index;
+                            if next_index == 0 {
+                                publish(PlayerEvent::Looped { path: Self::item_path(&playlist, Some(next_index)) });
+                            }
+                            current_index = next_index;
+                            pending_index = None;
+                            reconnect = StreamReconnect::default();
+                            last_progress = Instant::now();
+                            last_time_ms = None;
+                            tracing::debug!("Switched to playlist item {current_index}.");
+                        } else if fade_enabled && player_index == active && playlist.items.len() > 1 {
+                            fade = Some(Fade::new(FadeDirection::In, transition.duration()));
+                        }
+                    },
+                    Some(VideoPlayerCommand::ItemEnded(player_index)) => {
+                        // Event could be queued before media was changed
+                        if !media_loaded || player_index != active || players[active].state() != State::Ended {
+                            continue;
+                        }
+                        publish(PlayerEvent::Ended { path: Self::item_path(&playlist, Some(current_index)) });
+                        // Next item is already starting on other player, it switches on 'ItemPlaying'
+                        let mut preload_failed = false;
+                        if let Some(next_index) = pending_index {
+                            let pending_player = &players[(active + 1) % players.len()];
+                            if pending_player.state() == State::Playing {
+                                continue;
+                            }
+                            // Preloaded item is stuck opening or failed, it is cut to on active player instead
+                            tracing::warn!("Preloaded playlist item {next_index} not playing ({:?}), cutting to it.", pending_player.state());
+                            pending_player.stop();
+                            pending_index = None;
+                            preload_failed = true;
+                        }
+
+                        // Stream ended before its display time, e.g. camera or network dropped
+                        if let Some(item) = playlist.items.get(current_index)
+                            && item.kind == MediaKind::Stream
+                            && item.duration.is_none_or(|duration| {
+                                last_time_ms.unwrap_or_default() + STREAM_END_TOLERANCE_MS < duration.as_millis() as i64
+                            })
+                        {
+                            if let Some(event) = reconnect.schedule(&players[active], &config.stream, &playlist, current_index, last_time_ms, &event_tx) {
+                                publish(event);
+                            }
+                            continue;
+                        }
+
+                        let mut next_index = Self::next_index(&playlist, current_index, looping);
+                        if let Some(failed_index) = skipped_index.take()
+                            && next_index == Some(failed_index)
+                        {
+                            tracing::warn!("Skipping playlist item {failed_index}, it failed to preload.");
+                            next_index = Self::next_index(&playlist, failed_index, looping);
+                        }
+
+                        let loop_ended = next_index.is_none_or(|next_index| next_index == 0);
+                        if loop_ended && let Some(next_playlist) = queued.take() {
+                            tracing::info!("Loop ended, playing queued playlist.");
+                            let _ = event_tx.send(VideoPlayerCommand::Play(next_playlist));
+                            continue;
+                        }
+
+                        let Some(next_index) = next_index else {
+                            tracing::info!("Playlist finished.");
+                            media_loaded = false;
+                            fade = None;
+                            faded_out = false;
+                            if fade_enabled {
+                                Self::apply_adjustment(&players[active], &adjustment, true);
+                            }
+                            if let Some(info) = &splash {
+                                Self::show_splash(&vlc_instance, &players[active], info, &config.geometry);
+                            }
+                            continue;
+                        };
+
+                        let item = &playlist.items[next_index];
+                        if transition.kind == TransitionKind::Gapless && !preload_failed {
+                            let next_player = &players[(active + 1) % players.len()];
+                            if Self::play_item(&vlc_instance, next_player, item, playlist.items.len() == 1, looping, &config) {
+                                pending_index = Some(next_index);
+                                continue;
+                            }
+                        }
+
+                        if fade_enabled {
+                            fade = None;
+                            faded_out = false;
+                            Self::apply_adjustment(&players[active], &adjustment.faded(0.0), true);
+                        }
+                        if next_index == 0 {
+                            publish(PlayerEvent::Looped { path: Self::item_path(&playlist, Some(next_index)) });
+                        }
+                        current_index = next_index;
+                        reconnect = StreamReconnect::default();
+                        last_progress = Instant::now();
+                        last_time_ms = None;
+                        media_loaded = Self::play_item(&vlc_instance, &players[active], item, playlist.items.len() == 1, looping, &config);
+                        if let (false, Some(info)) = (media_loaded, &splash) {
+                            Self::show_splash(&vlc_instance, &players[active], info, &config.geometry);
+                        }
+                    },
+                    Some(VideoPlayerCommand::Stop(stop_feedback_tx)) => {
                         tracing::info!("VLC stopping playback");
-                        player.stop();
-                        tracing::debug!("VLC state after stop: {:?}", player.state());
+                        for player in &players {
+                            player.stop();
+                        }
+                        tracing::debug!("VLC state after stop: {:?}", players[active].state());
 
                         if stop_feedback_tx.send(()).is_err() {
                             tracing::warn!("Video Player stop failed send feedback.");
                         }
+                        if media_loaded {
+                            publish(PlayerEvent::Stopped);
+                        }
+
+                        media_loaded = false;
+                        pending_index = None;
+                        skipped_index = None;
+                        queued = None;
+                        reconnect = StreamReconnect::default();
+                        set_queued_status(&queued);
+                        fade = None;
+                        faded_out = false;
+                        if fade_enabled {
+                            Self::apply_adjustment(&players[active], &adjustment, true);
+                        }
+                        if let Some(info) = &splash {
+                            Self::show_splash(&vlc_instance, &players[active], info, &config.geometry);
+                        }
+                    },
+                    Some(VideoPlayerCommand::Queue(next_playlist)) => {
+                        // Single image or stream without display time is shown forever, it has no loop to wait for
+                        let is_endless = playlist.items.len() == 1 && match playlist.items[0].kind {
+                            MediaKind::Image => true,
+                            MediaKind::Stream => playlist.items[0].duration.is_none(),
+                            MediaKind::Video => false,
+                        };
+                        if !media_loaded || is_endless {
+                            let _ = event_tx.send(VideoPlayerCommand::Play(next_playlist));
+                        } else {
+                            queued = Some(next_playlist);
+                            set_queued_status(&queued);
+                        }
+                    },
+                    Some(VideoPlayerCommand::Pause) => {
+                        if media_loaded {
+                            players[active].set_pause(true);
+                        }
+                    },
+                    Some(VideoPlayerCommand::Resume) => {
+                        if media_loaded {
+                            players[active].set_pause(false);
+                        }
+                    },
+                    Some(VideoPlayerCommand::Skip(offset)) => {
+                        if playlist.is_empty() {
+                            continue;
+                        }
+                        for player in players.iter().filter(|player| player.state() != State::Stopped) {
+                            player.stop();
+                        }
+                        let len = playlist.items.len() as isize;
+                        current_index = (current_index as isize + offset).rem_euclid(len) as usize;
+                        tracing::info!("Skipping to playlist item {current_index}.");
+                        pending_index = None;
+                        skipped_index = None;
+                        reconnect = StreamReconnect::default();
+                        last_progress = Instant::now();
+                        last_time_ms = None;
+                        fade = None;
+                        faded_out = false;
+                        if fade_enabled {
+                            Self::apply_adjustment(&players[active], &adjustment, true);
+                        }
+
+                        let item = &playlist.items[current_index];
+                        media_loaded = Self::play_item(&vlc_instance, &players[active], item, playlist.items.len() == 1, looping, &config);
+                        if let (false, Some(info)) = (media_loaded, &splash) {
+                            Self::show_splash(&vlc_instance, &players[active], info, &config.geometry);
+                        }
+                    },
+                    Some(VideoPlayerCommand::Position(position_tx)) => {
+                        let player = &players[active];
+                        let position = playlist.items.get(current_index)
+                            .filter(|_| media_loaded)
+                            .and_then(|item| Some(PlaybackPosition {
+                                path: item.path.clone(),
+                                time_ms: player.get_time()?,
+                                length_ms: player.get_length(),
+                                playing: player.state() == State::Playing,
+                            }));
+                        let _ = position_tx.send(position);
                     },
-                    Err(_) => {
+                    Some(VideoPlayerCommand::SetTime(time_ms)) => {
+                        if media_loaded && pending_index.is_none() {
+                            // Seek back is not a loop
+                            last_time_ms = None;
+                            players[active].set_time(time_ms);
+                        }
+                    },
+                    Some(VideoPlayerCommand::SetRate(rate)) => {
+                        if media_loaded && players[active].set_rate(rate).is_err() {
+                            tracing::warn!("Video Player could not set rate {rate}.");
+                        }
+                    },
+                    Some(VideoPlayerCommand::ShowText(overlay)) => {
+                        for player in &players {
+                            Self::apply_text_overlay(player, &overlay);
+                        }
+                        text_overlay = Some(overlay);
+                    },
+                    Some(VideoPlayerCommand::HideText) => {
+                        tracing::info!("Hide text overlay");
+                        for player in &players {
+                            player.set_marquee_int(MarqueeOption::Enable, 0);
+                        }
+                        text_overlay = None;
+                    },
+                    Some(VideoPlayerCommand::ShowLogo(new_logo)) => {
+                        for player in &players {
+                            Self::apply_logo_overlay(player, &new_logo);
+                        }
+                        logo = Some(new_logo);
+                    },
+                    Some(VideoPlayerCommand::HideLogo) => {
+                        tracing::info!("Hide logo overlay");
+                        for player in &players {
+                            player.set_logo_int(LogoOption::Enable, 0);
+                        }
+                        logo = None;
+                    },
+                    Some(VideoPlayerCommand::SetAdjustment(new_adjustment)) => {
+                        adjustment = new_adjustment;
+                        for player in &players {
+                            Self::apply_adjustment(player, &adjustment, fade_enabled);
+                        }
+                    },
+                    Some(VideoPlayerCommand::SetSplash(info)) => {
+                        if !media_loaded && splash.as_ref() != Some(&info) {
+                            Self::show_splash(&vlc_instance, &players[active], &info, &config.geometry);
+                        }
+                        splash = Some(info);
+                    },
+                    Some(VideoPlayerCommand::Shutdown) | None => {
                         tracing::info!("Video Player shutting down.");
+                        for player in &players {
+                            player.stop();
+                        }
                         break;
                     }
                 }
+
+                let Some(reason) = recovery_reason else {
+                    continue;
+                };
+
+                failed_recoveries += 1;
+                last_recovery = Instant::now();
+                let restart_instance = failed_recoveries > watchdog.reloads_before_restart;
+                tracing::warn!("Watchdog: {reason}, recovery attempt {failed_recoveries}, restart instance = {restart_instance}");
+                {
+                    let mut status = watchdog_status_shared.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+                    if restart_instance {
+                        status.instance_restarts += 1;
+                    } else {
+                        status.media_reloads += 1;
+                    }
+                    status.last_recovery_reason = Some(reason);
+                    status.last_recovery_at = Some(chrono::Local::now().format("%Y-%m-%d %H:%M:%S").to_string());
+                }
+
+                for player in &players {
+                    player.stop();
+                }
+                pending_index = None;
+                reconnect = StreamReconnect::default();
+                fade = None;
+                faded_out = false;
+                active = 0;
+
+                if restart_instance {
+                    failed_recoveries = 0;
+                    match Self::create_instance(players_count, &event_tx) {
+                        Some((new_instance, new_players)) => {
+                            tracing::warn!("Watchdog: VLC instance recreated.");
+                            players = new_players;
+                            vlc_instance = new_instance;
+                            for player in &players {
+                                if let Some(overlay) = &text_overlay {
+                                    Self::apply_text_overlay(player, overlay);
+                                }
+                                if let Some(logo) = &logo {
+                                    Self::apply_logo_overlay(player, logo);
+                                }
+                                Self::apply_adjustment(player, &adjustment, fade_enabled);
+                            }
+                        },
+                        None => tracing::error!("Watchdog: could not recreate VLC instance, reloading media."),
+                    }
+                } else if fade_enabled {
+                    Self::apply_adjustment(&players[active], &adjustment, true);
+                }
+
+                last_progress = Instant::now();
+                last_time_ms = None;
+                media_loaded = playlist.items.get(current_index).is_some_and(|item| {
+                    Self::play_item(&vlc_instance, &players[active], item, playlist.items.len() == 1, looping, &config)
+                });
+                if let (false, Some(info)) = (media_loaded, &splash) {
+                    Self::show_splash(&vlc_instance, &players[active], info, &config.geometry);
+                }
             }
         });
         
-        Self { _video_player_task, player_ctrl_tx }
+        Self { _video_player_task, player_ctrl_tx, watchdog_status, status, events_tx }
     }
 }
 
 
 
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_display_geometry_override() {
+        let device: DisplayGeometry = serde_json::from_str(r#"{"rotation": 90, "aspect_ratio": "16:9"}"#).unwrap();
+        assert_eq!(device.rotation, Some(Rotation::Rotate90));
+        assert!(serde_json::from_str::<DisplayGeometry>(r#"{"rotation": 45}"#).is_err());
+
+        let item = DisplayGeometry { rotation: Some(Rotation::None), crop: Some("4:3".to_string()), ..Default::default() };
+        let merged = device.overridden_by(Some(&item));
+        assert_eq!(merged.rotation, Some(Rotation::None));
+        assert_eq!(merged.aspect_ratio.as_deref(), Some("16:9"));
+        assert_eq!(merged.crop.as_deref(), Some("4:3"));
+        assert_eq!(device.overridden_by(None), device);
+    }
+
+    #[test]
+    fn test_player_event_json() {
+        let event = PlayerEvent::Position { path: Some(PathBuf::from("/tmp/a.mp4")), time_ms: 1500, length_ms: Some(3000) };
+        let json = serde_json::to_value(&event).unwrap();
+        assert_eq!(json, serde_json::json!({"event": "position", "path": "/tmp/a.mp4", "time_ms": 1500, "length_ms": 3000}));
+        assert_eq!(serde_json::to_value(PlayerEvent::Stopped).unwrap(), serde_json::json!({"event": "stopped"}));
+    }
+
+    #[test]
+    fn test_playlist_next_index() {
+        let item = PlaylistItem::new(PathBuf::from("a.png"), MediaKind::Image);
+        let playlist = Playlist { items: vec![item.clone(), item] };
+        assert_eq!(VideoPlayer::next_index(&playlist, 0, false), Some(1));
+        assert_eq!(VideoPlayer::next_index(&playlist, 1, true), Some(0));
+        assert_eq!(VideoPlayer::next_index(&playlist, 1, false), None);
+        assert_eq!(VideoPlayer::next_index(&Playlist::default(), 0, true), None);
+
+        let fade = Fade { direction: FadeDirection::Out, started: Instant::now() - Duration::from_secs(1), duration: Duration::from_millis(500) };
+        assert_eq!(fade.progress(), (0.0, true));
+    }
+
+    #[test]
+    fn test_stream_item_and_reconnect_delay() {
+        let item = PlaylistItem::stream("rtsp://camera.local:554/live").unwrap();
+        assert_eq!((item.kind, item.path.to_string_lossy().as_ref()), (MediaKind::Stream, "rtsp://camera.local:554/live"));
+        assert!(PlaylistItem::stream("udp://@239.0.0.1:1234").is_ok());
+        assert!(PlaylistItem::stream("file:///etc/passwd").is_err());
+        assert!(PlaylistItem::stream("http://").is_err());
+        assert!(PlaylistItem::stream("http://camera.local/live\n:sout=#file").is_err());
+
+        let config = StreamConfig { reconnect_delay_ms: 1000, max_reconnect_delay_ms: 5000, ..Default::default() };
+        assert_eq!(config.reconnect_delay(0), Duration::from_secs(1));
+        assert_eq!(config.reconnect_delay(2), Duration::from_secs(4));
+        assert_eq!(config.reconnect_delay(40), Duration::from_secs(5));
+
+        // Single stream is retried forever, playlist moves on
+        let mut reconnect = StreamReconnect { attempts: config.max_reconnect_attempts, ..Default::default() };
+        assert!(!reconnect.moves_on(&config, &item, 1, None));
+        assert!(reconnect.moves_on(&config, &item, 2, None));
+        let mut timed_item = item.clone();
+        timed_item.duration = Some(Duration::from_secs(60));
+        let mut reconnect = StreamReconnect::default();
+        assert!(!reconnect.moves_on(&config, &timed_item, 2, Some(10_000)));
+        let mut reconnect = StreamReconnect::default();
+        assert!(reconnect.moves_on(&config, &timed_item, 2, Some(61_000)));
+    }
+}
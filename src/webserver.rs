@@ -1,12 +1,60 @@
-use std::{net::SocketAddr, sync::Arc};
+use std::{convert::Infallible, net::SocketAddr, path::{Path, PathBuf}, sync::Arc};
 
-use axum::{extract::{self, DefaultBodyLimit}, http::StatusCode, response::{Html, IntoResponse}, routing::{get, post}, Router};
+use axum::{extract::{self, DefaultBodyLimit}, http::{header, HeaderMap, StatusCode}, response::{sse, Html, IntoResponse, Response, Sse}, routing::{delete, get, head, post}, Json, Router};
+use base64::Engine;
+use tokio_stream::{wrappers::BroadcastStream, StreamExt};
 use tower_http::trace::{DefaultMakeSpan, DefaultOnRequest, DefaultOnResponse, TraceLayer};
 
-use crate::{FilesManagerSink, FilesSource, FilesSourceHandler, FilesSourceType};
+use crate::{config::{ConfigPatch, ConfigStore}, connectivity_monitor::ConnectivityState, events::{EventBus, SystemEvent, UploadProgress}, file_manager::{FilesManager, PendingActivation, UploadActivation}, log_buffer::LogBuffer, media_probe::MediaConfig, playlist::PlaylistCfg, resumable_upload::{UploadError, UploadStore}, scheduler::ScheduleStatus, sync::SyncStatus, url_fetcher::{FetchRequest, UrlFetchError, UrlFetcher}, video_player::{LogoOverlay, TextOverlay, VideoAdjustment, VideoPlayer}, FilesManagerSink, FilesSource, FilesSourceHandler, FilesSourceType, UploadedContent, UploadedFile, WiFiCredentialsProcedure};
+
+/// Dashboard is compiled into binary, so it works without internet.
+const DASHBOARD_HTML: &str = include_str!("dashboard/index.html");
+const DASHBOARD_JS: &str = include_str!("dashboard/app.js");
+const DASHBOARD_CSS: &str = include_str!("dashboard/style.css");
+
+/// Resumable upload protocol, see https://tus.io/protocols/resumable-upload.
+const TUS_VERSION: &str = "1.0.0";
+const TUS_RESUMABLE: &str = "tus-resumable";
+const TUS_VERSION_HEADER: &str = "tus-version";
+const TUS_EXTENSION: &str = "tus-extension";
+const TUS_MAX_SIZE: &str = "tus-max-size";
+const UPLOAD_OFFSET: &str = "upload-offset";
+const UPLOAD_LENGTH: &str = "upload-length";
+const UPLOAD_METADATA: &str = "upload-metadata";
+const TUS_CONTENT_TYPE: &str = "application/offset+octet-stream";
+
+/// Query of '/upload', see 'UploadActivation::parse'.
+#[derive(Debug, Default, serde::Deserialize)]
+struct UploadParams {
+    activation: Option<String>,
+    at: Option<String>,
+}
+
+/// Body of '/api/media/fetch'.
+#[derive(Debug, serde::Deserialize)]
+struct FetchParams {
+    url: String,
+    sha256: Option<String>,
+    filename: Option<String>,
+    activation: Option<String>,
+    at: Option<String>,
+}
 
 pub struct WebServerAppData {
     file_sender: tokio::sync::mpsc::Sender<FilesSourceType>,
+    wifi_manager: Option<WiFiCredentialsProcedure>,
+    connectivity_rx: Option<tokio::sync::watch::Receiver<ConnectivityState>>,
+    video_player: Option<Arc<VideoPlayer>>,
+    config_store: Option<Arc<ConfigStore>>,
+    event_bus: Option<Arc<EventBus>>,
+    media_path: Option<PathBuf>,
+    state_path: Option<PathBuf>,
+    log_buffer: Option<Arc<LogBuffer>>,
+    upload_store: Option<Arc<UploadStore>>,
+    url_fetcher: Option<UrlFetcher>,
+    schedule_rx: Option<tokio::sync::watch::Receiver<ScheduleStatus>>,
+    sync_rx: Option<tokio::sync::watch::Receiver<SyncStatus>>,
+    activation_rx: Option<tokio::sync::watch::Receiver<Option<PendingActivation>>>,
 }
 
 #[derive(Debug, thiserror::Error)]
@@ -18,7 +66,23 @@ pub enum WebServerError {
     TokioJoinError(#[from] tokio::task::JoinError),
 }
 
-pub struct WebServer;
+/// Web server, optional components enable related endpoints.
+#[derive(Default)]
+pub struct WebServer {
+    wifi_manager: Option<WiFiCredentialsProcedure>,
+    connectivity_rx: Option<tokio::sync::watch::Receiver<ConnectivityState>>,
+    video_player: Option<Arc<VideoPlayer>>,
+    config_store: Option<Arc<ConfigStore>>,
+    event_bus: Option<Arc<EventBus>>,
+    media_path: Option<PathBuf>,
+    state_path: Option<PathBuf>,
+    log_buffer: Option<Arc<LogBuffer>>,
+    upload_store: Option<Arc<UploadStore>>,
+    url_fetcher: Option<UrlFetcher>,
+    schedule_rx: Option<tokio::sync::watch::Receiver<ScheduleStatus>>,
+    sync_rx: Option<tokio::sync::watch::Receiver<SyncStatus>>,
+    activation_rx: Option<tokio::sync::watch::Receiver<Option<PendingActivation>>>,
+}
 
 impl FilesSource for WebServer {
     type Handler = WebServerHandler;
@@ -26,7 +90,20 @@ impl FilesSource for WebServer {
 
     async fn start(self, sink: Arc<dyn FilesManagerSink>) -> Result<Self::Handler, Self::Error> {
         let app_data = Arc::new(WebServerAppData {
-            file_sender: sink.get_tx().clone()
+            file_sender: sink.get_tx().clone(),
+            wifi_manager: self.wifi_manager,
+            connectivity_rx: self.connectivity_rx,
+            video_player: self.video_player,
+            config_store: self.config_store,
+            event_bus: self.event_bus,
+            media_path: self.media_path,
+            state_path: self.state_path,
+            log_buffer: self.log_buffer,
+            upload_store: self.upload_store,
+            url_fetcher: self.url_fetcher,
+            schedule_rx: self.schedule_rx,
+            sync_rx: self.sync_rx,
+            activation_rx: self.activation_rx,
         });
 
         let app = Self::build_router(app_data)
@@ -36,7 +113,7 @@ impl FilesSource for WebServer {
                 .on_response(DefaultOnResponse::new().level(tracing::Level::DEBUG)),
             );
 
-        let listener = tokio::net::TcpListener::bind("0.0.0.0:8080".to_string()).await?;
+        let listener = tokio::net::TcpListener::bind(format!("0.0.0.0:{}", Self::PORT)).await?;
         let address = listener.local_addr()?;
 
         tracing::info!("{}({}) listening on {}", env!("CARGO_PKG_NAME"), env!("CARGO_PKG_VERSION"), address);
@@ -63,70 +140,803 @@ impl FilesSource for WebServer {
 }
 
 impl WebServer {
-    const MAX_VIDEO_FILESIZE_BYTES: usize = 1024 * 1024 * 100;
+    pub const PORT: u16 = 8080;
+    pub const MAX_VIDEO_FILESIZE_BYTES: usize = 1024 * 1024 * 100;
+    const NOTICE_TIMEOUT_MS: u32 = 5000;
+    const UPLOAD_PROGRESS_STEP_BYTES: u64 = 1024 * 1024;
+
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_wifi_manager(mut self, wifi_manager: WiFiCredentialsProcedure) -> Self {
+        self.wifi_manager = Some(wifi_manager);
+        self
+    }
+
+    pub fn with_connectivity(mut self, connectivity_rx: tokio::sync::watch::Receiver<ConnectivityState>) -> Self {
+        self.connectivity_rx = Some(connectivity_rx);
+        self
+    }
+
+    pub fn with_video_player(mut self, video_player: Arc<VideoPlayer>) -> Self {
+        self.video_player = Some(video_player);
+        self
+    }
+
+    pub fn with_config_store(mut self, config_store: Arc<ConfigStore>) -> Self {
+        self.config_store = Some(config_store);
+        self
+    }
+
+    /// Enables '/api/events' and upload progress events.
+    pub fn with_event_bus(mut self, event_bus: Arc<EventBus>) -> Self {
+        self.event_bus = Some(event_bus);
+        self
+    }
+
+    /// Enables media library endpoints, 'media_path' is directory managed by files manager.
+    pub fn with_media_library(mut self, media_path: PathBuf) -> Self {
+        self.media_path = Some(media_path);
+        self
+    }
+
+    /// Logo files are also looked up in 'state_path'.
+    pub fn with_state_path(mut self, state_path: PathBuf) -> Self {
+        self.state_path = Some(state_path);
+        self
+    }
+
+    pub fn with_log_buffer(mut self, log_buffer: Arc<LogBuffer>) -> Self {
+        self.log_buffer = Some(log_buffer);
+        self
+    }
+
+    /// Enables resumable uploads at '/api/uploads'.
+    pub fn with_upload_store(mut self, upload_store: Arc<UploadStore>) -> Self {
+        self.upload_store = Some(upload_store);
+        self
+    }
+
+    /// Enables adding media by URL.
+    pub fn with_url_fetcher(mut self, url_fetcher: UrlFetcher) -> Self {
+        self.url_fetcher = Some(url_fetcher);
+        self
+    }
+
+    /// Enables '/api/schedule'.
+    pub fn with_schedule(mut self, schedule_rx: tokio::sync::watch::Receiver<ScheduleStatus>) -> Self {
+        self.schedule_rx = Some(schedule_rx);
+        self
+    }
+
+    /// Enables '/api/sync'.
+    pub fn with_sync(mut self, sync_rx: tokio::sync::watch::Receiver<SyncStatus>) -> Self {
+        self.sync_rx = Some(sync_rx);
+        self
+    }
+
+    /// Enables '/api/activation'.
+    pub fn with_pending_activation(mut self, activation_rx: tokio::sync::watch::Receiver<Option<PendingActivation>>) -> Self {
+        self.activation_rx = Some(activation_rx);
+        self
+    }
+
+    fn publish(app_data: &WebServerAppData, progress: UploadProgress) {
+        if let Some(event_bus) = &app_data.event_bus {
+            event_bus.publish(SystemEvent::Upload(progress));
+        }
+    }
+
+    /// Server-Sent Events, current WiFi and network state are sent first.
+    async fn events(extract::State(app_data): extract::State<Arc<WebServerAppData>>) -> impl IntoResponse {
+        let Some(event_bus) = &app_data.event_bus else {
+            return (StatusCode::NOT_FOUND, "Events not available").into_response();
+        };
+
+        let mut initial_events = vec![];
+        if let Some(wifi_manager) = &app_data.wifi_manager {
+            initial_events.push(SystemEvent::Wifi(wifi_manager.progress()));
+        }
+        if let Some(connectivity_rx) = &app_data.connectivity_rx {
+            initial_events.push(SystemEvent::Network(connectivity_rx.borrow().clone()));
+        }
+        if let Some(schedule_rx) = &app_data.schedule_rx {
+            initial_events.push(SystemEvent::Schedule(schedule_rx.borrow().clone()));
+        }
+        if let Some(sync_rx) = &app_data.sync_rx {
+            initial_events.push(SystemEvent::Sync(sync_rx.borrow().clone()));
+        }
+        if let Some(activation_rx) = &app_data.activation_rx {
+            initial_events.push(SystemEvent::Activation(activation_rx.borrow().clone()));
+        }
+
+        let live_events = BroadcastStream::new(event_bus.subscribe())
+            // Lagged receiver skips lost events
+            .filter_map(Result::ok);
+        let stream = tokio_stream::iter(initial_events)
+            .chain(live_events)
+            .map(|event| {
+                let sse_event = sse::Event::default()
+                    .event(event.name())
+                    .json_data(&event)
+                    .unwrap_or_else(|e| sse::Event::default().event("error").data(e.to_string()));
+                Ok::<_, Infallible>(sse_event)
+            });
+
+        Sse::new(stream).keep_alive(sse::KeepAlive::default()).into_response()
+    }
+
     async fn health_check() -> impl IntoResponse {
         "OK"
     }
 
-    async fn upload_form() -> Html<&'static str> {
-        Html(r#"
-            <!DOCTYPE html>
-            <html>
-            <head><title>Upload Video</title></head>
-            <body>
-                <h1>Upload Video File (max 100MB)</h1>
-                <form action="/upload" method="post" enctype="multipart/form-data">
-                    <input type="file" name="file" accept="video/*" required />
-                    <button type="submit">Upload</button>
-                </form>
-            </body>
-            </html>
-        "#)
+    async fn wifi_status(extract::State(app_data): extract::State<Arc<WebServerAppData>>) -> impl IntoResponse {
+        match &app_data.wifi_manager {
+            Some(wifi_manager) => Json(wifi_manager.progress()).into_response(),
+            None => (StatusCode::NOT_FOUND, "WiFi manager not available").into_response(),
+        }
+    }
+
+    async fn network_status(extract::State(app_data): extract::State<Arc<WebServerAppData>>) -> impl IntoResponse {
+        match &app_data.connectivity_rx {
+            Some(connectivity_rx) => Json(connectivity_rx.borrow().clone()).into_response(),
+            None => (StatusCode::NOT_FOUND, "Connectivity monitor not available").into_response(),
+        }
+    }
+
+    async fn schedule_status(extract::State(app_data): extract::State<Arc<WebServerAppData>>) -> impl IntoResponse {
+        match &app_data.schedule_rx {
+            Some(schedule_rx) => Json(schedule_rx.borrow().clone()).into_response(),
+            None => (StatusCode::NOT_FOUND, "Scheduler not available").into_response(),
+        }
+    }
+
+    async fn sync_status(extract::State(app_data): extract::State<Arc<WebServerAppData>>) -> impl IntoResponse {
+        match &app_data.sync_rx {
+            Some(sync_rx) => Json(sync_rx.borrow().clone()).into_response(),
+            None => (StatusCode::NOT_FOUND, "Playback sync not available").into_response(),
+        }
+    }
+
+    async fn pending_activation(extract::State(app_data): extract::State<Arc<WebServerAppData>>) -> impl IntoResponse {
+        match &app_data.activation_rx {
+            Some(activation_rx) => Json(activation_rx.borrow().clone()).into_response(),
+            None => (StatusCode::NOT_FOUND, "Files manager not available").into_response(),
+        }
+    }
+
+    async fn watchdog_status(extract::State(app_data): extract::State<Arc<WebServerAppData>>) -> impl IntoResponse {
+        match &app_data.video_player {
+            Some(video_player) => Json(video_player.watchdog_status()).into_response(),
+            None => (StatusCode::NOT_FOUND, "Video player not available").into_response(),
+        }
+    }
+
+    async fn show_text_overlay(
+        extract::State(app_data): extract::State<Arc<WebServerAppData>>,
+        Json(overlay): Json<TextOverlay>
+    ) -> impl IntoResponse {
+        match &app_data.video_player {
+            Some(video_player) => {
+                video_player.show_text(overlay);
+                (StatusCode::OK, "Text shown").into_response()
+            },
+            None => (StatusCode::NOT_FOUND, "Video player not available").into_response(),
+        }
+    }
+
+    async fn hide_text_overlay(extract::State(app_data): extract::State<Arc<WebServerAppData>>) -> impl IntoResponse {
+        match &app_data.video_player {
+            Some(video_player) => {
+                video_player.hide_text();
+                (StatusCode::OK, "Text hidden").into_response()
+            },
+            None => (StatusCode::NOT_FOUND, "Video player not available").into_response(),
+        }
+    }
+
+    /// Logo given by file name, media library is searched first, then state directory.
+    async fn logo_file_path(app_data: &WebServerAppData, filename: &Path) -> Option<PathBuf> {
+        let filename = filename.to_str().filter(|filename| FilesManager::is_valid_file_name(filename))?;
+        for dir_path in [&app_data.media_path, &app_data.state_path].into_iter().flatten() {
+            let file_path = dir_path.join(filename);
+            if tokio::fs::metadata(&file_path).await.is_ok_and(|metadata| metadata.is_file()) {
+                return Some(file_path);
+            }
+        }
+        None
+    }
+
+    /// Logo is persisted in config if available.
+    async fn show_logo_overlay(
+        extract::State(app_data): extract::State<Arc<WebServerAppData>>,
+        Json(mut logo): Json<LogoOverlay>
+    ) -> impl IntoResponse {
+        let Some(video_player) = &app_data.video_player else {
+            return (StatusCode::NOT_FOUND, "Video player not available").into_response();
+        };
+
+        logo.file = match Self::logo_file_path(&app_data, &logo.file).await {
+            Some(file_path) => file_path,
+            None => return (StatusCode::BAD_REQUEST, "Logo must be file name in media library or state directory").into_response(),
+        };
+
+        video_player.show_logo(logo.clone());
+        if let Some(config_store) = &app_data.config_store
+            && config_store.update(|config| config.logo = Some(logo)).await.is_err()
+        {
+            return (StatusCode::INTERNAL_SERVER_ERROR, "Logo shown, failed to save config").into_response();
+        }
+        (StatusCode::OK, "Logo shown").into_response()
+    }
+
+    async fn hide_logo_overlay(extract::State(app_data): extract::State<Arc<WebServerAppData>>) -> impl IntoResponse {
+        let Some(video_player) = &app_data.video_player else {
+            return (StatusCode::NOT_FOUND, "Video player not available").into_response();
+        };
+
+        video_player.hide_logo();
+        if let Some(config_store) = &app_data.config_store
+            && config_store.update(|config| config.logo = None).await.is_err()
+        {
+            return (StatusCode::INTERNAL_SERVER_ERROR, "Logo hidden, failed to save config").into_response();
+        }
+        (StatusCode::OK, "Logo hidden").into_response()
+    }
+
+    async fn get_adjustment(extract::State(app_data): extract::State<Arc<WebServerAppData>>) -> impl IntoResponse {
+        let adjustment = app_data.config_store.as_ref()
+            .map(|config_store| config_store.get().adjustment)
+            .unwrap_or_default();
+        Json(adjustment)
+    }
+
+    /// Applies adjustment without saving, e.g. while slider is moved.
+    async fn preview_adjustment(
+        extract::State(app_data): extract::State<Arc<WebServerAppData>>,
+        Json(adjustment): Json<VideoAdjustment>
+    ) -> impl IntoResponse {
+        let Some(video_player) = &app_data.video_player else {
+            return (StatusCode::NOT_FOUND, "Video player not available").into_response();
+        };
+
+        let adjustment = adjustment.clamped();
+        video_player.set_adjustment(adjustment);
+        Json(adjustment).into_response()
+    }
+
+    /// Adjustment is persisted in config if available.
+    async fn set_adjustment(
+        extract::State(app_data): extract::State<Arc<WebServerAppData>>,
+        Json(adjustment): Json<VideoAdjustment>
+    ) -> impl IntoResponse {
+        let Some(video_player) = &app_data.video_player else {
+            return (StatusCode::NOT_FOUND, "Video player not available").into_response();
+        };
+
+        let adjustment = adjustment.clamped();
+        video_player.set_adjustment(adjustment);
+        if let Some(config_store) = &app_data.config_store
+            && config_store.update(|config| config.adjustment = adjustment).await.is_err()
+        {
+            return (StatusCode::INTERNAL_SERVER_ERROR, "Adjustment applied, failed to save config").into_response();
+        }
+        Json(adjustment).into_response()
+    }
+
+    async fn reset_adjustment(extract::State(app_data): extract::State<Arc<WebServerAppData>>) -> impl IntoResponse {
+        let Some(video_player) = &app_data.video_player else {
+            return (StatusCode::NOT_FOUND, "Video player not available").into_response();
+        };
+
+        video_player.set_adjustment(VideoAdjustment::default());
+        if let Some(config_store) = &app_data.config_store
+            && config_store.update(|config| config.adjustment = VideoAdjustment::default()).await.is_err()
+        {
+            return (StatusCode::INTERNAL_SERVER_ERROR, "Adjustment reset, failed to save config").into_response();
+        }
+        (StatusCode::OK, "Adjustment reset").into_response()
+    }
+
+    async fn dashboard() -> Html<&'static str> {
+        Html(DASHBOARD_HTML)
+    }
+
+    async fn dashboard_js() -> impl IntoResponse {
+        ([(header::CONTENT_TYPE, "text/javascript; charset=utf-8")], DASHBOARD_JS)
+    }
+
+    async fn dashboard_css() -> impl IntoResponse {
+        ([(header::CONTENT_TYPE, "text/css; charset=utf-8")], DASHBOARD_CSS)
+    }
+
+    async fn player_status(extract::State(app_data): extract::State<Arc<WebServerAppData>>) -> impl IntoResponse {
+        match &app_data.video_player {
+            Some(video_player) => Json(video_player.status()).into_response(),
+            None => (StatusCode::NOT_FOUND, "Video player not available").into_response(),
+        }
     }
 
+    async fn player_command(
+        extract::State(app_data): extract::State<Arc<WebServerAppData>>,
+        extract::Path(command): extract::Path<String>
+    ) -> impl IntoResponse {
+        let Some(video_player) = &app_data.video_player else {
+            return (StatusCode::NOT_FOUND, "Video player not available").into_response();
+        };
+
+        match command.as_str() {
+            "pause" => video_player.pause(),
+            "resume" => video_player.resume(),
+            "next" => video_player.next(),
+            "previous" => video_player.previous(),
+            "stop" => video_player.stop().await,
+            _ => return (StatusCode::NOT_FOUND, format!("Unknown player command '{command}'")).into_response(),
+        }
+        (StatusCode::OK, "Done").into_response()
+    }
+
+    async fn library_files(extract::State(app_data): extract::State<Arc<WebServerAppData>>) -> impl IntoResponse {
+        let Some(media_path) = &app_data.media_path else {
+            return (StatusCode::NOT_FOUND, "Media library not available").into_response();
+        };
+
+        match FilesManager::list_library(media_path).await {
+            Ok(entries) => Json(entries).into_response(),
+            Err(e) => (StatusCode::INTERNAL_SERVER_ERROR, format!("Could not list library: {e}")).into_response(),
+        }
+    }
+
+    /// Files of current and queued playlist cannot be deleted, scheduled one is cancelled, result is published as files event.
+    async fn delete_library_file(
+        extract::State(app_data): extract::State<Arc<WebServerAppData>>,
+        extract::Path(filename): extract::Path<String>
+    ) -> impl IntoResponse {
+        if app_data.media_path.is_none() {
+            return (StatusCode::NOT_FOUND, "Media library not available").into_response();
+        }
+
+        if let Some(video_player) = &app_data.video_player {
+            let status = video_player.status();
+            let in_playlist = status.playlist.items.iter()
+                .chain(status.queued.iter().flat_map(|queued| queued.items.iter()))
+                .any(|item| item.path.file_name().is_some_and(|name| *name == *filename));
+            if in_playlist {
+                return (StatusCode::CONFLICT, format!("'{filename}' is in current or queued playlist")).into_response();
+            }
+        }
+
+        if app_data.file_sender.send(FilesSourceType::LibraryDelete { filename }).await.is_err() {
+            return (StatusCode::INTERNAL_SERVER_ERROR, "Failed to forward request").into_response();
+        }
+        (StatusCode::ACCEPTED, "Deleting").into_response()
+    }
+
+    /// Plays files from library, result is published as files event.
+    async fn set_playlist(
+        extract::State(app_data): extract::State<Arc<WebServerAppData>>,
+        Json(playlist_cfg): Json<PlaylistCfg>
+    ) -> impl IntoResponse {
+        if app_data.media_path.is_none() {
+            return (StatusCode::NOT_FOUND, "Media library not available").into_response();
+        }
+        if playlist_cfg.items.is_empty() {
+            return (StatusCode::BAD_REQUEST, "Playlist is empty").into_response();
+        }
+
+        if app_data.file_sender.send(FilesSourceType::LibraryPlaylist(playlist_cfg)).await.is_err() {
+            return (StatusCode::INTERNAL_SERVER_ERROR, "Failed to forward playlist").into_response();
+        }
+        (StatusCode::ACCEPTED, "Playlist accepted").into_response()
+    }
+
+    /// Media is downloaded in background, progress is published as fetch events.
+    async fn fetch_media(
+        extract::State(app_data): extract::State<Arc<WebServerAppData>>,
+        Json(params): Json<FetchParams>
+    ) -> impl IntoResponse {
+        let Some(url_fetcher) = &app_data.url_fetcher else {
+            return (StatusCode::NOT_FOUND, "Fetching by URL not available").into_response();
+        };
+
+        let activation = match Self::upload_activation(&app_data, params.activation.as_deref(), params.at.as_deref()) {
+            Ok(activation) => activation,
+            Err(e) => return (StatusCode::BAD_REQUEST, e).into_response(),
+        };
+        let request = FetchRequest { url: params.url, sha256: params.sha256, filename: params.filename, activation };
+        match url_fetcher.fetch(request) {
+            Ok(filename) => (StatusCode::ACCEPTED, Json(serde_json::json!({ "filename": filename }))).into_response(),
+            Err(e @ (UrlFetchError::QueueFull | UrlFetchError::Stopped)) => (StatusCode::SERVICE_UNAVAILABLE, e.to_string()).into_response(),
+            Err(e) => (StatusCode::BAD_REQUEST, e.to_string()).into_response(),
+        }
+    }
+
+    async fn get_config(extract::State(app_data): extract::State<Arc<WebServerAppData>>) -> impl IntoResponse {
+        match &app_data.config_store {
+            Some(config_store) => Json(config_store.get()).into_response(),
+            None => (StatusCode::NOT_FOUND, "Config not available").into_response(),
+        }
+    }
+
+    /// Changes sections given in body, response lists the ones taking effect after restart.
+    async fn set_config(
+        extract::State(app_data): extract::State<Arc<WebServerAppData>>,
+        Json(mut patch): Json<ConfigPatch>
+    ) -> impl IntoResponse {
+        let Some(config_store) = &app_data.config_store else {
+            return (StatusCode::NOT_FOUND, "Config not available").into_response();
+        };
+        let current_config = config_store.get();
+        if let Err(e) = patch.validate(&current_config) {
+            return (StatusCode::BAD_REQUEST, e).into_response();
+        }
+
+        // Saved logo is sent back as is, new one is file name as in '/api/overlay/logo'
+        if let Some(Some(logo)) = &mut patch.logo
+            && current_config.logo.is_none_or(|current_logo| current_logo.file != logo.file)
+        {
+            logo.file = match Self::logo_file_path(&app_data, &logo.file).await {
+                Some(file_path) => file_path,
+                None => return (StatusCode::BAD_REQUEST, "Logo must be file name in media library or state directory").into_response(),
+            };
+        }
+
+        let mut restart_required = vec![];
+        let config = match config_store.update(|config| restart_required = patch.apply(config)).await {
+            Ok(config) => config,
+            Err(e) => return (StatusCode::INTERNAL_SERVER_ERROR, format!("Failed to save config: {e}")).into_response(),
+        };
+
+        if let Some(video_player) = &app_data.video_player {
+            match config.logo.clone() {
+                Some(logo) => video_player.show_logo(logo),
+                None => video_player.hide_logo(),
+            }
+            video_player.set_adjustment(config.adjustment);
+        }
+        Json(serde_json::json!({ "config": config, "restart_required": restart_required })).into_response()
+    }
+
+    async fn logs(extract::State(app_data): extract::State<Arc<WebServerAppData>>) -> impl IntoResponse {
+        match &app_data.log_buffer {
+            Some(log_buffer) => Json(log_buffer.lines()).into_response(),
+            None => (StatusCode::NOT_FOUND, "Logs not available").into_response(),
+        }
+    }
+
+    fn media_config(app_data: &WebServerAppData) -> MediaConfig {
+        app_data.config_store.as_ref()
+            .map(|config_store| config_store.get().media)
+            .unwrap_or_default()
+    }
+
+    /// Explicit activation or default one from config.
+    fn upload_activation(app_data: &WebServerAppData, mode: Option<&str>, time: Option<&str>) -> Result<UploadActivation, String> {
+        match mode {
+            Some(mode) => UploadActivation::parse(mode, time),
+            None => Ok(app_data.config_store.as_ref()
+                .map(|config_store| config_store.get().upload.activation)
+                .unwrap_or_default()),
+        }
+    }
+
+    fn show_upload_notice(app_data: &WebServerAppData, files: &[UploadedFile]) {
+        let notice = match files {
+            [file] => format!("Upload received: {}", file.filename),
+            _ => format!("Upload received: {} files", files.len()),
+        };
+        if let Some(video_player) = &app_data.video_player {
+            video_player.show_text(TextOverlay::new(notice).with_timeout_ms(Self::NOTICE_TIMEOUT_MS));
+        }
+    }
+
+    /// All 'file' fields are added to library, nothing is changed if any of them is rejected.
     async fn upload_video(extract::State(
         app_data): extract::State<Arc<WebServerAppData>>,
+        extract::Query(params): extract::Query<UploadParams>,
+        headers: HeaderMap,
         mut multipart: extract::Multipart
     ) -> impl IntoResponse {
-        while let Some(field) = multipart.next_field().await.unwrap_or(None) {
-            if let Some(name) = field.name() {
-                if name != "file" {
-                    println!("Bad field name '{name}'.");
-                    continue;
+        let activation = match Self::upload_activation(&app_data, params.activation.as_deref(), params.at.as_deref()) {
+            Ok(activation) => activation,
+            Err(e) => return (StatusCode::BAD_REQUEST, e).into_response(),
+        };
+        let total_bytes = headers.get(header::CONTENT_LENGTH)
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.parse().ok());
+        let media_config = Self::media_config(&app_data);
+
+        let mut files = vec![];
+        let mut request_received_bytes = 0;
+        loop {
+            let mut field = match multipart.next_field().await {
+                Ok(Some(field)) => field,
+                Ok(None) => break,
+                // Files read so far are dropped as well
+                Err(e) => {
+                    Self::publish(&app_data, UploadProgress::Rejected { filename: String::new(), reason: format!("Malformed upload: {e}") });
+                    return (StatusCode::BAD_REQUEST, format!("Malformed upload: {e}")).into_response();
+                },
+            };
+            let Some(name) = field.name() else {
+                tracing::warn!("Field missing name!");
+                continue;
+            };
+            if name != "file" {
+                tracing::warn!("Bad field name '{name}'.");
+                continue;
+            }
+
+            let Some(filename) = field.file_name().map(str::to_string) else {
+                tracing::warn!("Missing filename in field {field:?}.");
+                continue;
+            };
+            if !FilesManager::is_valid_file_name(&filename) {
+                Self::publish(&app_data, UploadProgress::Rejected { filename: filename.clone(), reason: "Invalid file name".to_string() });
+                return (StatusCode::BAD_REQUEST, format!("Invalid file name '{filename}'")).into_response();
+            }
+
+            let mut buffer = bytes::BytesMut::new();
+            let mut reported_bytes = 0;
+            loop {
+                match field.chunk().await {
+                    Ok(Some(chunk)) => buffer.extend_from_slice(&chunk),
+                    Ok(None) => break,
+                    Err(_) => {
+                        Self::publish(&app_data, UploadProgress::Rejected { filename, reason: "Failed to read file".to_string() });
+                        return (StatusCode::BAD_REQUEST, "Failed to read file").into_response();
+                    },
                 }
-                
-                let filename = if let Some(filename) = field.file_name() {
-                    filename.to_string()
-                } else {
-                    tracing::warn!("Missing filename in field {field:?}.");
-                    continue;
-                };
-
-                let data = match field.bytes().await {
-                    Ok(data) => data,
-                    Err(_) => return (StatusCode::BAD_REQUEST, "Failed to read file").into_response(),
-                };
-
-                // Send to file manager
-                if app_data.file_sender.send(FilesSourceType::UploadedVideo { filename, data }).await.is_err() {
-                    return (StatusCode::INTERNAL_SERVER_ERROR, "Failed to forward file").into_response();
+
+                let received_bytes = request_received_bytes + buffer.len() as u64;
+                if received_bytes - reported_bytes >= Self::UPLOAD_PROGRESS_STEP_BYTES {
+                    reported_bytes = received_bytes;
+                    Self::publish(&app_data, UploadProgress::Receiving { filename: filename.clone(), received_bytes, total_bytes });
                 }
+            }
+            let data = buffer.freeze();
+            request_received_bytes += data.len() as u64;
+
+            // Unplayable file must not stop current video
+            if let Err(e) = media_config.probe(&data) {
+                tracing::warn!("Rejected upload {filename:?}, reason = {e}");
+                Self::publish(&app_data, UploadProgress::Rejected { filename: filename.clone(), reason: e.to_string() });
+                return (StatusCode::UNSUPPORTED_MEDIA_TYPE, format!("Unsupported file '{filename}': {e}")).into_response();
+            }
+
+            Self::publish(&app_data, UploadProgress::Received { filename: filename.clone(), size_bytes: data.len() as u64 });
+            files.push(UploadedFile { filename, content: UploadedContent::Bytes(data) });
+        }
+
+        if files.is_empty() {
+            return (StatusCode::BAD_REQUEST, "No file field").into_response();
+        }
+
+        // Send to file manager
+        Self::show_upload_notice(&app_data, &files);
+        if app_data.file_sender.send(FilesSourceType::UploadedFiles { files, activation }).await.is_err() {
+            return (StatusCode::INTERNAL_SERVER_ERROR, "Failed to forward file").into_response();
+        }
+
+        (StatusCode::OK, "Uploaded").into_response()
+    }
+
+    fn tus_header<'a>(headers: &'a HeaderMap, name: &str) -> Option<&'a str> {
+        headers.get(name).and_then(|value| value.to_str().ok())
+    }
+
+    /// Metadata format: 'key base64value,key base64value'.
+    fn parse_tus_metadata(metadata: &str, key: &str) -> Option<String> {
+        metadata.split(',')
+            .filter_map(|pair| pair.trim().split_once(' '))
+            .find(|(pair_key, _)| *pair_key == key)
+            .and_then(|(_, value)| base64::engine::general_purpose::STANDARD.decode(value).ok())
+            .and_then(|value| String::from_utf8(value).ok())
+    }
+
+    fn tus_error_response(e: UploadError) -> Response {
+        let status = match &e {
+            UploadError::NotFound(_) => StatusCode::NOT_FOUND,
+            UploadError::InvalidFileName(_) | UploadError::ExceedsLength(_) | UploadError::Interrupted(_) => StatusCode::BAD_REQUEST,
+            UploadError::TooLarge(_) => StatusCode::PAYLOAD_TOO_LARGE,
+            UploadError::OffsetMismatch { .. } | UploadError::Busy(_) => StatusCode::CONFLICT,
+            UploadError::TooMany(_) => StatusCode::SERVICE_UNAVAILABLE,
+            UploadError::TokioIoError(_) | UploadError::SerdeJsonError(_) => StatusCode::INTERNAL_SERVER_ERROR,
+        };
+        (status, [(TUS_RESUMABLE, TUS_VERSION)], e.to_string()).into_response()
+    }
+
+    /// Requests of other protocol version are rejected, missing version is accepted.
+    fn tus_version_rejection(headers: &HeaderMap) -> Option<Response> {
+        Self::tus_header(headers, TUS_RESUMABLE)
+            .filter(|version| *version != TUS_VERSION)
+            .map(|_| (StatusCode::PRECONDITION_FAILED, [(TUS_VERSION_HEADER, TUS_VERSION)], "Unsupported tus version").into_response())
+    }
+
+    async fn tus_options(extract::State(app_data): extract::State<Arc<WebServerAppData>>) -> impl IntoResponse {
+        let Some(upload_store) = app_data.upload_store.as_deref() else {
+            return (StatusCode::NOT_FOUND, "Resumable uploads not available").into_response();
+        };
+        (StatusCode::NO_CONTENT, [
+            (TUS_RESUMABLE, TUS_VERSION.to_string()),
+            (TUS_VERSION_HEADER, TUS_VERSION.to_string()),
+            (TUS_EXTENSION, "creation,termination".to_string()),
+            (TUS_MAX_SIZE, upload_store.max_size().to_string()),
+        ]).into_response()
+    }
+
+    /// tus creation, file name is given by 'filename' metadata.
+    async fn tus_create(
+        extract::State(app_data): extract::State<Arc<WebServerAppData>>,
+        headers: HeaderMap
+    ) -> impl IntoResponse {
+        let Some(upload_store) = app_data.upload_store.as_deref() else {
+            return (StatusCode::NOT_FOUND, "Resumable uploads not available").into_response();
+        };
+        if let Some(response) = Self::tus_version_rejection(&headers) {
+            return response;
+        }
+
+        let Some(length) = Self::tus_header(&headers, UPLOAD_LENGTH).and_then(|value| value.parse().ok()) else {
+            return (StatusCode::BAD_REQUEST, "Missing Upload-Length").into_response();
+        };
+        let metadata = Self::tus_header(&headers, UPLOAD_METADATA).unwrap_or_default();
+        let Some(filename) = Self::parse_tus_metadata(metadata, "filename") else {
+            return (StatusCode::BAD_REQUEST, "Missing filename in Upload-Metadata").into_response();
+        };
+        let mode = Self::parse_tus_metadata(metadata, "activation");
+        let time = Self::parse_tus_metadata(metadata, "at");
+        let activation = match Self::upload_activation(&app_data, mode.as_deref(), time.as_deref()) {
+            Ok(activation) => activation,
+            Err(e) => return (StatusCode::BAD_REQUEST, e).into_response(),
+        };
+
+        match upload_store.create(&filename, length, activation).await {
+            Ok(info) => (StatusCode::CREATED, [
+                (header::LOCATION.as_str(), format!("/api/uploads/{}", info.id)),
+                (TUS_RESUMABLE, TUS_VERSION.to_string()),
+                (UPLOAD_OFFSET, info.offset.to_string()),
+            ]).into_response(),
+            Err(e) => Self::tus_error_response(e),
+        }
+    }
+
+    async fn tus_offset(
+        extract::State(app_data): extract::State<Arc<WebServerAppData>>,
+        extract::Path(id): extract::Path<String>
+    ) -> impl IntoResponse {
+        let Some(upload_store) = app_data.upload_store.as_deref() else {
+            return (StatusCode::NOT_FOUND, "Resumable uploads not available").into_response();
+        };
+
+        match upload_store.info(&id).await {
+            Ok(info) => (StatusCode::OK, [
+                (header::CACHE_CONTROL.as_str(), "no-store".to_string()),
+                (TUS_RESUMABLE, TUS_VERSION.to_string()),
+                (UPLOAD_OFFSET, info.offset.to_string()),
+                (UPLOAD_LENGTH, info.length.to_string()),
+            ]).into_response(),
+            Err(e) => Self::tus_error_response(e),
+        }
+    }
 
-                return (StatusCode::OK, "Uploaded").into_response();
-            } else {
-                println!("Field missing name!")
+    /// Appends chunk, completed upload is handed to files manager like other uploads.
+    async fn tus_append(
+        extract::State(app_data): extract::State<Arc<WebServerAppData>>,
+        extract::Path(id): extract::Path<String>,
+        headers: HeaderMap,
+        body: axum::body::Body
+    ) -> impl IntoResponse {
+        let Some(upload_store) = app_data.upload_store.as_deref() else {
+            return (StatusCode::NOT_FOUND, "Resumable uploads not available").into_response();
+        };
+        if let Some(response) = Self::tus_version_rejection(&headers) {
+            return response;
+        }
+        if headers.get(header::CONTENT_TYPE).and_then(|value| value.to_str().ok()) != Some(TUS_CONTENT_TYPE) {
+            return (StatusCode::UNSUPPORTED_MEDIA_TYPE, format!("Content-Type must be {TUS_CONTENT_TYPE}")).into_response();
+        }
+        let Some(offset) = Self::tus_header(&headers, UPLOAD_OFFSET).and_then(|value| value.parse().ok()) else {
+            return (StatusCode::BAD_REQUEST, "Missing Upload-Offset").into_response();
+        };
+        let info = match upload_store.info(&id).await {
+            Ok(info) => info,
+            Err(e) => return Self::tus_error_response(e),
+        };
+
+        let mut received_bytes = offset;
+        let mut reported_bytes = offset;
+        let chunks = body.into_data_stream().map(|chunk| {
+            if let Ok(chunk) = &chunk {
+                received_bytes += chunk.len() as u64;
+                if received_bytes - reported_bytes >= Self::UPLOAD_PROGRESS_STEP_BYTES {
+                    reported_bytes = received_bytes;
+                    Self::publish(&app_data, UploadProgress::Receiving { filename: info.filename.clone(), received_bytes, total_bytes: Some(info.length) });
+                }
             }
+            chunk
+        });
+        let info = match upload_store.append(&id, offset, chunks).await {
+            Ok(info) => info,
+            Err(e) => {
+                tracing::info!("Upload {id} not appended, reason = {e}");
+                return Self::tus_error_response(e);
+            },
+        };
+        let offset_headers = [(TUS_RESUMABLE, TUS_VERSION.to_string()), (UPLOAD_OFFSET, info.offset.to_string())];
+        if !info.is_complete() {
+            return (StatusCode::NO_CONTENT, offset_headers).into_response();
         }
 
-        (StatusCode::BAD_REQUEST, "No file field").into_response()
+        let (info, upload_path) = match upload_store.take_completed(&id).await {
+            Ok(completed) => completed,
+            Err(e) => return Self::tus_error_response(e),
+        };
+
+        // Unplayable file must not stop current video
+        if let Err(e) = Self::media_config(&app_data).probe_file(&upload_path).await {
+            tracing::warn!("Rejected upload {:?}, reason = {e}", info.filename);
+            let _ = tokio::fs::remove_file(&upload_path).await;
+            Self::publish(&app_data, UploadProgress::Rejected { filename: info.filename.clone(), reason: e.to_string() });
+            return (StatusCode::UNSUPPORTED_MEDIA_TYPE, format!("Unsupported file '{}': {e}", info.filename)).into_response();
+        }
+
+        Self::publish(&app_data, UploadProgress::Received { filename: info.filename.clone(), size_bytes: info.length });
+        let files = vec![UploadedFile { filename: info.filename, content: UploadedContent::File(upload_path) }];
+        Self::show_upload_notice(&app_data, &files);
+        if app_data.file_sender.send(FilesSourceType::UploadedFiles { files, activation: info.activation }).await.is_err() {
+            return (StatusCode::INTERNAL_SERVER_ERROR, "Failed to forward file").into_response();
+        }
+        (StatusCode::NO_CONTENT, offset_headers).into_response()
+    }
+
+    /// tus termination.
+    async fn tus_remove(
+        extract::State(app_data): extract::State<Arc<WebServerAppData>>,
+        extract::Path(id): extract::Path<String>
+    ) -> impl IntoResponse {
+        let Some(upload_store) = app_data.upload_store.as_deref() else {
+            return (StatusCode::NOT_FOUND, "Resumable uploads not available").into_response();
+        };
+
+        match upload_store.remove(&id).await {
+            Ok(()) => (StatusCode::NO_CONTENT, [(TUS_RESUMABLE, TUS_VERSION)]).into_response(),
+            Err(e) => Self::tus_error_response(e),
+        }
     }
 
     fn build_router(app_data: Arc<WebServerAppData>) -> Router {
         Router::new()
+            .route("/", get(Self::dashboard))
+            .route("/assets/app.js", get(Self::dashboard_js))
+            .route("/assets/style.css", get(Self::dashboard_css))
             .route("/health", get(Self::health_check))
+            .route("/api/events", get(Self::events))
+            .route("/api/wifi", get(Self::wifi_status))
+            .route("/api/network", get(Self::network_status))
+            .route("/api/watchdog", get(Self::watchdog_status))
+            .route("/api/player", get(Self::player_status))
+            .route("/api/player/{command}", post(Self::player_command))
+            .route("/api/library", get(Self::library_files))
+            .route("/api/library/{filename}", delete(Self::delete_library_file))
+            .route("/api/playlist", post(Self::set_playlist))
+            .route("/api/media/fetch", post(Self::fetch_media))
+            .route("/api/schedule", get(Self::schedule_status))
+            .route("/api/sync", get(Self::sync_status))
+            .route("/api/activation", get(Self::pending_activation))
+            .route("/api/config", get(Self::get_config).post(Self::set_config))
+            .route("/api/logs", get(Self::logs))
+            .route("/api/overlay/text", post(Self::show_text_overlay).delete(Self::hide_text_overlay))
+            .route("/api/overlay/logo", post(Self::show_logo_overlay).delete(Self::hide_logo_overlay))
+            .route("/api/adjustment", get(Self::get_adjustment).post(Self::set_adjustment).delete(Self::reset_adjustment))
+            .route("/api/adjustment/preview", post(Self::preview_adjustment))
             .route("/upload", post(Self::upload_video))
                 .layer(DefaultBodyLimit::max(Self::MAX_VIDEO_FILESIZE_BYTES))
-            .route("/upload", get(Self::upload_form))
+            .route("/upload", get(Self::dashboard))
+            .route("/api/uploads", post(Self::tus_create).options(Self::tus_options))
+            .route("/api/uploads/{id}", head(Self::tus_offset).patch(Self::tus_append).delete(Self::tus_remove))
+                .layer(DefaultBodyLimit::max(Self::MAX_VIDEO_FILESIZE_BYTES))
             .with_state(app_data)
     }
 }
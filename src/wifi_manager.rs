@@ -1,11 +1,146 @@
-use std::{process::Command, thread, time::Duration};
+use std::{future::Future, net::{IpAddr, Ipv4Addr}, path::{Path, PathBuf}, pin::Pin, process::Output, sync::Mutex, time::Duration};
 
-use serde::Deserialize;
+use serde::{Deserialize, Serialize};
+use tokio::process::Command;
+use tokio_util::sync::CancellationToken;
+
+/// Prefix of connection profiles created by this program, keeps user profiles untouched.
+const CONNECTION_NAME_PREFIX: &str = "headlesspiplayer-";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+enum WifiSecurity {
+    Open,
+    WpaPsk,
+    Wpa3Sae,
+    /// PEAP with MSCHAPv2 inner authentication.
+    Wpa2Enterprise,
+}
 
 #[derive(Debug, Deserialize)]
 struct WiFiCredentialsCfg {
     ssid: String,
-    psswd: String
+
+    /// Passphrase or 802.1X password, not used by open networks.
+    #[serde(default)]
+    psswd: Option<String>,
+
+    /// Inferred from password presence if missing: 'wpa_psk' or 'open'.
+    #[serde(default)]
+    security: Option<WifiSecurity>,
+
+    /// 802.1X identity.
+    #[serde(default)]
+    identity: Option<String>,
+
+    /// 802.1X CA certificate file, relative to credentials file directory.
+    #[serde(default)]
+    ca_cert: Option<String>,
+
+    /// Device hostname, e.g. 'cosplay-pi'.
+    #[serde(default)]
+    hostname: Option<String>,
+
+    /// ISO 3166-1 alpha-2 regulatory domain, e.g. 'PL'.
+    #[serde(default)]
+    country: Option<String>,
+
+    /// Static addressing, DHCP is used if missing.
+    #[serde(default)]
+    ipv4: Option<StaticIpv4Cfg>,
+}
+
+#[derive(Debug, Deserialize)]
+struct StaticIpv4Cfg {
+    /// Address with prefix length, e.g. '192.168.1.50/24'.
+    address: String,
+
+    #[serde(default)]
+    gateway: Option<String>,
+
+    #[serde(default)]
+    dns: Vec<String>,
+}
+
+impl WiFiCredentialsCfg {
+    fn security(&self) -> WifiSecurity {
+        self.security.unwrap_or(match self.psswd {
+            Some(_) => WifiSecurity::WpaPsk,
+            None => WifiSecurity::Open,
+        })
+    }
+
+    fn validate(&self) -> Result<(), WifiManagerError> {
+        match self.security() {
+            WifiSecurity::Open => {},
+            WifiSecurity::WpaPsk | WifiSecurity::Wpa3Sae => {
+                let psswd = self.psswd.as_deref().unwrap_or_default();
+                // WPA-PSK also takes raw key of 64 hex digits, SAE needs passphrase
+                let is_raw_psk = self.security() == WifiSecurity::WpaPsk
+                    && psswd.len() == 64
+                    && psswd.chars().all(|c| c.is_ascii_hexdigit());
+                if !(8..=63).contains(&psswd.len()) && !is_raw_psk {
+                    return Err(WifiManagerError::InvalidConfig("passphrase must have 8 to 63 characters or be 64 hex digits".to_string()));
+                }
+            },
+            WifiSecurity::Wpa2Enterprise => {
+                if self.identity.is_none() || self.psswd.is_none() {
+                    return Err(WifiManagerError::InvalidConfig("enterprise network requires identity and psswd".to_string()));
+                }
+            },
+        }
+
+        if self.ca_cert.is_some() && self.security() != WifiSecurity::Wpa2Enterprise {
+            return Err(WifiManagerError::InvalidConfig("ca_cert is used only by enterprise networks".to_string()));
+        }
+
+        // Certificate is copied from FLASH drive directory only
+        if let Some(ca_cert) = &self.ca_cert {
+            let is_plain_name = Path::new(ca_cert).file_name().is_some_and(|name| name == ca_cert.as_str());
+            if !is_plain_name || ca_cert.starts_with('.') {
+                return Err(WifiManagerError::InvalidConfig("ca_cert must be file name in config file directory".to_string()));
+            }
+        }
+
+        if let Some(hostname) = &self.hostname {
+            let is_valid_label = |label: &str| {
+                !label.is_empty()
+                    && label.len() <= 63
+                    && !label.starts_with('-')
+                    && !label.ends_with('-')
+                    && label.chars().all(|c| c.is_ascii_alphanumeric() || c == '-')
+            };
+            if hostname.len() > 253 || !hostname.split('.').all(is_valid_label) {
+                return Err(WifiManagerError::InvalidConfig(format!("hostname '{hostname}'")));
+            }
+        }
+
+        if let Some(country) = &self.country
+            && (country.len() != 2 || !country.chars().all(|c| c.is_ascii_alphabetic()))
+        {
+            return Err(WifiManagerError::InvalidConfig(format!("country '{country}'")));
+        }
+
+        if let Some(ipv4) = &self.ipv4 {
+            let (address, prefix) = ipv4.address.split_once('/')
+                .ok_or_else(|| WifiManagerError::InvalidConfig(format!("ipv4 address '{}' missing prefix length", ipv4.address)))?;
+            let prefix_valid = prefix.parse::<u8>().is_ok_and(|prefix| prefix <= 32);
+            if address.parse::<Ipv4Addr>().is_err() || !prefix_valid {
+                return Err(WifiManagerError::InvalidConfig(format!("ipv4 address '{}'", ipv4.address)));
+            }
+
+            if let Some(gateway) = &ipv4.gateway {
+                gateway.parse::<Ipv4Addr>()
+                    .map_err(|_| WifiManagerError::InvalidConfig(format!("ipv4 gateway '{gateway}'")))?;
+            }
+
+            if let Some(dns) = ipv4.dns.iter().find(|dns| dns.parse::<IpAddr>().is_err()) {
+                return Err(WifiManagerError::InvalidConfig(format!("dns '{dns}'")));
+            }
+        }
+
+        Ok(())
+    }
 }
 
 #[derive(Debug, thiserror::Error)]
@@ -15,54 +150,381 @@ pub enum WifiManagerError {
 
     #[error("DeserializationError reason = '{0}'")]
     DeserializationError(#[from] serde_json::Error),
+
+    #[error("CommandFailed step = '{step}', reason = '{reason}'")]
+    CommandFailed {
+        step: &'static str,
+        reason: String,
+    },
+
+    #[error("InvalidConfig reason = '{0}'")]
+    InvalidConfig(String),
+
+    #[error("Timeout step = '{0}'")]
+    Timeout(&'static str),
+
+    #[error("Cancelled")]
+    Cancelled,
+}
+
+/// Progress of WiFi connection procedure.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(tag = "state", rename_all = "snake_case")]
+pub enum WifiProgress {
+    Idle,
+    Scanning,
+    Associating {
+        ssid: String,
+        attempt: u32,
+    },
+    Configuring {
+        ssid: String,
+    },
+    Dhcp {
+        ssid: String,
+    },
+    Connected {
+        ssid: String,
+        ip_address: Option<String>,
+    },
+    Failed {
+        ssid: Option<String>,
+        reason: String,
+    },
+}
+
+impl std::fmt::Display for WifiProgress {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            WifiProgress::Idle => write!(f, "idle"),
+            WifiProgress::Scanning => write!(f, "scanning"),
+            WifiProgress::Associating { ssid, attempt } => write!(f, "associating with '{ssid}', attempt {attempt}"),
+            WifiProgress::Configuring { ssid } => write!(f, "configuring addressing on '{ssid}'"),
+            WifiProgress::Dhcp { ssid } => write!(f, "waiting for DHCP lease on '{ssid}'"),
+            WifiProgress::Connected { ssid, ip_address } => write!(f, "connected to '{ssid}', ip={ip_address:?}"),
+            WifiProgress::Failed { ssid, reason } => write!(f, "failed ssid={ssid:?}, reason = '{reason}'"),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct WifiTimeouts {
+    pub scan: Duration,
+    pub associate: Duration,
+    pub configure: Duration,
+    pub dhcp: Duration,
+}
+
+impl Default for WifiTimeouts {
+    fn default() -> Self {
+        Self {
+            scan: Duration::from_secs(15),
+            associate: Duration::from_secs(45),
+            configure: Duration::from_secs(30),
+            dhcp: Duration::from_secs(20),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct RetryPolicy {
+    /// Number of association attempts, at least 1 is made.
+    pub max_attempts: u32,
+    pub backoff: Duration,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_attempts: 3,
+            backoff: Duration::from_secs(3),
+        }
+    }
+}
+
+pub type WifiManagerFuture<'a> = Pin<Box<dyn Future<Output = Result<String, WifiManagerError>> + Send + 'a>>;
+
+pub trait WifiManager: Send + Sync {
+    /// Connects using credentials file content, files referenced by config are relative to 'config_dir'.
+    /// Returns human readable summary.
+    fn apply_credentials<'a>(&'a self, config_file_content: &'a [u8], config_dir: &'a Path, cancel: CancellationToken) -> WifiManagerFuture<'a>;
+
+    /// Stream of progress events of all following procedures.
+    fn subscribe(&self) -> tokio::sync::broadcast::Receiver<WifiProgress>;
+
+    /// Last reported progress.
+    fn progress(&self) -> WifiProgress;
+}
+
+/// WiFi manager backed by NetworkManager CLI.
+pub struct NmcliWifiManager {
+    timeouts: WifiTimeouts,
+    retry_policy: RetryPolicy,
+    certs_path: PathBuf,
+    progress_tx: tokio::sync::broadcast::Sender<WifiProgress>,
+    last_progress: Mutex<WifiProgress>,
+}
+
+impl WifiManager for NmcliWifiManager {
+    fn apply_credentials<'a>(&'a self, config_file_content: &'a [u8], config_dir: &'a Path, cancel: CancellationToken) -> WifiManagerFuture<'a> {
+        Box::pin(async move {
+            let result = self.connect_procedure(config_file_content, config_dir, &cancel).await;
+            if let Err(e) = &result {
+                let ssid = match self.progress() {
+                    WifiProgress::Associating { ssid, .. }
+                    | WifiProgress::Configuring { ssid }
+                    | WifiProgress::Dhcp { ssid } => Some(ssid),
+                    _ => None,
+                };
+                self.report(WifiProgress::Failed { ssid, reason: e.to_string() });
+            }
+            result
+        })
+    }
+
+    fn subscribe(&self) -> tokio::sync::broadcast::Receiver<WifiProgress> {
+        self.progress_tx.subscribe()
+    }
+
+    fn progress(&self) -> WifiProgress {
+        self.last_progress.lock()
+            .map(|progress| progress.clone())
+            .unwrap_or(WifiProgress::Idle)
+    }
 }
 
-pub fn wifi_manager_procedure(config_file_content: &[u8]) -> Result<String, WifiManagerError> {
-    let config: WiFiCredentialsCfg = serde_json::from_slice(config_file_content)
-        .inspect_err(|_| {
-            let str_content = String::from_utf8_lossy(config_file_content);
-            tracing::warn!("Failed to parse wifi credentials file: {str_content}");
-    })?;
+impl NmcliWifiManager {
+    const PROGRESS_CAP: usize = 16;
+    const DHCP_POLL_INTERVAL: Duration = Duration::from_millis(500);
+
+    /// Certificates are copied to 'certs_path', FLASH drive can be removed after connecting.
+    pub fn new(timeouts: WifiTimeouts, retry_policy: RetryPolicy, certs_path: PathBuf) -> Self {
+        let (progress_tx, _) = tokio::sync::broadcast::channel(Self::PROGRESS_CAP);
+        Self {
+            timeouts,
+            retry_policy,
+            certs_path,
+            progress_tx,
+            last_progress: Mutex::new(WifiProgress::Idle),
+        }
+    }
+
+    fn report(&self, progress: WifiProgress) {
+        tracing::info!("WiFi progress: {progress}");
+        if let Ok(mut last_progress) = self.last_progress.lock() {
+            *last_progress = progress.clone();
+        }
+        // No subscribers is not an error
+        let _ = self.progress_tx.send(progress);
+    }
+
+    async fn connect_procedure(&self, config_file_content: &[u8], config_dir: &Path, cancel: &CancellationToken) -> Result<String, WifiManagerError> {
+        let config: WiFiCredentialsCfg = serde_json::from_slice(config_file_content)
+            .inspect_err(|_| {
+                let str_content = String::from_utf8_lossy(config_file_content);
+                tracing::warn!("Failed to parse wifi credentials file: {str_content}");
+        })?;
+
+        tracing::debug!("wifi config ssid = {}", config.ssid);
+        config.validate()?;
+
+        let mut applied = vec![];
+
+        // Regulatory domain limits available channels, set before scanning
+        if let Some(country) = &config.country {
+            let country = country.to_ascii_uppercase();
+            let mut country_cmd = Command::new("iw");
+            country_cmd.args(["reg", "set", &country]);
+            run_step("country", &mut country_cmd, self.timeouts.configure, cancel).await?;
+            applied.push(format!("country={country}"));
+        }
+
+        // Scanning is best effort, hidden networks will not be listed
+        self.report(WifiProgress::Scanning);
+        let mut rescan_cmd = Command::new("nmcli");
+        rescan_cmd.args(["dev", "wifi", "rescan"]);
+        if let Err(e) = run_step("scan", &mut rescan_cmd, self.timeouts.scan, cancel).await {
+            if matches!(e, WifiManagerError::Cancelled) {
+                return Err(e);
+            }
+            tracing::warn!("WiFi rescan failed, reason = {e}");
+        }
+
+        // Open and WPA-PSK networks are handled by 'dev wifi connect', others need explicit profile
+        let security = config.security();
+        let profile_name = match security {
+            WifiSecurity::Open | WifiSecurity::WpaPsk => None,
+            WifiSecurity::Wpa3Sae | WifiSecurity::Wpa2Enterprise => {
+                let profile_name = format!("{CONNECTION_NAME_PREFIX}{}", config.ssid);
+                self.add_connection_profile(&config, &profile_name, config_dir, cancel).await?;
+                Some(profile_name)
+            },
+        };
+        applied.push(format!("security={security:?}"));
+
+        let attempts = self.retry_policy.max_attempts.max(1);
+        let mut attempt = 1;
+        let connection_output = loop {
+            self.report(WifiProgress::Associating { ssid: config.ssid.clone(), attempt });
 
-    tracing::debug!("wifi config = {config:?}");
+            let mut connect_cmd = Command::new("nmcli");
+            match (&profile_name, &config.psswd) {
+                (Some(profile_name), _) => connect_cmd.args(["con", "up", "id", profile_name]),
+                (None, Some(psswd)) if security == WifiSecurity::WpaPsk => connect_cmd.args(["dev", "wifi", "connect", &config.ssid, "password", psswd]),
+                (None, _) => connect_cmd.args(["dev", "wifi", "connect", &config.ssid]),
+            };
+            match run_step("associate", &mut connect_cmd, self.timeouts.associate, cancel).await {
+                Ok(output) => break output,
+                Err(WifiManagerError::Cancelled) => return Err(WifiManagerError::Cancelled),
+                Err(e) if attempt < attempts => {
+                    tracing::warn!("Failed to connect to WiFi network '{}', attempt {attempt}/{attempts}: {e}", config.ssid);
+                    attempt += 1;
+                    tokio::select! {
+                        _ = cancel.cancelled() => return Err(WifiManagerError::Cancelled),
+                        _ = tokio::time::sleep(self.retry_policy.backoff) => {},
+                    }
+                },
+                Err(e) => {
+                    tracing::error!("Failed to connect to WiFi network '{}': {e}", config.ssid);
+                    return Err(e);
+                },
+            }
+        };
 
-    let output = Command::new("nmcli")
-        .arg("dev")
-        .arg("wifi")
-        .arg("connect")
-        .arg(&config.ssid)
-        .arg("password")
-        .arg(&config.psswd)
-        .output()?;
+        // Connection profile created by nmcli is addressed by uuid, name may be suffixed if already exists
+        let connection_stdout = String::from_utf8_lossy(&connection_output.stdout);
+        let connection_id = profile_name
+            .or_else(|| parse_connection_uuid(&connection_stdout))
+            .unwrap_or_else(|| config.ssid.clone());
+
+        if let Some(ipv4) = &config.ipv4 {
+            self.report(WifiProgress::Configuring { ssid: config.ssid.clone() });
+
+            let mut modify_cmd = Command::new("nmcli");
+            modify_cmd.args(["con", "modify", &connection_id, "ipv4.method", "manual", "ipv4.addresses", &ipv4.address]);
+            if let Some(gateway) = &ipv4.gateway {
+                modify_cmd.args(["ipv4.gateway", gateway]);
+            }
+            if !ipv4.dns.is_empty() {
+                modify_cmd.args(["ipv4.dns", &ipv4.dns.join(" ")]);
+            }
+            run_step("configure", &mut modify_cmd, self.timeouts.configure, cancel).await?;
+
+            // Reactivate to apply addressing
+            let mut up_cmd = Command::new("nmcli");
+            up_cmd.args(["con", "up", &connection_id]);
+            run_step("configure", &mut up_cmd, self.timeouts.associate, cancel).await?;
+            applied.push(format!("ipv4={}, gateway={:?}, dns={:?}", ipv4.address, ipv4.gateway, ipv4.dns));
+        }
+
+        self.report(WifiProgress::Dhcp { ssid: config.ssid.clone() });
+        let ip_address = tokio::select! {
+            _ = cancel.cancelled() => return Err(WifiManagerError::Cancelled),
+            result = tokio::time::timeout(self.timeouts.dhcp, Self::await_ip_address()) => {
+                result.map_err(|_| WifiManagerError::Timeout("dhcp"))?
+            },
+        };
+
+        if let Some(hostname) = &config.hostname {
+            let mut hostname_cmd = Command::new("nmcli");
+            hostname_cmd.args(["general", "hostname", hostname]);
+            run_step("hostname", &mut hostname_cmd, self.timeouts.configure, cancel).await?;
+            applied.push(format!("hostname={hostname}"));
+        }
+
+        self.report(WifiProgress::Connected { ssid: config.ssid.clone(), ip_address: Some(ip_address.clone()) });
+        if applied.is_empty() {
+            Ok(format!("Connected to '{}', ip={:?}.", config.ssid, ip_address))
+        } else {
+            Ok(format!("Connected to '{}', ip={:?}, applied {}.", config.ssid, ip_address, applied.join(", ")))
+        }
+    }
+
+    /// Replaces profile named 'profile_name' with one built from config.
+    async fn add_connection_profile(&self, config: &WiFiCredentialsCfg, profile_name: &str, config_dir: &Path, cancel: &CancellationToken) -> Result<(), WifiManagerError> {
+        // Profile may not exist, failure is expected
+        let mut delete_cmd = Command::new("nmcli");
+        delete_cmd.args(["con", "delete", "id", profile_name]);
+        if let Err(WifiManagerError::Cancelled) = run_step("configure", &mut delete_cmd, self.timeouts.configure, cancel).await {
+            return Err(WifiManagerError::Cancelled);
+        }
+
+        let psswd = config.psswd.clone().unwrap_or_default();
+        let mut add_cmd = Command::new("nmcli");
+        add_cmd.args(["con", "add", "type", "wifi", "ifname", "*", "con-name", profile_name, "ssid", &config.ssid]);
+        match config.security() {
+            WifiSecurity::Wpa3Sae => {
+                add_cmd.args(["wifi-sec.key-mgmt", "sae", "wifi-sec.psk", &psswd]);
+            },
+            WifiSecurity::Wpa2Enterprise => {
+                let identity = config.identity.clone().unwrap_or_default();
+                add_cmd.args([
+                    "wifi-sec.key-mgmt", "wpa-eap",
+                    "802-1x.eap", "peap",
+                    "802-1x.phase2-auth", "mschapv2",
+                    "802-1x.identity", &identity,
+                    "802-1x.password", &psswd,
+                ]);
+
+                if let Some(ca_cert) = &config.ca_cert {
+                    let ca_cert_path = self.store_certificate(&config_dir.join(ca_cert)).await?;
+                    add_cmd.arg("802-1x.ca-cert").arg(ca_cert_path);
+                }
+            },
+            WifiSecurity::Open | WifiSecurity::WpaPsk => {},
+        }
+        run_step("configure", &mut add_cmd, self.timeouts.configure, cancel).await?;
+        Ok(())
+    }
+
+    async fn store_certificate(&self, source_path: &Path) -> Result<PathBuf, WifiManagerError> {
+        let file_name = source_path.file_name()
+            .ok_or_else(|| WifiManagerError::InvalidConfig(format!("ca_cert {source_path:?}")))?;
+        tokio::fs::create_dir_all(&self.certs_path).await?;
+        let destination_path = self.certs_path.join(file_name);
+        tokio::fs::copy(source_path, &destination_path).await?;
+        tracing::info!("Stored CA certificate in {destination_path:?}");
+        Ok(destination_path)
+    }
+
+    async fn await_ip_address() -> String {
+        loop {
+            if let Some(ip_address) = try_getting_ip().await {
+                return ip_address;
+            }
+            tokio::time::sleep(Self::DHCP_POLL_INTERVAL).await;
+        }
+    }
+}
+
+/// Runs command killing it on timeout or cancellation.
+async fn run_step(step: &'static str, command: &mut Command, timeout_duration: Duration, cancel: &CancellationToken) -> Result<Output, WifiManagerError> {
+    command.kill_on_drop(true);
+    let output = tokio::select! {
+        biased;
+        _ = cancel.cancelled() => return Err(WifiManagerError::Cancelled),
+        result = tokio::time::timeout(timeout_duration, command.output()) => {
+            result.map_err(|_| WifiManagerError::Timeout(step))??
+        },
+    };
 
     if output.status.success() {
-        tracing::info!("wifi config = {config:?}, set.");
-        thread::sleep(Duration::from_secs(5));
-        let ip_address = try_getting_ip();
-        Ok(format!("Connected to '{}', ip={:?}.", config.ssid, ip_address))
+        Ok(output)
     } else {
         let stderr_str = String::from_utf8_lossy(&output.stderr);
-        tracing::error!(
-            "Failed to connect to WiFi network '{}': {}",
-            config.ssid,
-            stderr_str
-        );
-
-        let error_str = format!(
-            "nmcli failed with status {}: {}",
-            output.status,
-            stderr_str
-        );
-        Err(std::io::Error::new(std::io::ErrorKind::Other, error_str).into())
+        Err(WifiManagerError::CommandFailed {
+            step,
+            reason: format!("status {}: {}", output.status, stderr_str.trim()),
+        })
     }
 }
 
-fn try_getting_ip() -> Option<String> {
+/// Returns first global IPv4 address.
+pub async fn try_getting_ip() -> Option<String> {
     let output = Command::new("ip")
-        .arg("-4")
-        .arg("addr")
-        .arg("show")
+        .args(["-4", "-o", "addr", "show", "scope", "global"])
         .output()
+        .await
         .ok()?;
 
     if !output.status.success() {
@@ -70,5 +532,106 @@ fn try_getting_ip() -> Option<String> {
     }
 
     let stdout = String::from_utf8_lossy(&output.stdout);
-    Some(stdout.to_string())
-}
\ No newline at end of file
+    parse_ip_address(&stdout)
+}
+
+/// Finds uuid in "Device 'wlan0' successfully activated with '<uuid>'." message.
+fn parse_connection_uuid(nmcli_connect_output: &str) -> Option<String> {
+    let (_, tail) = nmcli_connect_output.split_once("activated with '")?;
+    let (uuid, _) = tail.split_once('\'')?;
+    Some(uuid.to_string())
+}
+
+fn parse_ip_address(ip_addr_output: &str) -> Option<String> {
+    ip_addr_output.lines()
+        .filter_map(|line| {
+            let mut words = line.split_whitespace();
+            words.find(|&word| word == "inet")?;
+            words.next()
+        })
+        .map(|cidr| cidr.split('/').next().unwrap_or(cidr).to_string())
+        .next()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_ip_address() {
+        let output = "3: wlan0    inet 192.168.1.23/24 brd 192.168.1.255 scope global dynamic noprefixroute wlan0\\       valid_lft 86363sec preferred_lft 86363sec\n";
+        assert_eq!(parse_ip_address(output), Some("192.168.1.23".to_string()));
+        assert_eq!(parse_ip_address(""), None);
+    }
+
+    #[test]
+    fn test_parse_connection_uuid() {
+        let output = "Device 'wlan0' successfully activated with 'b6a3c2de-3f5e-4b8a-9d7c-2a1e0f9b8c7d'.\n";
+        assert_eq!(parse_connection_uuid(output), Some("b6a3c2de-3f5e-4b8a-9d7c-2a1e0f9b8c7d".to_string()));
+        assert_eq!(parse_connection_uuid("Error: No network with SSID 'stage' found."), None);
+    }
+
+    #[test]
+    fn test_validate_network_config() {
+        let valid: WiFiCredentialsCfg = serde_json::from_str(r#"{
+            "ssid": "stage", "psswd": "secret123", "hostname": "cosplay-pi", "country": "PL",
+            "ipv4": { "address": "192.168.1.50/24", "gateway": "192.168.1.1", "dns": ["1.1.1.1"] }
+        }"#).unwrap();
+        assert!(valid.validate().is_ok());
+
+        let invalid_address: WiFiCredentialsCfg = serde_json::from_str(r#"{
+            "ssid": "stage", "psswd": "secret123", "ipv4": { "address": "192.168.1.50" }
+        }"#).unwrap();
+        assert!(matches!(invalid_address.validate(), Err(WifiManagerError::InvalidConfig(_))));
+
+        let open: WiFiCredentialsCfg = serde_json::from_str(r#"{ "ssid": "venue" }"#).unwrap();
+        assert_eq!(open.security(), WifiSecurity::Open);
+        assert!(open.validate().is_ok());
+
+        let enterprise: WiFiCredentialsCfg = serde_json::from_str(r#"{
+            "ssid": "venue", "security": "wpa2_enterprise", "identity": "crew", "psswd": "secret", "ca_cert": "ca.pem"
+        }"#).unwrap();
+        assert!(enterprise.validate().is_ok());
+
+        for ca_cert in ["/etc/shadow", "../ca.pem", "certs/ca.pem", ".."] {
+            let outside_cert: WiFiCredentialsCfg = serde_json::from_value(serde_json::json!({
+                "ssid": "venue", "security": "wpa2_enterprise", "identity": "crew", "psswd": "secret", "ca_cert": ca_cert
+            })).unwrap();
+            assert!(matches!(outside_cert.validate(), Err(WifiManagerError::InvalidConfig(_))), "{ca_cert}");
+        }
+
+        let enterprise_no_identity: WiFiCredentialsCfg = serde_json::from_str(r#"{
+            "ssid": "venue", "security": "wpa2_enterprise", "psswd": "secret"
+        }"#).unwrap();
+        assert!(matches!(enterprise_no_identity.validate(), Err(WifiManagerError::InvalidConfig(_))));
+
+        let short_passphrase: WiFiCredentialsCfg = serde_json::from_str(r#"{
+            "ssid": "stage", "security": "wpa3_sae", "psswd": "short"
+        }"#).unwrap();
+        assert!(matches!(short_passphrase.validate(), Err(WifiManagerError::InvalidConfig(_))));
+
+        let raw_psk = "0123456789abcdefABCDEF0123456789abcdef0123456789abcdef0123456789";
+        let raw_psk_cfg: WiFiCredentialsCfg = serde_json::from_value(serde_json::json!({ "ssid": "stage", "psswd": raw_psk })).unwrap();
+        assert!(raw_psk_cfg.validate().is_ok());
+        let long_passphrase: WiFiCredentialsCfg = serde_json::from_value(serde_json::json!({ "ssid": "stage", "psswd": raw_psk.replace('0', "g") })).unwrap();
+        assert!(matches!(long_passphrase.validate(), Err(WifiManagerError::InvalidConfig(_))));
+
+        let invalid_hostname: WiFiCredentialsCfg = serde_json::from_str(r#"{
+            "ssid": "stage", "psswd": "secret123", "hostname": "cosplay_pi"
+        }"#).unwrap();
+        assert!(matches!(invalid_hostname.validate(), Err(WifiManagerError::InvalidConfig(_))));
+    }
+
+    #[tokio::test]
+    async fn test_cancelled_before_start() {
+        let wifi_manager = NmcliWifiManager::new(WifiTimeouts::default(), RetryPolicy::default(), std::env::temp_dir());
+        let mut progress_rx = wifi_manager.subscribe();
+        let cancel = CancellationToken::new();
+        cancel.cancel();
+
+        let result = wifi_manager.apply_credentials(br#"{"ssid": "stage", "psswd": "secret123"}"#, Path::new("."), cancel).await;
+        assert!(matches!(result, Err(WifiManagerError::Cancelled)));
+        assert_eq!(progress_rx.recv().await.unwrap(), WifiProgress::Scanning);
+        assert!(matches!(wifi_manager.progress(), WifiProgress::Failed { .. }));
+    }
+}